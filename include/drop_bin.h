@@ -0,0 +1,34 @@
+#ifndef DROP_BIN_H
+#define DROP_BIN_H
+
+/* C interface to the drop-bin crate, available when built with the `ffi` feature, such as with
+ * `cargo rustc --release --features ffi --crate-type staticlib`.
+ *
+ * Functions that return `int` return 0 on success, or -1 if the bin panicked, such as because a
+ * destructor panicked and poisoned it. */
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef struct DropBin DropBin;
+
+/* Create a new bin. It must eventually be destroyed with `drop_bin_free`. */
+DropBin *drop_bin_new(void);
+
+/* Add a resource to the bin, to be destroyed later by calling `destructor(ptr)`, possibly on
+ * another thread. `destructor` must not be NULL. If this fails, the resource is left to the
+ * caller. */
+int drop_bin_add(const DropBin *bin, void *ptr, void (*destructor)(void *));
+
+/* Clear the bin, destroying all resources that have been previously added to it. */
+int drop_bin_clear(const DropBin *bin);
+
+/* Destroy the bin, along with all the resources in it. Passing NULL does nothing. */
+int drop_bin_free(DropBin *bin);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
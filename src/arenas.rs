@@ -0,0 +1,229 @@
+use crate::inner::Stats;
+use crate::slot_lock::ReadGuard;
+use crate::slot_lock::SlotLock;
+use crate::slot_lock::WriteGuard;
+use crate::sync::atomic::AtomicBool;
+use crate::sync::atomic::AtomicUsize;
+use crate::sync::atomic::Ordering;
+use crate::sync::const_fn;
+use crate::sync::Mutex;
+use crate::Inner;
+
+/// A bin's values, split between two arenas so that adds never wait for clears.
+///
+/// Values are added to the active arena. A clear makes the other arena active and then takes the
+/// values out of the one that was, so adds carry on in the new arena while it does. Until the
+/// clear has locked it, the old arena is *retired*: adds that started before the swap may still
+/// be using it, but no new ones will.
+#[derive(Debug, Default)]
+pub(crate) struct Arenas<'a> {
+    locks: [SlotLock<Inner<'a>>; 2],
+    /// The index of the arena that values are added to.
+    active: AtomicUsize,
+    /// Whether the inactive arena is retired and waiting to be cleared.
+    retired: AtomicBool,
+    /// Held while retiring an arena, so that two clears don't both swap the arenas.
+    retiring: Mutex<()>,
+}
+
+impl<'a> Arenas<'a> {
+    const_fn! {
+        pub(crate) fn new(first: Inner<'a>, second: Inner<'a>) -> Self {
+            Self {
+                locks: [SlotLock::new(first), SlotLock::new(second)],
+                active: AtomicUsize::new(0),
+                retired: AtomicBool::new(false),
+                retiring: Mutex::new(()),
+            }
+        }
+    }
+
+    /// Get shared access to the active arena to add values to it.
+    ///
+    /// This only fails if the whole bin is locked, or if clears swap the arenas twice while it
+    /// runs.
+    pub(crate) fn read(&self) -> Option<ReadGuard<'_, Inner<'a>>> {
+        for _ in 0..2 {
+            let index = self.active.load(Ordering::SeqCst);
+            let Some(guard) = self.locks[index].try_read() else {
+                continue;
+            };
+            // Either a clear that swaps the arenas from here on sees this reader and waits for
+            // it, or this sees the swap and moves on to the new arena.
+            if self.active.load(Ordering::SeqCst) == index {
+                return Some(guard);
+            }
+        }
+        None
+    }
+
+    /// Lock the retired arena to take its values out, first retiring the active one if there is
+    /// none and `clear` is set, which is then unset.
+    ///
+    /// Returns `None` if there is nothing to clear, or if adds are still using the retired arena
+    /// or another clear is retiring one.
+    pub(crate) fn lock_retired(&self, clear: &AtomicBool) -> Option<WriteGuard<'_, Inner<'a>>> {
+        let _retiring = self.retiring.try_lock().ok()?;
+        if !self.retired.load(Ordering::Relaxed) {
+            if !clear.swap(false, Ordering::Relaxed) {
+                return None;
+            }
+            let active = self.active.load(Ordering::Relaxed);
+            self.active.store(1 - active, Ordering::SeqCst);
+            self.retired.store(true, Ordering::Relaxed);
+        }
+
+        let retired = 1 - self.active.load(Ordering::Relaxed);
+        let guard = self.locks[retired].try_write()?;
+        self.retired.store(false, Ordering::Relaxed);
+        Some(guard)
+    }
+
+    /// Lock the retired arena, or else the active one, retiring it, without waiting for adds:
+    /// unlike `lock_retired`, this leaves the active arena alone if adds are using it.
+    ///
+    /// If adds are using the arena, the number of them is returned instead. `clear` is unset if
+    /// the active arena is retired, since that clears it.
+    pub(crate) fn try_lock(&self, clear: &AtomicBool) -> Result<WriteGuard<'_, Inner<'a>>, usize> {
+        let _retiring = self.retiring.try_lock().map_err(|_| 0_usize)?;
+        let active = self.active.load(Ordering::Relaxed);
+        let retired = self.retired.load(Ordering::Relaxed);
+        let index = if retired { 1 - active } else { active };
+
+        let guard = self.locks[index].try_write();
+        let guard = guard.ok_or_else(|| self.locks[index].readers())?;
+        if retired {
+            self.retired.store(false, Ordering::Relaxed);
+        } else {
+            // Adds that see the old arena locked retry in the new one.
+            self.active.store(1 - active, Ordering::SeqCst);
+            clear.store(false, Ordering::Relaxed);
+        }
+        Ok(guard)
+    }
+
+    /// Check whether an arena has been retired and is waiting to be cleared.
+    pub(crate) fn is_retired(&self) -> bool {
+        self.retired.load(Ordering::Relaxed)
+    }
+
+    /// Get exclusive access to all of the bin's values.
+    ///
+    /// The values are gathered into one arena: the retired one if there is one, so that the clear
+    /// waiting for it still drops them, and the active one otherwise.
+    pub(crate) fn try_write(&self) -> Option<WriteGuard<'_, Inner<'a>>> {
+        let active = self.active.load(Ordering::SeqCst);
+        let retired = self.retired.load(Ordering::Relaxed);
+        let target = if retired { 1 - active } else { active };
+
+        let mut into = self.locks[target].try_write()?;
+        let mut from = self.locks[1 - target].try_write()?;
+        gather(&mut into, &mut from, retired);
+        Some(into)
+    }
+
+    /// Get unique access to all of the bin's values, gathering them into one arena like
+    /// [`try_write`](Self::try_write).
+    pub(crate) fn get_mut(&mut self) -> &mut Inner<'a> {
+        let active = self.active.load(Ordering::Relaxed);
+        let retired = self.retired.load(Ordering::Relaxed);
+        let target = if retired { 1 - active } else { active };
+
+        let [first, second] = &mut self.locks;
+        let (into, from) = if target == 0 {
+            (first.get_mut(), second.get_mut())
+        } else {
+            (second.get_mut(), first.get_mut())
+        };
+        gather(into, from, retired);
+        into
+    }
+
+    /// Get the locks of both arenas.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &SlotLock<Inner<'a>>> {
+        self.locks.iter()
+    }
+
+    /// Summarize what the bin contains, or `None` if both arenas are locked.
+    pub(crate) fn stats(&self) -> Option<Stats> {
+        (self.iter())
+            .filter_map(|arena| Some(arena.try_read()?.stats()))
+            .reduce(Stats::merge)
+    }
+}
+
+/// Move the values in `from` into `into`, as newer values if `from_is_newer` is set and older
+/// values otherwise.
+fn gather<'a>(into: &mut Inner<'a>, from: &mut Inner<'a>, from_is_newer: bool) {
+    let panics = from.take_panics();
+    into.add_panics(panics);
+    if from.is_empty() {
+        return;
+    }
+    let detached = from.detach();
+    if from_is_newer {
+        into.append(detached);
+    } else {
+        into.reattach(detached);
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::arenas::Arenas;
+    use crate::sync::atomic::AtomicBool;
+
+    #[test]
+    fn arenas() {
+        let arenas = Arenas::default();
+        let clear = AtomicBool::new(true);
+
+        let read = arenas.read().unwrap();
+        read.add(5_u32);
+        // The add keeps the retired arena from being cleared, but not new adds.
+        assert!(arenas.lock_retired(&clear).is_none());
+        assert!(arenas.is_retired());
+        arenas.read().unwrap().add(6_u32);
+
+        drop(read);
+        let mut retired = arenas.lock_retired(&clear).unwrap();
+        assert!(!arenas.is_retired());
+        assert_eq!(retired.clear().values, 1);
+        drop(retired);
+
+        // Nothing else was asked to be cleared.
+        assert!(arenas.lock_retired(&clear).is_none());
+        let read = arenas.read().unwrap();
+        assert_eq!(arenas.try_lock(&clear).err(), Some(1));
+        drop(read);
+        assert_eq!(arenas.try_lock(&clear).unwrap().clear().values, 1);
+        assert!(!arenas.is_retired());
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use crate::arenas::Arenas;
+    use crate::sync::atomic::AtomicBool;
+    use loom::sync::Arc;
+    use loom::thread;
+
+    #[test]
+    fn swap() {
+        loom::model(|| {
+            let arenas = Arc::new(Arenas::default());
+
+            let thread = thread::spawn({
+                let arenas = arenas.clone();
+                move || arenas.read().map(|arena| arena.add(1_u32)).is_some()
+            });
+            let clear = AtomicBool::new(true);
+            let cleared = (arenas.lock_retired(&clear)).map_or(0, |mut arena| arena.clear().values);
+            let added = thread.join().unwrap();
+
+            // The value was either cleared or left for the next clear, which gets every arena.
+            let rest = arenas.try_write().unwrap().clear().values;
+            assert_eq!(cleared + rest, usize::from(added));
+        });
+    }
+}
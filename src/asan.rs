@@ -0,0 +1,40 @@
+//! Address sanitizer annotations for storage segments, enabled by the `asan` feature.
+//!
+//! Without the feature these do nothing. With it, the crate must be built with
+//! `-Zsanitizer=address` so that the sanitizer runtime is linked in.
+
+#[cfg(feature = "asan")]
+extern "C" {
+    fn __asan_poison_memory_region(addr: *const std::ffi::c_void, size: usize);
+    fn __asan_unpoison_memory_region(addr: *const std::ffi::c_void, size: usize);
+}
+
+/// Mark `len` bytes starting at `ptr` as inaccessible, so that any access to them is reported.
+///
+/// # Safety
+///
+/// The bytes must be part of a live allocation, and must not be accessed until they are
+/// [unpoisoned](unpoison) or the allocation is freed.
+#[cfg_attr(not(feature = "asan"), allow(unused_variables))]
+pub(crate) unsafe fn poison<T>(ptr: *const T, len: usize) {
+    // SAFETY: Upheld by the caller.
+    #[cfg(feature = "asan")]
+    unsafe {
+        __asan_poison_memory_region(ptr.cast(), len);
+    }
+}
+
+/// Mark `len` bytes starting at `ptr` as accessible again.
+///
+/// # Safety
+///
+/// The bytes must be part of a live allocation.
+#[cfg_attr(not(feature = "asan"), allow(unused_variables))]
+#[cfg_attr(feature = "boxed-storage", allow(dead_code))]
+pub(crate) unsafe fn unpoison<T>(ptr: *const T, len: usize) {
+    // SAFETY: Upheld by the caller.
+    #[cfg(feature = "asan")]
+    unsafe {
+        __asan_unpoison_memory_region(ptr.cast(), len);
+    }
+}
@@ -0,0 +1,121 @@
+use crate::inner::Cursor;
+use crate::slot_lock::ReadGuard;
+use crate::Bin;
+use crate::Inner;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::mem::size_of;
+use std::ptr::NonNull;
+
+/// A session for adding many values to a bin, created by [`Bin::batch`].
+///
+/// Clears of the values added through the guard wait until it is dropped, and so do the methods
+/// listed there, which must not be called on the thread that holds it.
+pub struct BinWriteGuard<'b, 'a> {
+    /// The storage last written to. This borrows from `inner`, so must be dropped first.
+    cursor: Cursor<'b>,
+    /// The read lock on the bin, or `None` if the bin was locked when the guard was created.
+    inner: Option<ReadGuard<'b, Inner<'a>>>,
+    bin: &'b Bin<'a>,
+}
+
+impl<'b, 'a> BinWriteGuard<'b, 'a> {
+    pub(crate) fn new(bin: &'b Bin<'a>, inner: Option<ReadGuard<'b, Inner<'a>>>) -> Self {
+        Self {
+            cursor: Cursor::default(),
+            inner,
+            bin,
+        }
+    }
+
+    /// Add a value to the bin.
+    ///
+    /// Like [`Bin::add`], this may drop the value immediately, such as once the bin has been
+    /// [sealed](Bin::seal), and panics if the bin has been [poisoned](Bin::is_poisoned). Values
+    /// added through a batch count towards the bin's [limit](crate::Builder::limit), but are never
+    /// held back by it.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub fn add<T: Send + 'a>(&mut self, value: T) {
+        self.bin.check_poisoned();
+        if self.bin.is_sealed() {
+            return;
+        }
+        if let Some(inner) = &self.inner {
+            // SAFETY: The `Inner` lives in `self.bin` rather than the guard, and the read lock
+            // keeps it from being cleared until after `self.cursor` has been dropped.
+            let inner = unsafe { NonNull::from(&**inner).as_ref() };
+            if let Some(limit) = &self.bin.limit {
+                limit.add(size_of::<T>());
+            }
+            if let Some(pacer) = &self.bin.pacer {
+                pacer.add(size_of::<T>());
+            }
+            inner.add_at(value, None, &mut self.cursor);
+        } else {
+            // Just drop the value if the bin is locked.
+        }
+    }
+}
+
+impl Debug for BinWriteGuard<'_, '_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BinWriteGuard").finish_non_exhaustive()
+    }
+}
+
+impl Drop for BinWriteGuard<'_, '_> {
+    fn drop(&mut self) {
+        self.cursor = Cursor::default();
+        self.inner = None;
+        self.bin.added();
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::test_util::CallOnDrop;
+    use crate::Bin;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::thread;
+
+    #[test]
+    fn batch() {
+        let destructors_called = AtomicUsize::new(0);
+
+        let bin = Bin::new();
+
+        let mut batch = bin.batch();
+        for i in 0..1000 {
+            batch.add(i);
+            batch.add(CallOnDrop(|| {
+                destructors_called.fetch_add(1, SeqCst);
+            }));
+        }
+
+        // Clearing is deferred until the batch ends.
+        bin.clear();
+        assert_eq!(destructors_called.load(SeqCst), 0);
+        drop(batch);
+        assert_eq!(destructors_called.load(SeqCst), 1000);
+    }
+
+    #[test]
+    fn sealed() {
+        let dropped = AtomicUsize::new(0);
+        let bin = Bin::new();
+
+        thread::scope(|s| {
+            let mut batch = bin.batch();
+            // Sealing waits for the batch to end, but values added meanwhile are dropped.
+            s.spawn(|| bin.seal());
+            while !bin.is_sealed() {
+                thread::yield_now();
+            }
+            batch.add(CallOnDrop(|| _ = dropped.fetch_add(1, SeqCst)));
+            assert_eq!(dropped.load(SeqCst), 1);
+        });
+        assert_eq!(bin.estimate_clear_cost().items, 0);
+    }
+}
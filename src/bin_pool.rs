@@ -0,0 +1,280 @@
+use crate::Bin;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::mem;
+use std::ops::Deref;
+use std::sync::Arc;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+use std::thread;
+use std::thread::JoinHandle;
+
+/// A pool of bins that are reused, for bins that only live as long as a request or connection.
+///
+/// [`get`](Self::get) hands out an empty bin, and when the returned [`PooledBin`] is dropped the
+/// bin goes back to the pool. The pool's worker thread clears it there, so that neither making a
+/// bin, clearing it nor allocating its storage happens on the hot path. Up to `max_idle` cleared
+/// bins are kept, with their storage, for the next requests.
+///
+/// # Example
+///
+/// ```
+/// use drop_bin::BinPool;
+///
+/// let pool = BinPool::new(16);
+///
+/// let bin = pool.get();
+/// bin.add(vec![0_u8; 4096]);
+/// // The bin is cleared on the pool's worker thread.
+/// drop(bin);
+/// ```
+pub struct BinPool {
+    shared: Arc<Shared>,
+    worker: Option<JoinHandle<()>>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    /// Notified when a bin is returned or the pool is stopped.
+    returned: Condvar,
+    max_idle: usize,
+    make: fn() -> Bin<'static>,
+}
+
+struct State {
+    /// Bins that have been cleared and are ready to be handed out.
+    idle: Vec<Bin<'static>>,
+    /// Bins that have been given back, but not cleared yet.
+    returned: VecDeque<Bin<'static>>,
+    stopped: bool,
+}
+
+impl BinPool {
+    /// Start a pool that keeps up to `max_idle` cleared bins around.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the worker thread could not be spawned.
+    #[must_use]
+    pub fn new(max_idle: usize) -> Self {
+        Self::with_factory(max_idle, Bin::new)
+    }
+
+    /// Start a pool like [`new`](Self::new) that makes its bins with `make`, such as to build them
+    /// with custom settings.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the worker thread could not be spawned.
+    #[must_use]
+    pub fn with_factory(max_idle: usize, make: fn() -> Bin<'static>) -> Self {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                idle: Vec::new(),
+                returned: VecDeque::new(),
+                stopped: false,
+            }),
+            returned: Condvar::new(),
+            max_idle,
+            make,
+        });
+        let worker = thread::Builder::new()
+            .name("drop-bin-pool".to_owned())
+            .spawn({
+                let shared = shared.clone();
+                move || shared.work()
+            })
+            .expect("failed to spawn bin pool thread");
+
+        Self {
+            shared,
+            worker: Some(worker),
+        }
+    }
+
+    /// Take an empty bin from the pool, making a new one if none are idle.
+    #[must_use]
+    pub fn get(&self) -> PooledBin {
+        let bin = self.shared.lock().idle.pop();
+        PooledBin {
+            bin: Some(bin.unwrap_or_else(self.shared.make)),
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Get the number of cleared bins that are ready to be handed out.
+    #[must_use]
+    pub fn idle(&self) -> usize {
+        self.shared.lock().idle.len()
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn work(&self) {
+        let mut state = self.lock();
+        loop {
+            if state.stopped {
+                // Bins given back before the pool stopped are dropped here, like any others.
+                let returned = mem::take(&mut state.returned);
+                drop(state);
+                drop(returned);
+                return;
+            }
+            let Some(mut bin) = state.returned.pop_front() else {
+                state = (self.returned.wait(state)).unwrap_or_else(PoisonError::into_inner);
+                continue;
+            };
+            drop(state);
+
+            // Bins that can't be used again are dropped instead.
+            let reusable = !bin.is_poisoned() && !bin.is_sealed();
+            if reusable {
+                bin.force_clear();
+                bin.reset();
+            }
+
+            state = self.lock();
+            if reusable && state.idle.len() < self.max_idle {
+                state.idle.push(bin);
+                continue;
+            }
+            // The bin's values are dropped with it, so the lock isn't held meanwhile.
+            drop(state);
+            drop(bin);
+            state = self.lock();
+        }
+    }
+}
+
+impl Debug for BinPool {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let state = self.shared.lock();
+        f.debug_struct("BinPool")
+            .field("idle", &state.idle.len())
+            .field("returned", &state.returned.len())
+            .field("max_idle", &self.shared.max_idle)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for BinPool {
+    fn drop(&mut self) {
+        self.shared.lock().stopped = true;
+        self.shared.returned.notify_all();
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// A bin taken from a [`BinPool`], which goes back to the pool when this is dropped.
+pub struct PooledBin {
+    /// The bin, until it is given back.
+    bin: Option<Bin<'static>>,
+    shared: Arc<Shared>,
+}
+
+impl Deref for PooledBin {
+    type Target = Bin<'static>;
+
+    fn deref(&self) -> &Self::Target {
+        self.bin.as_ref().unwrap()
+    }
+}
+
+impl Debug for PooledBin {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&**self, f)
+    }
+}
+
+impl Drop for PooledBin {
+    fn drop(&mut self) {
+        let Some(bin) = self.bin.take() else {
+            return;
+        };
+        let mut state = self.shared.lock();
+        if state.stopped {
+            // Nothing will clear the bin, so it is cleared by being dropped here.
+            drop(state);
+            drop(bin);
+            return;
+        }
+        state.returned.push_back(bin);
+        drop(state);
+        self.shared.returned.notify_one();
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::test_util::CallOnDrop;
+    use crate::BinPool;
+    use crate::DropBehavior;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn reuses_bins() {
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        let pool = BinPool::new(1);
+        let bin = pool.get();
+        bin.add(CallOnDrop(|| _ = DROPPED.fetch_add(1, SeqCst)));
+        drop(bin);
+
+        for _ in 0..1000 {
+            if pool.idle() == 1 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(pool.idle(), 1);
+        assert_eq!(DROPPED.load(SeqCst), 1);
+
+        let bin = pool.get();
+        assert_eq!(pool.idle(), 0);
+        assert_eq!(bin.estimate_clear_cost().items, 0);
+    }
+
+    #[test]
+    fn resets_bins() {
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        let pool = BinPool::new(1);
+        let bin = pool.get();
+        let cleared = bin.subscribe_cleared();
+        bin.set_drop_behavior(DropBehavior::Leak);
+        bin.clear();
+        drop(bin);
+
+        // The subscription ends once the bin has been reset.
+        while cleared.recv().is_ok() {}
+        for _ in 0..1000 {
+            if pool.idle() == 1 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        // The next user doesn't see the last one's clears or settings.
+        let bin = pool.get();
+        assert_eq!(bin.epoch(), 0);
+        assert!(bin.recent_clears().is_empty());
+        bin.add(CallOnDrop(|| _ = DROPPED.fetch_add(1, SeqCst)));
+        // With the pool stopped, the bin is dropped as it is given back.
+        drop(pool);
+        drop(bin);
+        assert_eq!(DROPPED.load(SeqCst), 1);
+    }
+}
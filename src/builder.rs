@@ -0,0 +1,372 @@
+use crate::drop_on_new_thread;
+#[cfg(feature = "memory-stats")]
+use crate::rss::RssTrigger;
+use crate::sync::const_fn;
+#[cfg(feature = "watchdog")]
+use crate::watchdog::Watchdog;
+use crate::Arenas;
+use crate::Bin;
+use crate::ClearReport;
+use crate::Debounce;
+use crate::GrowthMonitor;
+use crate::GrowthWarning;
+#[cfg(feature = "watchdog")]
+use crate::HungDestructor;
+use crate::Incremental;
+use crate::Inner;
+use crate::Limit;
+use crate::Overflow;
+use crate::Pacer;
+use crate::PanicPolicy;
+use crate::SegmentAlloc;
+use crate::Shrinker;
+use crate::Slicer;
+use std::time::Duration;
+
+/// A builder for a [`Bin`] with custom settings.
+///
+/// # Example
+///
+/// ```
+/// static BIN: drop_bin::Bin<'static> = drop_bin::Bin::builder()
+///     .max_segment_size(1024 * 1024)
+///     .build();
+///
+/// BIN.add(vec![1, 2, 3]);
+/// ```
+#[derive(Debug)]
+#[must_use]
+pub struct Builder {
+    max_segment_size: usize,
+    trim_after_clear: Option<(usize, fn())>,
+    on_clear: Option<fn(&ClearReport)>,
+    #[cfg(feature = "memory-stats")]
+    clear_above_rss: Option<(usize, usize)>,
+    limit: Option<(usize, Overflow)>,
+    pace: Option<(usize, Duration)>,
+    incremental: Option<(usize, usize)>,
+    max_pause: Option<Duration>,
+    shrink_below: Option<(f64, u32)>,
+    min_clear_interval: Option<Duration>,
+    growth: Option<GrowthMonitor>,
+    on_segment_alloc: Option<fn(&SegmentAlloc)>,
+    on_panic: PanicPolicy,
+    #[cfg(feature = "decommit")]
+    decommit: bool,
+    #[cfg(feature = "watchdog")]
+    watchdog: Option<Watchdog>,
+}
+
+impl Builder {
+    const_fn! {
+        /// Create a new builder with the default settings.
+        pub fn new() -> Self {
+            Self {
+                max_segment_size: usize::MAX,
+                trim_after_clear: None,
+                on_clear: None,
+                #[cfg(feature = "memory-stats")]
+                clear_above_rss: None,
+                limit: None,
+                pace: None,
+                incremental: None,
+                max_pause: None,
+                shrink_below: None,
+                min_clear_interval: None,
+                growth: None,
+                on_segment_alloc: None,
+                on_panic: PanicPolicy::Propagate,
+                #[cfg(feature = "decommit")]
+                decommit: false,
+                #[cfg(feature = "watchdog")]
+                watchdog: None,
+            }
+        }
+    }
+
+    const_fn! {
+        /// Set the maximum size in bytes of the segments that values are stored in.
+        ///
+        /// Each segment the bin allocates is twice the size of the last one, up to this limit;
+        /// after that, additional segments of this size are allocated instead. Values larger than
+        /// the limit, or than 64 KiB, are given a segment of their own that is freed once they
+        /// are dropped. By default there is no limit.
+        pub fn max_segment_size(mut self, max_segment_size: usize) -> Self {
+            self.max_segment_size = max_segment_size;
+            self
+        }
+    }
+
+    const_fn! {
+        /// Call `trim` after every clear that dropped more than `threshold` bytes of values.
+        ///
+        /// Memory freed by destructors often stays in the allocator rather than being returned to
+        /// the operating system, so this is a place to ask it to give the memory back. The
+        /// [`trim`](crate::trim) module has hooks for common allocators.
+        pub fn trim_after_clear(mut self, threshold: usize, trim: fn()) -> Self {
+            self.trim_after_clear = Some((threshold, trim));
+            self
+        }
+    }
+
+    const_fn! {
+        /// Call `on_clear` with a report of every clear, using the same notion of a clear as
+        /// [`Bin::epoch`].
+        ///
+        /// The hook runs on the thread that cleared the bin, after the bin has been unlocked. With
+        /// the `drop-histogram` feature, the report includes how long each destructor took.
+        pub fn on_clear(mut self, on_clear: fn(&ClearReport)) -> Self {
+            self.on_clear = Some(on_clear);
+            self
+        }
+    }
+
+    #[cfg(feature = "memory-stats")]
+    const_fn! {
+        /// Clear the bin whenever the process's resident set size is above `limit` bytes, checking
+        /// it once every `every` adds.
+        ///
+        /// Measuring the RSS is a system call on most platforms, so `every` should be large enough
+        /// to amortize it. Enabled by the `memory-stats` feature.
+        pub fn clear_above_rss(mut self, limit: usize, every: usize) -> Self {
+            self.clear_above_rss = Some((limit, every));
+            self
+        }
+    }
+
+    const_fn! {
+        /// Cap the bin at `max` bytes of values, with `overflow` deciding what happens to values
+        /// added beyond it.
+        ///
+        /// The cap counts the sizes of the values themselves, not memory they own on the heap, and
+        /// is reset whenever the bin is emptied. Values larger than the cap are still let into an
+        /// empty bin.
+        pub fn limit(mut self, max: usize, overflow: Overflow) -> Self {
+            self.limit = Some((max, overflow));
+            self
+        }
+    }
+
+    const_fn! {
+        /// Clear the bin automatically to keep the bytes of values in it near `target`, leaving
+        /// at least `min_interval` between clears.
+        ///
+        /// The bin watches how quickly values are added and how long clears take, and starts each
+        /// clear early enough that the values added while it runs don't take the bin far past the
+        /// target. Clears are also spaced out so that they take up at most a quarter of the time.
+        /// Like [`limit`](Self::limit), this counts the sizes of the values themselves.
+        pub fn pace(mut self, target: usize, min_interval: Duration) -> Self {
+            self.pace = Some((target, min_interval));
+            self
+        }
+    }
+
+    const_fn! {
+        /// Drop up to `budget` values every `every` adds, so that the bin is emptied gradually
+        /// without calling [`clear`](Bin::clear).
+        ///
+        /// Like an incremental garbage collector, this spreads the work of clearing over the adds.
+        /// When the last batch has been dropped, the bin's values are taken out as the next batch,
+        /// which takes time proportional to how many there are; the values are then dropped
+        /// `budget` at a time. As long as `budget` is more than `every`, values are dropped faster
+        /// than they are added, so the backlog stays small. Each step advances the
+        /// [epoch](Bin::epoch).
+        pub fn incremental(mut self, every: usize, budget: usize) -> Self {
+            self.incremental = Some((every, budget));
+            self
+        }
+    }
+
+    const_fn! {
+        /// Split clears into slices that each take about `target`, such as a millisecond, so that
+        /// no single call to [`clear`](Bin::clear) pauses for much longer than that.
+        ///
+        /// The bin learns how long its values take to drop from the slices it has run, and sizes
+        /// each slice to fit the target. The values a slice doesn't get to are dropped by the
+        /// clears that follow, such as those of a [`CleanerPool`](crate::CleanerPool), before any
+        /// values added since are taken out. Each slice advances the [epoch](Bin::epoch). Taking
+        /// the values out of the bin still takes time proportional to how many there are.
+        pub fn max_pause(mut self, target: Duration) -> Self {
+            self.max_pause = Some(target);
+            self
+        }
+    }
+
+    const_fn! {
+        /// Free storage once `clears` clears in a row have found less than `utilization` of it in
+        /// use, such as `0.25` for a quarter.
+        ///
+        /// This right-sizes bins with bursty workloads: the storage left over from a burst is
+        /// kept through short lulls, but given back to the allocator if they go on. Enough
+        /// storage is kept to hold the most that any of those clears found in use, and the
+        /// largest storages are freed first.
+        pub fn shrink_below(mut self, utilization: f64, clears: u32) -> Self {
+            self.shrink_below = Some((utilization, clears));
+            self
+        }
+    }
+
+    const_fn! {
+        /// Leave at least `interval` between clears, so that many callers clearing the bin out of
+        /// habit don't make it thrash.
+        ///
+        /// A call to [`clear`](Bin::clear) that comes too soon after the last clear is put off,
+        /// and clears put off like this are coalesced into one that runs on the first add after
+        /// the interval is up. [`force_clear`](Bin::force_clear) clears the bin regardless, as do
+        /// a [`Coordinator`](crate::Coordinator) and a [`CleanerPool`](crate::CleanerPool), which
+        /// decide for themselves when to clear.
+        pub fn min_clear_interval(mut self, interval: Duration) -> Self {
+            self.min_clear_interval = Some(interval);
+            self
+        }
+    }
+
+    const_fn! {
+        /// Call `on_growth` when the bin has gone on growing for longer than `window` without
+        /// being cleared, which usually means that nothing is clearing it.
+        ///
+        /// The bin checks this every 64 adds, so it is cheap, and calls `on_growth` once for each
+        /// stretch of growth on the thread that added a value. Any clear that advances the
+        /// [epoch](Bin::epoch), or a shrink in the [bytes used](Bin::bytes_used), starts a new
+        /// stretch.
+        pub fn warn_on_growth(mut self, window: Duration, on_growth: fn(&GrowthWarning)) -> Self {
+            self.growth = Some(GrowthMonitor::new(window, on_growth));
+            self
+        }
+    }
+
+    const_fn! {
+        /// Call `on_segment_alloc` whenever the bin allocates a new storage segment, with the
+        /// segment's size and the size of the value that needed it, so that unexpected growth
+        /// shows up as it happens.
+        ///
+        /// Emptied segments that are reused aren't reported. The callback runs on the thread
+        /// that added the value, while the bin is locked, so it must not use the bin. With the
+        /// `boxed-storage` feature, values don't go in segments and it is never called.
+        pub fn on_segment_alloc(mut self, on_segment_alloc: fn(&SegmentAlloc)) -> Self {
+            self.on_segment_alloc = Some(on_segment_alloc);
+            self
+        }
+    }
+
+    const_fn! {
+        /// Set what clears do when a destructor panics. By default, the panic propagates.
+        pub fn on_panic(mut self, on_panic: PanicPolicy) -> Self {
+            self.on_panic = on_panic;
+            self
+        }
+    }
+
+    #[cfg(feature = "decommit")]
+    const_fn! {
+        /// Tell the operating system after every clear that the pages of the emptied storage are
+        /// disposable, so that the process's resident memory drops even though the storage is
+        /// kept for reuse. Enabled by the `decommit` feature.
+        ///
+        /// The pages are faulted back in, zeroed, as values are added to them again, so this
+        /// trades some time on later adds for memory between clears. On Windows the pages are
+        /// reset rather than decommitted, since the allocator owns them.
+        pub fn decommit_after_clear(mut self) -> Self {
+            self.decommit = true;
+            self
+        }
+    }
+
+    #[cfg(feature = "watchdog")]
+    const_fn! {
+        /// Call `on_hung` when a single destructor has been running for longer than `limit`
+        /// during a clear, such as one blocked on the network. Enabled by the `watchdog` feature.
+        ///
+        /// Destructors are watched by a background thread shared by every bin, which calls
+        /// `on_hung` once for each destructor that goes over the limit, while it is still
+        /// running. The callback runs on that thread, so it shouldn't block for long.
+        pub fn watchdog(mut self, limit: Duration, on_hung: fn(&HungDestructor)) -> Self {
+            self.watchdog = Some(Watchdog { limit, on_hung });
+            self
+        }
+    }
+
+    const_fn! {
+        /// Create the bin.
+        #[must_use]
+        pub fn build<'a>(self) -> Bin<'a> {
+            let arenas = Arenas::new(self.inner(), self.inner());
+            let incremental = match self.incremental {
+                Some((every, budget)) => Some(Incremental::new(every, budget)),
+                None => None,
+            };
+            let slicer = match self.max_pause {
+                Some(target) => Some(Slicer::new(target)),
+                None => None,
+            };
+            let mut bin = Bin::from_arenas(arenas, incremental, slicer);
+            bin.trim_after_clear = self.trim_after_clear;
+            bin.on_clear = self.on_clear;
+            #[cfg(feature = "memory-stats")]
+            if let Some((limit, every)) = self.clear_above_rss {
+                bin.rss_trigger = Some(RssTrigger::new(limit, every));
+            }
+            if let Some((max, overflow)) = self.limit {
+                bin.limit = Some(Limit::new(max, overflow));
+            }
+            if let Some((target, min_interval)) = self.pace {
+                bin.pacer = Some(Pacer::new(target, min_interval));
+            }
+            if let Some((utilization, clears)) = self.shrink_below {
+                bin.shrinker = Some(Shrinker::new(utilization, clears));
+            }
+            if let Some(interval) = self.min_clear_interval {
+                bin.debounce = Some(Debounce::new(interval));
+            }
+            bin.growth = self.growth;
+            bin
+        }
+    }
+
+    const_fn! {
+        /// Create the bin like [`build`](Self::build), making clears of more than `threshold`
+        /// values drop them on a new thread, like
+        /// [`clear_in_background`](Bin::clear_in_background), instead of on the thread that
+        /// cleared the bin. Only bins of `'static` values can send them to another thread.
+        ///
+        /// This keeps small clears cheap while getting large ones off the caller. An offloaded
+        /// clear counts as finished once the values have been handed to the thread: it advances
+        /// the [`epoch`](Bin::epoch) and is reported to [`on_clear`](Self::on_clear) with no
+        /// duration, while the destructors may still be running. Their storage is freed rather
+        /// than reused. If the thread can't be spawned, the values are dropped on the current
+        /// thread instead.
+        #[must_use]
+        pub fn build_offloading_above(self, threshold: usize) -> Bin<'static> {
+            let mut bin = self.build();
+            bin.offload = Some((threshold, drop_on_new_thread));
+            bin
+        }
+    }
+}
+
+impl Builder {
+    const_fn! {
+        /// Create the contents of one of the bin's arenas.
+        fn inner<'a>(&self) -> Inner<'a> {
+            let mut inner = Inner::new();
+            inner.max_segment_size = self.max_segment_size;
+            inner.on_panic = self.on_panic;
+            inner.on_segment_alloc = self.on_segment_alloc;
+            #[cfg(feature = "decommit")]
+            {
+                inner.decommit = self.decommit;
+            }
+            #[cfg(feature = "watchdog")]
+            {
+                inner.watchdog = self.watchdog;
+            }
+            inner
+        }
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
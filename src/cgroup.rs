@@ -0,0 +1,157 @@
+//! Clearing bins as the process's cgroup approaches its memory limit, enabled by the `cgroup`
+//! feature.
+//!
+//! Containerized workloads are killed for exceeding their cgroup's limit long before the host as
+//! a whole runs low on memory, so this watches the limit that actually matters. Only cgroup v2 is
+//! supported.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use drop_bin::cgroup::CgroupWatcher;
+//! use drop_bin::Bin;
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//!
+//! let bin = Arc::new(Bin::new());
+//! // Clear the bin whenever the cgroup is using more than 90% of its limit.
+//! let _watcher = CgroupWatcher::new(bin.clone(), 0.9, Duration::from_secs(1))?;
+//! # Ok::<_, std::io::Error>(())
+//! ```
+
+use crate::Bin;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// The memory usage of a cgroup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CgroupMemory {
+    /// The number of bytes currently in use, from `memory.current`.
+    pub current: u64,
+    /// The lower of `memory.high` and `memory.max`, or `None` if neither is set.
+    pub limit: Option<u64>,
+}
+
+impl CgroupMemory {
+    /// Read the memory usage of the current process's cgroup.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the process is not in a cgroup v2 hierarchy, or its files could not be read.
+    pub fn read() -> io::Result<Self> {
+        Self::read_from(&cgroup_dir()?)
+    }
+
+    fn read_from(dir: &Path) -> io::Result<Self> {
+        let read = |name| fs::read_to_string(dir.join(name));
+
+        let current = read("memory.current")?
+            .trim()
+            .parse()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let limit = [read("memory.high"), read("memory.max")]
+            .iter()
+            .filter_map(|limit| parse_limit(limit.as_ref().ok()?))
+            .min();
+
+        Ok(Self { current, limit })
+    }
+}
+
+/// Clears a bin whenever the process's cgroup is close to its memory limit.
+///
+/// The cgroup is polled by a background thread, which stops when this is dropped.
+#[derive(Debug)]
+pub struct CgroupWatcher {
+    stop: Arc<AtomicBool>,
+}
+
+impl CgroupWatcher {
+    /// Start clearing the bin whenever the cgroup's usage is above `threshold` times its limit,
+    /// checking every `interval`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the cgroup's memory usage can't be read, or the background thread could not be
+    /// spawned.
+    pub fn new(bin: Arc<Bin<'static>>, threshold: f64, interval: Duration) -> io::Result<Self> {
+        let dir = cgroup_dir()?;
+        CgroupMemory::read_from(&dir)?;
+
+        let stop = Arc::new(AtomicBool::new(false));
+        thread::Builder::new()
+            .name("drop-bin-cgroup".to_owned())
+            .spawn({
+                let stop = stop.clone();
+                move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        if let Ok(memory) = CgroupMemory::read_from(&dir) {
+                            if memory.limit.is_some_and(|limit| {
+                                #[allow(clippy::cast_precision_loss)]
+                                let ratio = memory.current as f64 / limit as f64;
+                                ratio > threshold
+                            }) {
+                                bin.clear();
+                            }
+                        }
+                        thread::sleep(interval);
+                    }
+                }
+            })?;
+
+        Ok(Self { stop })
+    }
+}
+
+impl Drop for CgroupWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Find the directory of the current process's cgroup.
+fn cgroup_dir() -> io::Result<PathBuf> {
+    let cgroups = fs::read_to_string("/proc/self/cgroup")?;
+    let path = parse_cgroup_path(&cgroups).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "process is not in a cgroup v2 hierarchy",
+        )
+    })?;
+    Ok(PathBuf::from("/sys/fs/cgroup").join(path.trim_start_matches('/')))
+}
+
+/// Get the cgroup v2 path out of the contents of `/proc/<pid>/cgroup`.
+fn parse_cgroup_path(cgroups: &str) -> Option<&str> {
+    cgroups.lines().find_map(|line| line.strip_prefix("0::"))
+}
+
+/// Parse a memory limit file, which is `max` when there is no limit.
+fn parse_limit(limit: &str) -> Option<u64> {
+    limit.trim().parse().ok()
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::cgroup::parse_cgroup_path;
+    use crate::cgroup::parse_limit;
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            parse_cgroup_path("12:pids:/foo\n0::/user.slice/app.scope\n"),
+            Some("/user.slice/app.scope")
+        );
+        assert_eq!(parse_cgroup_path("12:pids:/foo\n"), None);
+
+        assert_eq!(parse_limit("1073741824\n"), Some(1 << 30));
+        assert_eq!(parse_limit("max\n"), None);
+    }
+}
@@ -0,0 +1,132 @@
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::sync::mpsc;
+
+/// A value sent to a cleaner.
+type Garbage<'a> = Box<dyn Send + 'a>;
+
+/// Create a bin split into producer and consumer halves.
+///
+/// Unlike [`Bin`](crate::Bin), adding a value never contends with clearing: senders push values
+/// onto a lock-free queue, and the single cleaner drops whatever has been queued when it is
+/// cleared. Each value is boxed, so this is best suited to values that are expensive to drop
+/// rather than many small ones.
+///
+/// # Example
+///
+/// ```
+/// let (sender, mut cleaner) = drop_bin::channel();
+///
+/// let sender2 = sender.clone();
+/// std::thread::spawn(move || sender2.add(vec![1, 2, 3])).join().unwrap();
+/// sender.add("Hello World!".to_owned());
+///
+/// cleaner.clear();
+/// // Both values' destructors have been run.
+/// ```
+#[must_use]
+pub fn channel<'a>() -> (BinSender<'a>, BinCleaner<'a>) {
+    let (sender, receiver) = mpsc::channel();
+    (BinSender { sender }, BinCleaner { receiver })
+}
+
+/// The producer half of a [`channel`], which can be cloned and shared between threads.
+pub struct BinSender<'a> {
+    sender: mpsc::Sender<Garbage<'a>>,
+}
+
+impl<'a> BinSender<'a> {
+    /// Add a value to be dropped by the cleaner.
+    ///
+    /// This never blocks. If the cleaner has been dropped, the value is dropped immediately.
+    pub fn add<T: Send + 'a>(&self, value: T) {
+        // The value is returned and dropped if the cleaner is gone.
+        let _ = self.sender.send(Box::new(value));
+    }
+}
+
+impl Clone for BinSender<'_> {
+    fn clone(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+impl Debug for BinSender<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BinSender").finish_non_exhaustive()
+    }
+}
+
+/// The consumer half of a [`channel`], which drops the values that have been sent to it.
+///
+/// Any values still queued are dropped along with the cleaner.
+pub struct BinCleaner<'a> {
+    receiver: mpsc::Receiver<Garbage<'a>>,
+}
+
+impl BinCleaner<'_> {
+    /// Drop all the values that have been added so far.
+    ///
+    /// Returns the number of values dropped.
+    pub fn clear(&mut self) -> usize {
+        self.receiver.try_iter().map(drop).count()
+    }
+}
+
+impl Debug for BinCleaner<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BinCleaner").finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::channel::BinCleaner;
+    use crate::channel::BinSender;
+    use crate::test_util::assert_thread_safe;
+    use crate::test_util::CallOnDrop;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::thread;
+
+    #[test]
+    fn channel() {
+        let dropped = AtomicUsize::new(0);
+        let (sender, mut cleaner) = crate::channel();
+
+        thread::scope(|s| {
+            for _ in 0..4 {
+                let sender = sender.clone();
+                let dropped = &dropped;
+                s.spawn(move || {
+                    for _ in 0..10 {
+                        sender.add(CallOnDrop(|| {
+                            dropped.fetch_add(1, SeqCst);
+                        }));
+                    }
+                });
+            }
+        });
+        assert_eq!(dropped.load(SeqCst), 0);
+
+        assert_eq!(cleaner.clear(), 40);
+        assert_eq!(dropped.load(SeqCst), 40);
+
+        drop(cleaner);
+        sender.add(CallOnDrop(|| {
+            dropped.fetch_add(1, SeqCst);
+        }));
+        assert_eq!(dropped.load(SeqCst), 41);
+    }
+
+    #[test]
+    #[allow(clippy::extra_unused_lifetimes)]
+    fn thread_safe<'a>() {
+        fn assert_send<T: Send>() {}
+        assert_thread_safe::<BinSender<'a>>();
+        assert_send::<BinCleaner<'a>>();
+    }
+}
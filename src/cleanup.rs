@@ -0,0 +1,262 @@
+use crate::sync::const_fn;
+use crate::sync::Mutex;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::future::poll_fn;
+use std::future::Future;
+use std::mem;
+use std::pin::Pin;
+use std::sync::PoisonError;
+use std::task::Poll;
+
+/// An asynchronous cleanup added to a bin.
+type Cleanup<'a> = Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
+
+/// The asynchronous cleanups waiting for the next async clear.
+///
+/// These are kept outside of the bin's lock so that adding one never fails, as dropping a future
+/// in place of running it would cancel it.
+pub(crate) struct Cleanups<'a>(Mutex<Vec<Cleanup<'a>>>);
+
+impl<'a> Cleanups<'a> {
+    const_fn! {
+        pub(crate) fn new() -> Self {
+            Self(Mutex::new(Vec::new()))
+        }
+    }
+
+    pub(crate) fn push(&self, cleanup: impl Future<Output = ()> + Send + 'a) {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Box::pin(cleanup));
+    }
+
+    /// Run all the cleanups that have been added so far to completion, concurrently.
+    ///
+    /// If the returned future is dropped first, the cleanups that haven't finished are put back
+    /// to be run by the next call.
+    pub(crate) async fn run(&self) {
+        let mut running = Running {
+            cleanups: self,
+            pending: mem::take(&mut *self.0.lock().unwrap_or_else(PoisonError::into_inner)),
+        };
+
+        poll_fn(|cx| {
+            (running.pending).retain_mut(|cleanup| cleanup.as_mut().poll(cx).is_pending());
+            if running.pending.is_empty() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await;
+    }
+}
+
+/// Cleanups taken out of a bin to be run, which are put back if they are cancelled.
+struct Running<'c, 'a> {
+    cleanups: &'c Cleanups<'a>,
+    pending: Vec<Cleanup<'a>>,
+}
+
+impl Drop for Running<'_, '_> {
+    fn drop(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let mut cleanups = self
+            .cleanups
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        // Keep them ahead of any cleanups added since.
+        cleanups.splice(0..0, self.pending.drain(..));
+    }
+}
+
+impl Default for Cleanups<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for Cleanups<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let len = self.0.lock().map_or(0, |cleanups| cleanups.len());
+        f.debug_struct("Cleanups").field("len", &len).finish()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::test_util::CallOnDrop;
+    use crate::Bin;
+    use std::future::Future;
+    use std::pin::pin;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::task::Context;
+    use std::task::Poll;
+    use std::task::Waker;
+
+    #[test]
+    fn clear_async() {
+        let polls = AtomicUsize::new(0);
+
+        let bin = Bin::new();
+        for _ in 0..2 {
+            bin.add_async(std::future::poll_fn(|cx| {
+                // Finish on the second poll.
+                if polls.fetch_add(1, SeqCst) < 2 {
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                } else {
+                    Poll::Ready(())
+                }
+            }));
+        }
+        assert_eq!(polls.load(SeqCst), 0);
+
+        // Plain clears leave asynchronous cleanups alone.
+        bin.clear();
+        assert_eq!(polls.load(SeqCst), 0);
+
+        let mut clear = pin!(bin.clear_async());
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(clear.as_mut().poll(&mut cx).is_pending());
+        assert!(clear.as_mut().poll(&mut cx).is_ready());
+        assert_eq!(polls.load(SeqCst), 4);
+    }
+
+    #[test]
+    fn clear_async_cancelled() {
+        let polls = AtomicUsize::new(0);
+
+        let bin = Bin::new();
+        bin.add_async(std::future::poll_fn(|_| {
+            // Finish on the second poll.
+            if polls.fetch_add(1, SeqCst) < 1 {
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
+        }));
+
+        let mut cx = Context::from_waker(Waker::noop());
+        {
+            let mut clear = pin!(bin.clear_async());
+            assert!(clear.as_mut().poll(&mut cx).is_pending());
+        }
+        assert_eq!(polls.load(SeqCst), 1);
+
+        // The unfinished cleanup is run by the next clear rather than cancelled.
+        let mut clear = pin!(bin.clear_async());
+        assert!(clear.as_mut().poll(&mut cx).is_ready());
+        assert_eq!(polls.load(SeqCst), 2);
+    }
+
+    #[test]
+    fn clear_yielding() {
+        let dropped = AtomicUsize::new(0);
+        let bin = Bin::new();
+        for _ in 0..5 {
+            bin.add(CallOnDrop(|| {
+                dropped.fetch_add(1, SeqCst);
+            }));
+        }
+
+        let mut clear = pin!(bin.clear_yielding(2));
+        let mut cx = Context::from_waker(Waker::noop());
+        assert!(clear.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(dropped.load(SeqCst), 2);
+        assert!(clear.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(dropped.load(SeqCst), 4);
+        assert!(clear.as_mut().poll(&mut cx).is_ready());
+        assert_eq!(dropped.load(SeqCst), 5);
+        assert_eq!(bin.epoch(), 1);
+    }
+
+    #[test]
+    fn clear_yielding_cancelled() {
+        let dropped = AtomicUsize::new(0);
+        let bin = Bin::new();
+        for _ in 0..5 {
+            bin.add(CallOnDrop(|| {
+                dropped.fetch_add(1, SeqCst);
+            }));
+        }
+
+        {
+            let mut clear = pin!(bin.clear_yielding(2));
+            let mut cx = Context::from_waker(Waker::noop());
+            assert!(clear.as_mut().poll(&mut cx).is_pending());
+        }
+        // The values that weren't dropped yet are back in the bin.
+        assert_eq!(dropped.load(SeqCst), 2);
+        assert_eq!(bin.estimate_clear_cost().items, 3);
+        assert_eq!(bin.epoch(), 1);
+
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 5);
+    }
+
+    #[test]
+    #[cfg(feature = "tokio")]
+    fn clear_cooperative_cancelled() {
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        let bin = Bin::new();
+        for _ in 0..1000 {
+            bin.add(CallOnDrop(|| {
+                DROPPED.fetch_add(1, SeqCst);
+            }));
+        }
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let mut clear = pin!(bin.clear_cooperative());
+            std::future::poll_fn(|cx| {
+                assert!(clear.as_mut().poll(cx).is_pending());
+                Poll::Ready(())
+            })
+            .await;
+        });
+
+        let dropped = DROPPED.load(SeqCst);
+        assert!(0 < dropped && dropped < 1000, "{dropped}");
+        assert_eq!(bin.estimate_clear_cost().items, 1000 - dropped);
+        bin.clear();
+        assert_eq!(DROPPED.load(SeqCst), 1000);
+    }
+
+    #[test]
+    #[cfg(feature = "tokio")]
+    fn clear_cooperative() {
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        let bin = Bin::new();
+        for _ in 0..1000 {
+            bin.add(CallOnDrop(|| {
+                DROPPED.fetch_add(1, SeqCst);
+            }));
+        }
+
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let seen = runtime.block_on(async {
+            let other = tokio::spawn(async { DROPPED.load(SeqCst) });
+            bin.clear_cooperative().await;
+            other.await.unwrap()
+        });
+
+        // The other task got to run part way through the clear.
+        assert!(0 < seen && seen < 1000, "{seen}");
+        assert_eq!(DROPPED.load(SeqCst), 1000);
+        assert_eq!(bin.epoch(), 1);
+    }
+}
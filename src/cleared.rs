@@ -0,0 +1,133 @@
+use crate::sync::const_fn;
+use crate::sync::Mutex;
+use crate::Bin;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::future::Future;
+use std::mem;
+use std::pin::Pin;
+use std::sync::PoisonError;
+use std::task::Context;
+use std::task::Poll;
+use std::task::Waker;
+
+/// A future that resolves once the bin has been cleared, created by [`Bin::cleared`].
+#[must_use = "futures do nothing unless polled"]
+pub struct Cleared<'b, 'a> {
+    bin: &'b Bin<'a>,
+    /// The epoch of the bin when this future was created.
+    epoch: u64,
+}
+
+impl<'b, 'a> Cleared<'b, 'a> {
+    pub(crate) fn new(bin: &'b Bin<'a>) -> Self {
+        Self {
+            bin,
+            epoch: bin.epoch(),
+        }
+    }
+}
+
+impl Future for Cleared<'_, '_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.bin.cleared_since(self.epoch) {
+            return Poll::Ready(());
+        }
+
+        self.bin.waiters.register(cx.waker());
+
+        // Check again in case the bin was cleared before we were registered.
+        if self.bin.cleared_since(self.epoch) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Debug for Cleared<'_, '_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cleared")
+            .field("epoch", &self.epoch)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The tasks waiting for a bin to be cleared.
+pub(crate) struct Waiters(Mutex<Vec<Waker>>);
+
+impl Waiters {
+    const_fn! {
+        pub(crate) fn new() -> Self {
+            Self(Mutex::new(Vec::new()))
+        }
+    }
+
+    pub(crate) fn register(&self, waker: &Waker) {
+        let mut wakers = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if !wakers.iter().any(|w| w.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+    }
+
+    /// Wake every waiting task. This must be called after the bin's epoch has been advanced, or
+    /// after it has been emptied.
+    pub(crate) fn wake_all(&self) {
+        let wakers = mem::take(&mut *self.0.lock().unwrap_or_else(PoisonError::into_inner));
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+impl Default for Waiters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for Waiters {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let len = self.0.lock().map_or(0, |wakers| wakers.len());
+        f.debug_struct("Waiters").field("len", &len).finish()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::Bin;
+    use std::future::Future;
+    use std::pin::pin;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::sync::Arc;
+    use std::task::Context;
+    use std::task::Wake;
+    use std::task::Waker;
+
+    struct Flag(AtomicBool);
+    impl Wake for Flag {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, SeqCst);
+        }
+    }
+
+    #[test]
+    fn cleared() {
+        let woken = Arc::new(Flag(AtomicBool::new(false)));
+        let waker = Waker::from(woken.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let bin = Bin::new();
+        let mut cleared = pin!(bin.cleared());
+        assert!(cleared.as_mut().poll(&mut cx).is_pending());
+        assert!(cleared.as_mut().poll(&mut cx).is_pending());
+
+        bin.clear();
+        assert!(woken.0.load(SeqCst));
+        assert!(cleared.as_mut().poll(&mut cx).is_ready());
+    }
+}
@@ -1,9 +1,11 @@
+use crate::sync::atomic;
+use crate::sync::atomic::AtomicPtr;
+use crate::sync::const_fn;
+use crate::sync::AtomicMut;
 use std::fmt;
 use std::fmt::Debug;
 use std::fmt::Formatter;
 use std::ptr;
-use std::sync::atomic;
-use std::sync::atomic::AtomicPtr;
 
 /// A concurrent insert-only linked list.
 pub(crate) struct ConcurrentList<T> {
@@ -19,12 +21,15 @@ unsafe impl<T: Send> Send for Node<T> {}
 unsafe impl<T: Send + Sync> Sync for Node<T> {}
 
 impl<T> ConcurrentList<T> {
-    pub(crate) const fn new() -> Self {
-        Self {
-            head: AtomicPtr::new(ptr::null_mut()),
+    const_fn! {
+        pub(crate) fn new() -> Self {
+            Self {
+                head: AtomicPtr::new(ptr::null_mut()),
+            }
         }
     }
 
+    #[cfg_attr(feature = "crossbeam", allow(dead_code))]
     fn head_node(&self) -> Option<&Node<T>> {
         let head = self.head.load(atomic::Ordering::Acquire);
 
@@ -34,9 +39,9 @@ impl<T> ConcurrentList<T> {
             Some(unsafe { &*head })
         }
     }
-    #[cfg(test)]
+    #[cfg(all(test, not(loom)))]
     fn head_node_mut(&mut self) -> Option<&mut Node<T>> {
-        let head = *self.head.get_mut();
+        let head = self.head.load_mut();
 
         if head.is_null() {
             None
@@ -45,10 +50,11 @@ impl<T> ConcurrentList<T> {
         }
     }
 
+    #[cfg_attr(feature = "crossbeam", allow(dead_code))]
     pub(crate) fn head(&self) -> Option<&T> {
         self.head_node().map(|node| &node.value)
     }
-    #[cfg(test)]
+    #[cfg(all(test, not(loom)))]
     pub(crate) fn head_mut(&mut self) -> Option<&mut T> {
         self.head_node_mut().map(|node| &mut node.value)
     }
@@ -67,7 +73,7 @@ impl<T> ConcurrentList<T> {
         })
     }
     pub(crate) fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> + '_ {
-        let mut node = *self.head.get_mut();
+        let mut node = self.head.load_mut();
 
         std::iter::from_fn(move || {
             if node.is_null() {
@@ -82,6 +88,7 @@ impl<T> ConcurrentList<T> {
     pub(crate) fn drain(&mut self) -> impl Iterator<Item = T> + '_ {
         std::iter::from_fn(move || self.pop())
     }
+    #[cfg_attr(feature = "crossbeam", allow(dead_code))]
     pub(crate) fn into_iter(mut self) -> impl Iterator<Item = T> {
         std::iter::from_fn(move || self.pop())
     }
@@ -112,21 +119,21 @@ impl<T> ConcurrentList<T> {
         &unsafe { &*node }.value
     }
     pub(crate) fn pop(&mut self) -> Option<T> {
-        let head_ptr = self.head.get_mut();
+        let head_ptr = self.head.load_mut();
         if head_ptr.is_null() {
             None
         } else {
-            let head_node = unsafe { Box::from_raw(*head_ptr) };
-            *head_ptr = head_node.next;
+            let head_node = unsafe { Box::from_raw(head_ptr) };
+            self.head.store_mut(head_node.next);
             Some(head_node.value)
         }
     }
 
-    #[cfg(test)]
     pub(crate) fn len(&self) -> usize {
         self.iter().count()
     }
-    #[cfg(test)]
+
+    #[cfg_attr(feature = "crossbeam", allow(dead_code))]
     pub(crate) fn is_empty(&self) -> bool {
         self.head.load(atomic::Ordering::Relaxed).is_null()
     }
@@ -150,7 +157,7 @@ impl<T> Drop for ConcurrentList<T> {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(loom)))]
 mod tests {
     use crate::concurrent_list::ConcurrentList;
     use crate::test_util::assert_thread_safe;
@@ -221,3 +228,31 @@ mod tests {
         assert_thread_safe::<ConcurrentList<()>>();
     }
 }
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use crate::concurrent_list::ConcurrentList;
+    use loom::sync::Arc;
+    use loom::thread;
+
+    #[test]
+    fn concurrent_push() {
+        loom::model(|| {
+            let list = Arc::new(ConcurrentList::new());
+
+            let thread = thread::spawn({
+                let list = list.clone();
+                move || assert_eq!(*list.push(1), 1)
+            });
+            assert_eq!(*list.push(2), 2);
+            thread.join().unwrap();
+
+            let mut values = Arc::try_unwrap(list)
+                .unwrap()
+                .into_iter()
+                .collect::<Vec<_>>();
+            values.sort_unstable();
+            assert_eq!(values, [1, 2]);
+        });
+    }
+}
@@ -1,11 +1,12 @@
-use std::cell::UnsafeCell;
+use crate::sync::atomic;
+use crate::sync::atomic::AtomicUsize;
+use crate::sync::AtomicMut;
+use crate::sync::UnsafeCell;
 use std::fmt;
 use std::fmt::Debug;
 use std::fmt::Formatter;
 use std::mem;
 use std::mem::MaybeUninit;
-use std::sync::atomic;
-use std::sync::atomic::AtomicUsize;
 
 /// A concurrent append-only boxed slice.
 pub struct ConcurrentSlice<T> {
@@ -50,20 +51,20 @@ impl<T> ConcurrentSlice<T> {
         };
 
         // SAFETY: We never read from this data type without exclusive access.
-        let val = unsafe { &mut *self.data[old_len].get() };
+        let val = self.data[old_len].with_mut(|value| unsafe { &mut *value });
         *val = MaybeUninit::new(value);
         Ok(unsafe { &mut *val.as_mut_ptr() })
     }
 
-    #[cfg(test)]
+    #[cfg(all(test, not(loom)))]
     fn iter_maybe_uninit_mut(
         &mut self,
     ) -> impl Iterator<Item = &mut MaybeUninit<T>> + DoubleEndedIterator + '_ {
-        self.data[..*self.len.get_mut()]
+        self.data[..self.len.load_mut()]
             .iter_mut()
             .map(UnsafeCell::get_mut)
     }
-    #[cfg(test)]
+    #[cfg(all(test, not(loom)))]
     pub(crate) unsafe fn iter_assume_init_mut(
         &mut self,
     ) -> impl Iterator<Item = &mut T> + DoubleEndedIterator + '_ {
@@ -71,8 +72,8 @@ impl<T> ConcurrentSlice<T> {
             .map(|val| unsafe { &mut *val.as_mut_ptr() })
     }
     pub(crate) fn drain(&mut self) -> impl Iterator<Item = T> + DoubleEndedIterator + '_ {
-        let old_len = *self.len.get_mut();
-        *self.len.get_mut() = 0;
+        let old_len = self.len.load_mut();
+        self.len.store_mut(0);
 
         self.data[..old_len].iter_mut().map(|cell| {
             let value = mem::replace(cell.get_mut(), MaybeUninit::uninit());
@@ -81,7 +82,7 @@ impl<T> ConcurrentSlice<T> {
     }
     pub(crate) fn into_iter(mut self) -> impl Iterator<Item = T> + DoubleEndedIterator {
         let data = mem::replace(&mut self.data, Vec::new().into_boxed_slice());
-        let len = *self.len.get_mut();
+        let len = self.len.load_mut();
         mem::forget(self);
 
         Vec::from(data).into_iter().take(len).map(|cell| {
@@ -113,7 +114,7 @@ impl<T> Drop for ConcurrentSlice<T> {
 unsafe impl<T: Send> Send for ConcurrentSlice<T> {}
 unsafe impl<T: Send + Sync> Sync for ConcurrentSlice<T> {}
 
-#[cfg(test)]
+#[cfg(all(test, not(loom)))]
 mod tests {
     use crate::concurrent_slice::ConcurrentSlice;
     use crate::test_util::assert_thread_safe;
@@ -1,3 +1,4 @@
+use crate::sync::const_fn;
 use crate::ConcurrentList;
 use crate::ConcurrentSlice;
 
@@ -8,9 +9,11 @@ pub struct ConcurrentVec<T> {
 }
 
 impl<T> ConcurrentVec<T> {
-    pub(crate) const fn new() -> Self {
-        Self {
-            data: ConcurrentList::new(),
+    const_fn! {
+        pub(crate) fn new() -> Self {
+            Self {
+                data: ConcurrentList::new(),
+            }
         }
     }
 
@@ -33,7 +36,7 @@ impl<T> ConcurrentVec<T> {
         }
     }
 
-    #[cfg(test)]
+    #[cfg(all(test, not(loom)))]
     pub(crate) unsafe fn iter_assume_init_mut(&mut self) -> impl Iterator<Item = &mut T> + '_ {
         self.data
             .iter_mut()
@@ -46,12 +49,10 @@ impl<T> ConcurrentVec<T> {
             .flat_map(|slice| slice.into_iter().rev())
     }
 
-    #[cfg(test)]
     pub(crate) fn len(&self) -> usize {
         self.data.iter().map(ConcurrentSlice::len).sum()
     }
 
-    #[cfg(test)]
     pub(crate) fn is_empty(&self) -> bool {
         self.data.is_empty()
     }
@@ -63,7 +64,7 @@ impl<T> Default for ConcurrentVec<T> {
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(loom)))]
 mod tests {
     use crate::concurrent_vec::ConcurrentVec;
     use crate::test_util::assert_thread_safe;
@@ -0,0 +1,211 @@
+use crate::weak_bins::WeakBins;
+use crate::Bin;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+/// A byte budget shared by many bins, which clears some of them whenever they use more than it
+/// between them.
+///
+/// Per-bin settings like [`Builder::limit`](crate::Builder::limit) can't cap the memory of a
+/// whole process, since each bin only knows about itself. A coordinator adds up the
+/// [bytes used](Bin::bytes_used) by every bin registered with it, and when the total is over the
+/// budget it clears bins in the order chosen by its [`ClearOrder`] until the total is back
+/// under.
+///
+/// # Example
+///
+/// ```
+/// use drop_bin::Bin;
+/// use drop_bin::ClearOrder;
+/// use drop_bin::Coordinator;
+/// use std::sync::Arc;
+///
+/// let coordinator = Coordinator::new(64 * 1024 * 1024, ClearOrder::LargestFirst);
+///
+/// let textures = Arc::new(Bin::new());
+/// let meshes = Arc::new(Bin::new());
+/// coordinator.register(&textures);
+/// coordinator.register(&meshes);
+///
+/// textures.add(vec![0_u8; 4096]);
+/// // Call this periodically, such as once per frame.
+/// coordinator.enforce();
+/// ```
+pub struct Coordinator {
+    budget: usize,
+    order: ClearOrder,
+    /// The registered bins, with when the coordinator last cleared them or when they were
+    /// registered.
+    bins: WeakBins<Instant>,
+}
+
+/// The order in which a [`Coordinator`] clears bins when they are over its budget.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub enum ClearOrder {
+    /// Clear the bins using the most bytes first, which clears as few bins as possible.
+    LargestFirst,
+    /// Clear the bins that the coordinator has gone longest without clearing first.
+    OldestFirst,
+    /// Clear bins in the order returned by the function, which is given the usage of every
+    /// registered bin and returns indices into it. Bins left out aren't cleared.
+    Custom(fn(&[BinUsage]) -> Vec<usize>),
+}
+
+/// How much a bin registered with a [`Coordinator`] is using, for choosing the order to clear
+/// bins in with [`ClearOrder::Custom`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BinUsage {
+    /// The number of bytes of storage that the bin's values take up.
+    pub bytes: usize,
+    /// How long it has been since the coordinator last cleared the bin, or since it was
+    /// registered.
+    pub idle: Duration,
+}
+
+impl Coordinator {
+    /// Create a coordinator that keeps its bins to `budget` bytes between them, clearing them in
+    /// the given order.
+    #[must_use]
+    pub const fn new(budget: usize, order: ClearOrder) -> Self {
+        Self {
+            budget,
+            order,
+            bins: WeakBins::new(),
+        }
+    }
+
+    /// Count the bin towards the budget until it is dropped.
+    pub fn register(&self, bin: &Arc<Bin<'static>>) {
+        self.bins.register(bin, Instant::now());
+    }
+
+    /// Get the number of bytes of storage that the values of every registered bin take up.
+    #[must_use]
+    pub fn bytes_used(&self) -> usize {
+        (self.bins.alive().iter())
+            .map(|(bin, _)| bin.bytes_used())
+            .sum()
+    }
+
+    /// Clear bins until they are back under the budget, if they are over it, returning the
+    /// number of bins that were cleared.
+    ///
+    /// The bins are cleared on the current thread, with [`Bin::clear`].
+    pub fn enforce(&self) -> usize {
+        let now = Instant::now();
+        let (alive, usage): (Vec<_>, Vec<_>) = (self.bins.alive().into_iter())
+            .map(|(bin, cleared)| {
+                let usage = BinUsage {
+                    bytes: bin.bytes_used(),
+                    idle: now.saturating_duration_since(cleared),
+                };
+                (bin, usage)
+            })
+            .unzip();
+
+        let mut total: usize = usage.iter().map(|usage| usage.bytes).sum();
+        if total <= self.budget {
+            return 0;
+        }
+        let order = match self.order {
+            ClearOrder::LargestFirst => sorted(&usage, |usage| usage.bytes),
+            ClearOrder::OldestFirst => sorted(&usage, |usage| usage.idle),
+            ClearOrder::Custom(order) => order(&usage),
+        };
+
+        let mut cleared = Vec::new();
+        for i in order {
+            if total <= self.budget {
+                break;
+            }
+            let (Some(bin), Some(usage)) = (alive.get(i), usage.get(i)) else {
+                continue;
+            };
+            // The budget only holds if the clear isn't put off.
+            bin.force_clear();
+            total = total.saturating_sub(usage.bytes);
+            cleared.push(Arc::downgrade(bin));
+        }
+
+        let now = Instant::now();
+        for (bin, last_cleared) in &mut *self.bins.lock() {
+            if cleared.iter().any(|cleared| cleared.ptr_eq(bin)) {
+                *last_cleared = now;
+            }
+        }
+        cleared.len()
+    }
+}
+
+/// Get the indices of the bins, largest `key` first.
+fn sorted<K: Ord>(usage: &[BinUsage], key: impl Fn(&BinUsage) -> K) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..usage.len()).collect();
+    order.sort_by(|&a, &b| key(&usage[b]).cmp(&key(&usage[a])));
+    order
+}
+
+impl Debug for Coordinator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Coordinator")
+            .field("budget", &self.budget)
+            .field("order", &self.order)
+            .field("bins", &self.bins.len())
+            .finish()
+    }
+}
+
+// Values aren't counted towards the bytes used with `boxed-storage`.
+#[cfg(all(test, not(loom), not(feature = "boxed-storage")))]
+mod tests {
+    use crate::Bin;
+    use crate::BinUsage;
+    use crate::ClearOrder;
+    use crate::Coordinator;
+    use std::sync::Arc;
+
+    #[test]
+    fn clears_over_budget() {
+        let coordinator = Coordinator::new(100, ClearOrder::LargestFirst);
+        let small = Arc::new(Bin::new());
+        let large = Arc::new(Bin::new());
+        coordinator.register(&small);
+        coordinator.register(&large);
+
+        small.add([0_u8; 64]);
+        assert_eq!(coordinator.enforce(), 0);
+
+        // Clearing the larger bin is enough.
+        large.add([0_u8; 128]);
+        assert_eq!(coordinator.enforce(), 1);
+        assert_eq!(small.bytes_used(), 64);
+        assert_eq!(large.bytes_used(), 0);
+    }
+
+    #[test]
+    fn orders() {
+        fn last(usage: &[BinUsage]) -> Vec<usize> {
+            vec![usage.len() - 1]
+        }
+
+        for (order, cleared) in [
+            (ClearOrder::OldestFirst, [true, true, false]),
+            (ClearOrder::Custom(last), [false, false, true]),
+        ] {
+            let coordinator = Coordinator::new(16, order);
+            let bins: Vec<_> = (0..3).map(|_| Arc::new(Bin::new())).collect();
+            for bin in &bins {
+                coordinator.register(bin);
+                bin.add([0_u8; 16]);
+            }
+            coordinator.enforce();
+            let emptied = bins.iter().map(|bin| bin.bytes_used() == 0);
+            assert!(emptied.eq(cleared), "{order:?}");
+        }
+    }
+}
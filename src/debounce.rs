@@ -0,0 +1,104 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::OnceLock;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Coalesces clears that are asked for in quick succession, set with
+/// [`Builder::min_clear_interval`](crate::Builder::min_clear_interval).
+#[derive(Debug)]
+pub(crate) struct Debounce {
+    interval: Duration,
+    /// Whether a clear was put off because it was asked for too soon after the last one.
+    deferred: AtomicBool,
+    /// When the last clear was let through, as given by [`now`], or 0 if there hasn't been one.
+    last: AtomicU64,
+}
+
+impl Debounce {
+    pub(crate) const fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            deferred: AtomicBool::new(false),
+            last: AtomicU64::new(0),
+        }
+    }
+
+    /// Check whether a clear asked for now can start, putting it off if it is too soon.
+    pub(crate) fn try_start(&self) -> bool {
+        let last = self.last.load(Ordering::Relaxed);
+        let now = now();
+        let interval = u64::try_from(self.interval.as_nanos()).unwrap_or(u64::MAX);
+        // If another thread starts a clear first, this one is coalesced into it.
+        let started = (last == 0 || now.saturating_sub(last) >= interval)
+            && (self.last)
+                .compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok();
+        self.deferred.store(!started, Ordering::Relaxed);
+        started
+    }
+
+    /// Record that a clear is starting regardless of the interval.
+    pub(crate) fn start(&self) {
+        self.last.fetch_max(now(), Ordering::Relaxed);
+        self.deferred.store(false, Ordering::Relaxed);
+    }
+
+    /// Forget the clears that have happened, as if the bin were new.
+    pub(crate) fn reset(&mut self) {
+        *self = Self::new(self.interval);
+    }
+
+    /// Check whether a clear that was put off can start now.
+    pub(crate) fn due(&self) -> bool {
+        self.deferred.load(Ordering::Relaxed) && self.try_start()
+    }
+}
+
+/// Get the number of nanoseconds since the first call, plus one so that it is never 0.
+fn now() -> u64 {
+    static START: OnceLock<Instant> = OnceLock::new();
+    let elapsed = START.get_or_init(Instant::now).elapsed().as_nanos();
+    u64::try_from(elapsed).unwrap_or(u64::MAX).saturating_add(1)
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::test_util::CallOnDrop;
+    use crate::Bin;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn coalesces() {
+        let dropped = AtomicUsize::new(0);
+        let value = || CallOnDrop(|| _ = dropped.fetch_add(1, SeqCst));
+        let bin = Bin::builder()
+            .min_clear_interval(Duration::from_millis(50))
+            .build();
+
+        bin.add(value());
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 1);
+
+        // Clears straight after another are put off.
+        bin.add(value());
+        bin.clear();
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 1);
+        bin.force_clear();
+        assert_eq!(dropped.load(SeqCst), 2);
+
+        // A clear that was put off runs on the first add once the interval is up, and drops that
+        // value too.
+        bin.add(value());
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 2);
+        thread::sleep(Duration::from_millis(60));
+        bin.add(value());
+        assert_eq!(dropped.load(SeqCst), 4);
+    }
+}
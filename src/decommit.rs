@@ -0,0 +1,101 @@
+#[cfg(windows)]
+use windows_sys::Win32::System::Memory::VirtualAlloc;
+#[cfg(windows)]
+use windows_sys::Win32::System::Memory::MEM_RESET;
+#[cfg(windows)]
+use windows_sys::Win32::System::Memory::PAGE_READWRITE;
+
+/// Tell the operating system that the whole pages within `len` bytes from `ptr` are disposable,
+/// so that they stop counting towards the process's resident memory until they are next written
+/// to. Enabled by the `decommit` feature.
+///
+/// # Safety
+///
+/// The bytes must be owned by the caller, and must not be read again before they are written.
+#[cfg(unix)]
+pub(crate) unsafe fn decommit(ptr: *mut u8, len: usize) {
+    // SAFETY: This has no preconditions.
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    let Some((start, len)) = pages(ptr, len, usize::try_from(page_size).unwrap_or(4096)) else {
+        return;
+    };
+    // SAFETY: The pages are owned by the caller, who doesn't need their contents.
+    unsafe { libc::madvise(start.cast(), len, libc::MADV_DONTNEED) };
+}
+
+/// Tell the operating system that the whole pages within `len` bytes from `ptr` are disposable,
+/// so that they stop counting towards the process's resident memory until they are next written
+/// to. Enabled by the `decommit` feature.
+///
+/// # Safety
+///
+/// The bytes must be owned by the caller, and must not be read again before they are written.
+#[cfg(windows)]
+pub(crate) unsafe fn decommit(ptr: *mut u8, len: usize) {
+    let Some((start, len)) = pages(ptr, len, 4096) else {
+        return;
+    };
+    // Memory from the heap can't be decommitted, since the heap owns its reservation, so it is
+    // reset instead: the system then discards the pages rather than paging them out.
+    // SAFETY: The pages are owned by the caller, who doesn't need their contents.
+    unsafe { VirtualAlloc(start.cast(), len, MEM_RESET, PAGE_READWRITE) };
+}
+
+/// Do nothing, on platforms where pages can't be decommitted.
+///
+/// # Safety
+///
+/// This is always safe, but matches the other platforms.
+#[cfg(not(any(unix, windows)))]
+pub(crate) unsafe fn decommit(_ptr: *mut u8, _len: usize) {}
+
+/// Get the start and length of the whole pages within `len` bytes from `ptr`, if there are any.
+#[cfg_attr(not(any(unix, windows)), allow(dead_code))]
+fn pages(ptr: *mut u8, len: usize, page_size: usize) -> Option<(*mut u8, usize)> {
+    let addr = ptr as usize;
+    let start = addr.checked_next_multiple_of(page_size)?;
+    let end = (addr + len) / page_size * page_size;
+    (start < end).then(|| (ptr.wrapping_add(start - addr), end - start))
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::decommit::decommit;
+    use crate::decommit::pages;
+    use crate::Bin;
+
+    #[test]
+    fn whole_pages() {
+        let at = |addr: usize| addr as *mut u8;
+        assert_eq!(pages(at(4096), 8192, 4096), Some((at(4096), 8192)));
+        assert_eq!(pages(at(4000), 8300, 4096), Some((at(4096), 8192)));
+        assert_eq!(pages(at(4000), 4000, 4096), None);
+    }
+
+    #[test]
+    fn reused() {
+        let bin = Bin::builder().decommit_after_clear().build();
+        for i in 0..10_000_u64 {
+            bin.add(i);
+        }
+        bin.clear();
+
+        // The decommitted storage is written to again rather than replaced.
+        let capacity = bin.capacity();
+        for i in 0..10_000_u64 {
+            bin.add(i);
+        }
+        bin.clear();
+        assert_eq!(bin.capacity(), capacity);
+    }
+
+    #[test]
+    fn written_again() {
+        let mut bytes = vec![1_u8; 64 * 1024];
+        // SAFETY: The bytes are owned here, and are written before they are read again.
+        unsafe { decommit(bytes.as_mut_ptr(), bytes.len()) };
+        // The pages can be written to and read back like any others.
+        bytes.fill(2);
+        assert!(bytes.iter().all(|&byte| byte == 2));
+    }
+}
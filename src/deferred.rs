@@ -0,0 +1,161 @@
+use crate::Bin;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::mem::ManuallyDrop;
+use std::ops::Deref;
+use std::ops::DerefMut;
+
+/// A value that is moved into a bin when it goes out of scope.
+///
+/// This is usually created by the [`defer_to!`](crate::defer_to) macro.
+pub struct Deferred<'b, 'a, T: Send + 'a> {
+    bin: &'b Bin<'a>,
+    value: ManuallyDrop<T>,
+}
+
+impl<'b, 'a, T: Send + 'a> Deferred<'b, 'a, T> {
+    /// Wrap a value so that it is added to the bin when it is dropped.
+    // Immediately dropping the result is how `defer_to!` is used as a statement.
+    #[allow(clippy::must_use_candidate)]
+    pub fn new(bin: &'b Bin<'a>, value: T) -> Self {
+        Self {
+            bin,
+            value: ManuallyDrop::new(value),
+        }
+    }
+
+    /// Take the value back out, so that it will not be added to the bin.
+    #[must_use]
+    pub fn into_inner(this: Self) -> T {
+        let mut this = ManuallyDrop::new(this);
+        // SAFETY: `this` is never used again and its destructor will not run.
+        unsafe { ManuallyDrop::take(&mut this.value) }
+    }
+}
+
+impl<'a, T: Send + 'a> Deref for Deferred<'_, 'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<'a, T: Send + 'a> DerefMut for Deferred<'_, 'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
+
+impl<'a, T: Send + Debug + 'a> Debug for Deferred<'_, 'a, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Deferred").field(&*self.value).finish()
+    }
+}
+
+impl<'a, T: Send + 'a> Drop for Deferred<'_, 'a, T> {
+    fn drop(&mut self) {
+        // SAFETY: The value is never used again after this.
+        let value = unsafe { ManuallyDrop::take(&mut self.value) };
+        self.bin.add(value);
+    }
+}
+
+/// Move the result of an expression into a bin once it goes out of scope.
+///
+/// Used as a statement, the value is added to the bin at the end of that statement. Bound to a
+/// variable, it derefs to the value and is added to the bin at the end of the variable's scope.
+///
+/// # Example
+///
+/// ```
+/// use drop_bin::defer_to;
+///
+/// let bin = drop_bin::Bin::new();
+///
+/// defer_to!(bin, vec![1, 2, 3]);
+///
+/// let mut data = defer_to!(bin, "Hello".to_owned());
+/// data.push_str(" World!");
+/// drop(data);
+/// // Neither value's destructor has been run.
+/// ```
+#[macro_export]
+macro_rules! defer_to {
+    ($bin:expr, $value:expr $(,)?) => {
+        $crate::Deferred::new(&$bin, $value)
+    };
+}
+
+/// Run a block with a fresh bin that is cleared when the block exits.
+///
+/// # Example
+///
+/// ```
+/// use drop_bin::binned;
+///
+/// let len = binned!(bin => {
+///     let data = vec![0_u8; 1024];
+///     let len = data.len();
+///     bin.add(data);
+///     len
+/// });
+/// // `data`'s destructor has been run.
+/// assert_eq!(len, 1024);
+/// ```
+#[macro_export]
+macro_rules! binned {
+    ($bin:ident => $body:expr) => {{
+        let $bin = $crate::Bin::new();
+        let result = $body;
+        result
+    }};
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::deferred::Deferred;
+    use crate::test_util::CallOnDrop;
+    use crate::Bin;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::atomic::Ordering::SeqCst;
+
+    #[test]
+    fn defer_to() {
+        let destructor_called = AtomicBool::new(false);
+
+        let bin = Bin::new();
+
+        crate::defer_to!(
+            bin,
+            CallOnDrop(|| assert!(!destructor_called.swap(true, SeqCst))),
+        );
+        assert!(!destructor_called.load(SeqCst));
+
+        bin.clear();
+        assert!(destructor_called.load(SeqCst));
+    }
+
+    #[test]
+    fn into_inner() {
+        let bin = Bin::new();
+
+        let mut value = crate::defer_to!(bin, "Hello".to_owned());
+        value.push('!');
+        assert_eq!(Deferred::into_inner(value), "Hello!");
+        assert_eq!(bin.size(), 0);
+    }
+
+    #[test]
+    fn binned() {
+        let destructor_called = AtomicBool::new(false);
+
+        crate::binned!(bin => {
+            bin.add(CallOnDrop(|| assert!(!destructor_called.swap(true, SeqCst))));
+            assert!(!destructor_called.load(SeqCst));
+        });
+
+        assert!(destructor_called.load(SeqCst));
+    }
+}
@@ -0,0 +1,15 @@
+/// What happens to the values left in a bin when it is dropped, set with
+/// [`Bin::set_drop_behavior`](crate::Bin::set_drop_behavior).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DropBehavior {
+    /// Drop the values, like [`Bin::clear`](crate::Bin::clear).
+    #[default]
+    Clear,
+    /// Forget the values without running their destructors, like
+    /// [`Bin::leak`](crate::Bin::leak).
+    ///
+    /// This is for bins that are destroyed while the process is exiting, where running every
+    /// destructor would only slow shutdown down.
+    Leak,
+}
@@ -0,0 +1,107 @@
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::time::Duration;
+
+/// Moving averages of how long destructors have taken to run, keyed by the address of the
+/// function that runs them.
+///
+/// This is shared by every bin, since a type costs about as much to drop whichever bin it is in.
+static DROP_TIMES: Mutex<BTreeMap<usize, Duration>> = Mutex::new(BTreeMap::new());
+
+/// Each new sample is given this fraction of the weight of the average, as its reciprocal.
+const SMOOTHING: u32 = 8;
+
+/// The drop times measured during a clear, kept apart from the learned ones so that destructors
+/// don't contend on the lock.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Samples(BTreeMap<usize, (Duration, u32)>);
+
+impl Samples {
+    /// Record that a destructor took `duration` to run.
+    pub(crate) fn record(&mut self, destructor: usize, duration: Duration) {
+        let (total, count) = self.0.entry(destructor).or_default();
+        *total = total.saturating_add(duration);
+        *count = count.saturating_add(1);
+    }
+
+    /// Fold the samples into the learned drop times, taking each destructor's mean over the clear
+    /// as one sample.
+    pub(crate) fn learn(&self) {
+        if self.0.is_empty() {
+            return;
+        }
+        let mut times = DROP_TIMES.lock().unwrap_or_else(PoisonError::into_inner);
+        for (&destructor, &(total, count)) in &self.0 {
+            let duration = total / count;
+            let average = times.entry(destructor).or_insert(duration);
+            *average = average
+                .saturating_mul(SMOOTHING - 1)
+                .saturating_add(duration)
+                / SMOOTHING;
+        }
+    }
+}
+
+/// A copy of the learned drop times, so that they can be looked up without taking the lock.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct DropTimes(BTreeMap<usize, Duration>);
+
+impl DropTimes {
+    /// Copy the drop times learned so far.
+    pub(crate) fn learned() -> Self {
+        Self(
+            DROP_TIMES
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone(),
+        )
+    }
+
+    /// Get how long a destructor is expected to take, or `None` if it hasn't been run yet.
+    pub(crate) fn get(&self, destructor: usize) -> Option<Duration> {
+        self.0.get(&destructor).copied()
+    }
+
+    /// Estimate how long the destructors together are expected to take, or `None` if none of
+    /// them has been run yet. Those that haven't are counted as taking no time.
+    pub(crate) fn total(&self, destructors: impl IntoIterator<Item = usize>) -> Option<Duration> {
+        (destructors.into_iter())
+            .filter_map(|destructor| self.get(destructor))
+            .reduce(Duration::saturating_add)
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::drop_times::DropTimes;
+    use crate::drop_times::Samples;
+    use std::time::Duration;
+
+    #[test]
+    fn moving_average() {
+        // No destructors live at these addresses.
+        let (key, other) = (1, 2);
+        let mut samples = Samples::default();
+        samples.record(key, Duration::from_micros(80));
+        samples.learn();
+        assert_eq!(
+            DropTimes::learned().get(key),
+            Some(Duration::from_micros(80))
+        );
+
+        // Each batch counts as one sample of its mean.
+        let mut samples = Samples::default();
+        samples.record(key, Duration::ZERO);
+        samples.record(key, Duration::ZERO);
+        samples.learn();
+        let times = DropTimes::learned();
+        assert_eq!(times.get(key), Some(Duration::from_micros(70)));
+
+        assert_eq!(times.total([other]), None);
+        assert_eq!(
+            times.total([key, other, key]),
+            Some(Duration::from_micros(140))
+        );
+    }
+}
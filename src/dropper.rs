@@ -0,0 +1,75 @@
+use crate::inner::Detached;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+
+/// The contents of a bin that has been consumed by [`Bin::into_dropper`](crate::Bin::into_dropper),
+/// which are dropped along with this.
+///
+/// A dropper for a `Bin<'static>` is `Send + 'static`, so the cleanup work can be moved onto
+/// another thread or into a task queue without copying anything.
+///
+/// # Example
+///
+/// ```
+/// let bin = drop_bin::Bin::new();
+/// bin.add(vec![1, 2, 3]);
+///
+/// let dropper = bin.into_dropper();
+/// std::thread::spawn(move || dropper.run()).join().unwrap();
+/// ```
+pub struct Dropper<'a> {
+    detached: Detached<'a>,
+}
+
+impl<'a> Dropper<'a> {
+    pub(crate) fn new(detached: Detached<'a>) -> Self {
+        Self { detached }
+    }
+
+    /// Drop all the values.
+    ///
+    /// This is the same as dropping the dropper.
+    pub fn run(self) {
+        drop(self.detached);
+    }
+}
+
+impl Debug for Dropper<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Dropper").finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::test_util::CallOnDrop;
+    use crate::Bin;
+    use crate::Dropper;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::thread;
+
+    #[test]
+    fn into_dropper() {
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        let bin = Bin::new();
+        for _ in 0..3 {
+            bin.add(CallOnDrop(|| {
+                DROPPED.fetch_add(1, SeqCst);
+            }));
+        }
+
+        let dropper = bin.into_dropper();
+        assert_eq!(DROPPED.load(SeqCst), 0);
+        thread::spawn(move || dropper.run()).join().unwrap();
+        assert_eq!(DROPPED.load(SeqCst), 3);
+    }
+
+    #[test]
+    fn thread_safe() {
+        fn assert_send<T: Send + 'static>() {}
+        assert_send::<Dropper<'static>>();
+    }
+}
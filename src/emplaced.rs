@@ -0,0 +1,69 @@
+use crate::slot_lock::ReadGuard;
+use crate::Bin;
+use crate::Inner;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::ptr::NonNull;
+
+/// A value added to a bin with [`Bin::emplace_pinned`].
+///
+/// Clears of the value wait until the guard is dropped.
+pub struct Emplaced<'b, 'a, T> {
+    value: NonNull<T>,
+    /// The read lock on the bin, which keeps the value from being cleared.
+    inner: Option<ReadGuard<'b, Inner<'a>>>,
+    bin: &'b Bin<'a>,
+}
+
+impl<'b, 'a, T> Emplaced<'b, 'a, T> {
+    /// Create a guard for a value that was added under the read lock.
+    ///
+    /// # Safety
+    ///
+    /// `value` must point to a value in `inner` whose allocation isn't freed until it has been
+    /// dropped at a clear.
+    pub(crate) unsafe fn new(
+        bin: &'b Bin<'a>,
+        inner: ReadGuard<'b, Inner<'a>>,
+        value: NonNull<T>,
+    ) -> Self {
+        Self {
+            value,
+            inner: Some(inner),
+            bin,
+        }
+    }
+}
+
+impl<T> Deref for Emplaced<'_, '_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: The read lock keeps the value from being dropped.
+        unsafe { self.value.as_ref() }
+    }
+}
+
+impl<T> DerefMut for Emplaced<'_, '_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: The read lock keeps the value from being dropped, and only this guard gives
+        // access to it.
+        unsafe { self.value.as_mut() }
+    }
+}
+
+impl<T: Debug> Debug for Emplaced<'_, '_, T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&**self, f)
+    }
+}
+
+impl<T> Drop for Emplaced<'_, '_, T> {
+    fn drop(&mut self) {
+        self.inner = None;
+        self.bin.added();
+    }
+}
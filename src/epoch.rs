@@ -0,0 +1,183 @@
+//! An epoch-based interface to a bin, for deferring the freeing of nodes in concurrent data
+//! structures in the style of `crossbeam-epoch`.
+//!
+//! Threads [`pin`](Bin::pin) the bin while they read from the structure, and nodes that have been
+//! unlinked from it are [retired](Guard::retire) into the bin. A clear only frees the nodes that
+//! were retired before every guard that is still pinned was created, so no thread can be left
+//! holding a pointer to a freed node.
+//!
+//! # Example
+//!
+//! ```
+//! let bin = drop_bin::epoch::Bin::new();
+//!
+//! let guard = bin.pin();
+//! let node = Box::into_raw(Box::new(5));
+//! unsafe fn free(ptr: *mut ()) {
+//!     drop(unsafe { Box::from_raw(ptr.cast::<i32>()) });
+//! }
+//! // SAFETY: The node has been unlinked, and is only freed once no guards can be reading it.
+//! unsafe { guard.retire(node.cast(), free) };
+//!
+//! bin.clear();
+//! // The guard was pinned before the node was retired, so the node is kept.
+//! assert_eq!(bin.pending(), 1);
+//!
+//! drop(guard);
+//! bin.clear();
+//! assert_eq!(bin.pending(), 0);
+//! ```
+
+use crate::qsbr::Retired;
+use crate::sync::const_fn;
+use crate::sync::Mutex;
+use crate::sync::MutexGuard;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::sync::PoisonError;
+
+/// A bin whose values are only dropped once no guard that was pinned before they were retired is
+/// still alive.
+///
+/// Unlike [`qsbr::Bin`](crate::qsbr::Bin), threads don't register: only threads that currently
+/// hold a [`Guard`] hold values back. Clears must be called to free the values, for example
+/// periodically or after retiring a certain number of them.
+pub struct Bin<'a> {
+    retired: Retired<'a>,
+    /// The number of guards that are pinned in each era.
+    pins: Mutex<BTreeMap<u64, usize>>,
+}
+
+impl<'a> Bin<'a> {
+    const_fn! {
+        /// Create a new bin.
+        #[must_use]
+        pub fn new() -> Self {
+            Self {
+                retired: Retired::new(),
+                pins: Mutex::new(BTreeMap::new()),
+            }
+        }
+    }
+
+    /// Pin the bin, so that values retired from now on aren't dropped until the guard is.
+    pub fn pin(&self) -> Guard<'_, 'a> {
+        let mut pins = self.lock_pins();
+        // The era is read under the lock, so that a clear that has advanced it sees the guard.
+        let era = self.retired.era();
+        *pins.entry(era).or_insert(0) += 1;
+        Guard { bin: self, era }
+    }
+
+    /// Take the retired values out of the bin, then drop those that no pinned guard can be
+    /// reading.
+    ///
+    /// Like [`Bin::leak`](crate::Bin::leak), this waits for retires in progress on other threads
+    /// to finish.
+    pub fn clear(&self) {
+        self.retired
+            .clear(|| self.lock_pins().first_key_value().map(|(&era, _)| era));
+    }
+
+    /// Get the number of values that have been taken out of the bin, but are waiting for guards to
+    /// be dropped.
+    #[must_use]
+    pub fn pending(&self) -> usize {
+        self.retired.pending()
+    }
+
+    fn lock_pins(&self) -> MutexGuard<'_, BTreeMap<u64, usize>> {
+        self.pins.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for Bin<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for Bin<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Bin")
+            .field("values", self.retired.values())
+            .field("pending", &self.pending())
+            .field("pinned", &self.lock_pins().values().sum::<usize>())
+            .finish()
+    }
+}
+
+/// A pin of a [`Bin`], returned by [`Bin::pin`], which keeps values that are retired while it is
+/// alive from being dropped.
+#[derive(Debug)]
+#[must_use]
+pub struct Guard<'b, 'a> {
+    bin: &'b Bin<'a>,
+    /// The era that the guard was pinned in.
+    era: u64,
+}
+
+impl<'a> Guard<'_, 'a> {
+    /// Retire a pointer, to be cleaned up by calling `deleter` on it once no guard that is
+    /// currently pinned is left.
+    ///
+    /// # Safety
+    ///
+    /// It must be safe to call `deleter` with `ptr` exactly once, on any thread, at any point
+    /// during `'a` after every guard pinned before this call has been dropped.
+    pub unsafe fn retire(&self, ptr: *mut (), deleter: unsafe fn(*mut ())) {
+        // SAFETY: Upheld by the caller.
+        unsafe { self.bin.retired.add_raw(ptr, deleter) };
+    }
+
+    /// Defer dropping a value until no guard that is currently pinned is left.
+    pub fn defer<T: Send + 'a>(&self, value: T) {
+        self.bin.retired.add(value);
+    }
+}
+
+impl Drop for Guard<'_, '_> {
+    fn drop(&mut self) {
+        let mut pins = self.bin.lock_pins();
+        if let Some(count) = pins.get_mut(&self.era) {
+            *count -= 1;
+            if *count == 0 {
+                pins.remove(&self.era);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::epoch::Bin;
+    use crate::test_util::CallOnDrop;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+
+    #[test]
+    fn waits_for_guards() {
+        let dropped = AtomicUsize::new(0);
+        let drop = || CallOnDrop(|| _ = dropped.fetch_add(1, SeqCst));
+        let bin = Bin::new();
+
+        let old = bin.pin();
+        old.defer(drop());
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 0);
+
+        // Guards pinned after the value was taken out don't hold it back.
+        let new = bin.pin();
+        new.defer(drop());
+        std::mem::drop(old);
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 1);
+        assert_eq!(bin.pending(), 1);
+
+        std::mem::drop(new);
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 2);
+    }
+}
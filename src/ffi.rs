@@ -0,0 +1,185 @@
+//! A C interface to bins, enabled by the `ffi` feature.
+//!
+//! Bins are created with [`drop_bin_new`] and destroyed with [`drop_bin_free`]. Foreign resources
+//! are added as a pointer along with the C function that destroys them. A matching C header can be
+//! found in `include/drop_bin.h`.
+//!
+//! To link the crate into a C program, build it as a static or dynamic library with
+//! `cargo rustc --release --features ffi --crate-type staticlib` (or `cdylib`). The crate types
+//! aren't set in the manifest, since that would build them for every crate that depends on this
+//! one, and they can't be built without `std`.
+//!
+//! Panics can't unwind into C, so functions that can panic catch it and return -1 instead of 0.
+
+use crate::Bin;
+use std::ffi::c_int;
+use std::ffi::c_void;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+
+/// A C destructor.
+type ForeignDestructor = unsafe extern "C" fn(*mut c_void);
+
+/// A resource owned by C code, destroyed by calling a C function on it.
+struct Foreign {
+    ptr: *mut c_void,
+    destructor: ForeignDestructor,
+}
+
+/// Destroy a boxed [`Foreign`] added to a bin with [`Bin::add_raw`].
+///
+/// # Safety
+///
+/// `foreign` must come from `Box::into_raw`, and it must be safe to call its destructor on its
+/// pointer.
+unsafe fn destroy(foreign: *mut ()) {
+    // SAFETY: Upheld by the caller.
+    let foreign = unsafe { Box::from_raw(foreign.cast::<Foreign>()) };
+    // SAFETY: Upheld by the caller.
+    unsafe { (foreign.destructor)(foreign.ptr) };
+}
+
+/// Run `f`, returning 0 if it succeeds or -1 if it panics.
+fn catch(f: impl FnOnce()) -> c_int {
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(()) => 0,
+        Err(_) => -1,
+    }
+}
+
+/// Create a new bin.
+///
+/// The bin must eventually be destroyed with [`drop_bin_free`].
+#[no_mangle]
+#[must_use]
+pub extern "C" fn drop_bin_new() -> *mut Bin<'static> {
+    Box::into_raw(Box::new(Bin::new()))
+}
+
+/// Add a resource to the bin, to be destroyed by calling `destructor` on `ptr`.
+///
+/// This returns 0 on success, or -1 if the bin panicked, such as because it was
+/// [poisoned](Bin::is_poisoned), in which case the resource is left to the caller. A panic in a
+/// clear started once the resource has been added poisons the bin, and is reported by the next
+/// call instead.
+///
+/// # Safety
+///
+/// `bin` must be a bin created by [`drop_bin_new`] that has not been freed. It must be safe to call
+/// `destructor` with `ptr` exactly once, on any thread.
+#[no_mangle]
+#[must_use]
+pub unsafe extern "C" fn drop_bin_add(
+    bin: *const Bin<'static>,
+    ptr: *mut c_void,
+    destructor: ForeignDestructor,
+) -> c_int {
+    // SAFETY: Upheld by the caller.
+    let bin = unsafe { &*bin };
+    // Panics are only caught up to the point where the bin takes the resource, so that the box
+    // isn't leaked and the caller knows whether it still owns the resource.
+    let Ok(inner) = panic::catch_unwind(AssertUnwindSafe(|| bin.read_for_add())) else {
+        return -1;
+    };
+    let foreign = Box::into_raw(Box::new(Foreign { ptr, destructor })).cast();
+    match inner {
+        // SAFETY: Upheld by the caller.
+        Some(inner) => unsafe { inner.add_raw(foreign, destroy) },
+        // SAFETY: Upheld by the caller.
+        None => unsafe { destroy(foreign) },
+    }
+    let _ = catch(|| bin.added());
+    0
+}
+
+/// Clear the bin, destroying all resources that have been previously added to it.
+///
+/// This returns 0 on success, or -1 if the bin panicked.
+///
+/// # Safety
+///
+/// `bin` must be a bin created by [`drop_bin_new`] that has not been freed.
+#[no_mangle]
+#[must_use]
+pub unsafe extern "C" fn drop_bin_clear(bin: *const Bin<'static>) -> c_int {
+    // SAFETY: Upheld by the caller.
+    catch(|| unsafe { &*bin }.clear())
+}
+
+/// Destroy the bin, along with all the resources in it.
+///
+/// Passing a null pointer does nothing. This returns 0 on success, or -1 if the bin panicked.
+///
+/// # Safety
+///
+/// `bin` must be null or a bin created by [`drop_bin_new`] that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn drop_bin_free(bin: *mut Bin<'static>) -> c_int {
+    if bin.is_null() {
+        return 0;
+    }
+    // SAFETY: Upheld by the caller.
+    catch(|| drop(unsafe { Box::from_raw(bin) }))
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::ffi::drop_bin_add;
+    use crate::ffi::drop_bin_clear;
+    use crate::ffi::drop_bin_free;
+    use crate::ffi::drop_bin_new;
+    use crate::test_util::CallOnDrop;
+    use crate::Bin;
+    use std::ffi::c_void;
+    use std::panic;
+    use std::panic::AssertUnwindSafe;
+    use std::ptr;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+
+    static DESTROYED: AtomicUsize = AtomicUsize::new(0);
+
+    fn resource(value: u64) -> *mut c_void {
+        Box::into_raw(Box::new(value)).cast()
+    }
+
+    unsafe extern "C" fn destroy(ptr: *mut c_void) {
+        drop(unsafe { Box::from_raw(ptr.cast::<u64>()) });
+        DESTROYED.fetch_add(1, SeqCst);
+    }
+
+    #[test]
+    fn ffi() {
+        let bin = drop_bin_new();
+
+        unsafe {
+            assert_eq!(drop_bin_add(bin, resource(1), destroy), 0);
+            assert_eq!(DESTROYED.load(SeqCst), 0);
+            assert_eq!(drop_bin_clear(bin), 0);
+            assert_eq!(DESTROYED.load(SeqCst), 1);
+
+            assert_eq!(drop_bin_add(bin, resource(2), destroy), 0);
+            assert_eq!(drop_bin_free(bin), 0);
+            assert_eq!(DESTROYED.load(SeqCst), 2);
+
+            assert_eq!(drop_bin_free(ptr::null_mut()), 0);
+        }
+    }
+
+    #[test]
+    fn panics() {
+        let bin = Bin::new();
+        bin.add(CallOnDrop(|| panic!("destructor panicked")));
+        bin.add(5);
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| bin.clear())).is_err());
+        let bin = Box::into_raw(Box::new(bin));
+
+        unsafe {
+            let ptr = resource(3);
+            assert_eq!(drop_bin_add(bin, ptr, destroy), -1);
+            drop(Box::from_raw(ptr.cast::<u64>()));
+            assert_eq!(drop_bin_clear(bin), -1);
+            assert_eq!(drop_bin_free(bin), 0);
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use crate::sync::atomic::AtomicU8;
+use crate::sync::atomic::Ordering;
+use crate::sync::const_fn;
+use crate::sync::spin_loop;
+use crate::sync::UnsafeCell;
+use crate::zeroize;
+use core::fmt;
+use core::fmt::Debug;
+use core::fmt::Formatter;
+use core::marker::PhantomData;
+use core::mem::align_of;
+use core::mem::size_of;
+use core::ptr;
+
+/// The bin isn't in use.
+const IDLE: u8 = 0;
+/// A value is being added to the bin.
+const ADDING: u8 = 1;
+/// The bin is being cleared.
+const CLEARING: u8 = 2;
+
+/// A bin that keeps its values in a buffer given to it, for targets without a heap.
+///
+/// Values are packed into the start of the buffer and the table of their destructors into the
+/// end, so neither adding nor clearing allocates. Once the two meet the bin is full, and
+/// [`add`](Self::add) drops values immediately. The bin is locked with a spin lock rather than
+/// the operating system's, and adds that happen while it is being cleared, such as from the
+/// destructors it runs, drop their values immediately instead of waiting.
+///
+/// # Example
+///
+/// ```
+/// use drop_bin::FixedBin;
+///
+/// let mut buffer = [0_u8; 256];
+/// let bin = FixedBin::new(&mut buffer);
+///
+/// bin.add([1_u32; 4]);
+/// assert_eq!(bin.try_add([0_u8; 1024]), Err([0; 1024]));
+///
+/// bin.clear();
+/// ```
+pub struct FixedBin<'a> {
+    start: *mut u8,
+    len: usize,
+    state: AtomicU8,
+    cursors: UnsafeCell<Cursors>,
+    /// The bin holds values of any type that lives for `'a`, and borrows the buffer for `'a`.
+    invariant_over_lifetime_a: PhantomData<fn(&'a ()) -> &'a mut [u8]>,
+}
+
+// SAFETY: Only `Send` values are added, and `cursors` is only accessed under the lock.
+unsafe impl Send for FixedBin<'_> {}
+// SAFETY: As above.
+unsafe impl Sync for FixedBin<'_> {}
+
+/// Where the bin's values end and its entries begin, as offsets into the buffer.
+#[derive(Debug, Clone, Copy)]
+struct Cursors {
+    values: usize,
+    entries: usize,
+}
+
+/// A value in a [`FixedBin`].
+#[derive(Clone, Copy)]
+struct Entry {
+    /// The offset of the value in the buffer.
+    offset: usize,
+    drop: unsafe fn(*mut u8),
+}
+
+impl<'a> FixedBin<'a> {
+    const_fn! {
+        /// Create a bin that stores its values in the buffer.
+        #[must_use]
+        pub fn new(buffer: &'a mut [u8]) -> Self {
+            Self {
+                start: buffer.as_mut_ptr(),
+                len: buffer.len(),
+                state: AtomicU8::new(IDLE),
+                cursors: UnsafeCell::new(Cursors {
+                    values: 0,
+                    entries: buffer.len(),
+                }),
+                invariant_over_lifetime_a: PhantomData,
+            }
+        }
+    }
+
+    /// Add a value to the bin.
+    ///
+    /// The value is dropped immediately if there isn't room for it in the buffer, or the bin is
+    /// being cleared.
+    pub fn add<T: Send + 'a>(&self, value: T) {
+        drop(self.try_add(value));
+    }
+
+    /// Add a value to the bin, giving it back if there isn't room for it in the buffer or the
+    /// bin is being cleared.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the value can't be added.
+    pub fn try_add<T: Send + 'a>(&self, value: T) -> Result<(), T> {
+        if !self.lock(ADDING) {
+            return Err(value);
+        }
+        // SAFETY: The bin is locked.
+        let cursors = self.cursors.with_mut(|cursors| unsafe { &mut *cursors });
+        let Some((value_offset, entry_offset)) = self.place::<T>(*cursors) else {
+            self.state.store(IDLE, Ordering::Release);
+            return Err(value);
+        };
+
+        let base = self.start;
+        // SAFETY: `place` found room for the value, suitably aligned, and the entry in the buffer
+        // between the values and entries that are already there. Entries aren't aligned.
+        unsafe {
+            base.add(value_offset).cast::<T>().write(value);
+            base.add(entry_offset)
+                .cast::<Entry>()
+                .write_unaligned(Entry {
+                    offset: value_offset,
+                    drop: drop_value::<T>,
+                });
+        }
+        *cursors = Cursors {
+            values: value_offset + size_of::<T>(),
+            entries: entry_offset,
+        };
+        self.state.store(IDLE, Ordering::Release);
+        Ok(())
+    }
+
+    /// Drop all the values in the bin, oldest first.
+    ///
+    /// This does nothing if the bin is already being cleared. If a destructor panics, the values
+    /// after it are leaked.
+    pub fn clear(&self) {
+        /// Empties the bin and unlocks it, even if a destructor panics.
+        struct Reset<'b, 'a>(&'b FixedBin<'a>);
+
+        impl Drop for Reset<'_, '_> {
+            fn drop(&mut self) {
+                let bin = self.0;
+                // SAFETY: The bin is locked.
+                let cursors = bin.cursors.with_mut(|cursors| unsafe { &mut *cursors });
+                // SAFETY: The values have been dropped, and their bytes are in the buffer.
+                unsafe { zeroize::zero(bin.start, cursors.values) };
+                *cursors = Cursors {
+                    values: 0,
+                    entries: bin.len,
+                };
+                bin.state.store(IDLE, Ordering::Release);
+            }
+        }
+
+        if !self.lock(CLEARING) {
+            return;
+        }
+        let reset = Reset(self);
+        // SAFETY: The bin is locked, and adds don't touch the cursors while it is cleared.
+        let entries = self.cursors.with(|cursors| unsafe { (*cursors).entries });
+        // The oldest entry is at the end of the buffer.
+        for offset in (entries..self.len).step_by(size_of::<Entry>()).rev() {
+            // SAFETY: The entries are packed together at the end of the buffer, and each value is
+            // dropped only once since the bin is emptied afterward.
+            unsafe {
+                let entry = self.start.add(offset).cast::<Entry>().read_unaligned();
+                (entry.drop)(self.start.add(entry.offset));
+            }
+        }
+        drop(reset);
+    }
+
+    /// Get the number of values in the bin.
+    ///
+    /// This is zero while the bin is being cleared.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        if !self.lock(ADDING) {
+            return 0;
+        }
+        // SAFETY: The bin is locked.
+        let entries = self.cursors.with(|cursors| unsafe { (*cursors).entries });
+        self.state.store(IDLE, Ordering::Release);
+        (self.len - entries) / size_of::<Entry>()
+    }
+
+    /// Check whether the bin holds no values.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Lock the bin for adding or clearing, returning `false` if it is being cleared.
+    fn lock(&self, purpose: u8) -> bool {
+        loop {
+            match (self.state).compare_exchange_weak(
+                IDLE,
+                purpose,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(CLEARING) => return false,
+                Err(_) => spin_loop(),
+            }
+        }
+    }
+
+    /// Find the offsets to put a value of type `T` and its entry at, if there is room.
+    fn place<T>(&self, cursors: Cursors) -> Option<(usize, usize)> {
+        let start = self.start as usize;
+        let value =
+            (start.checked_add(cursors.values)?).checked_next_multiple_of(align_of::<T>())?;
+        let value_end = value.checked_add(size_of::<T>())?;
+        let entry = cursors.entries.checked_sub(size_of::<Entry>())?;
+        (value_end - start <= entry).then_some((value - start, entry))
+    }
+}
+
+impl Drop for FixedBin<'_> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl Debug for FixedBin<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FixedBin")
+            .field("len", &self.len())
+            .field("capacity", &self.len)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Drop the value of type `T` at `ptr`.
+///
+/// # Safety
+///
+/// `ptr` must point to a valid `T` that is not used again.
+unsafe fn drop_value<T>(ptr: *mut u8) {
+    // SAFETY: Upheld by the caller.
+    unsafe { ptr::drop_in_place(ptr.cast::<T>()) };
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::test_util::CallOnDrop;
+    use crate::FixedBin;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+
+    #[test]
+    fn fixed() {
+        let dropped = AtomicUsize::new(0);
+        let value = || CallOnDrop(|| _ = dropped.fetch_add(1, SeqCst));
+        let mut buffer = [0_u8; 128];
+        let bin = FixedBin::new(&mut buffer);
+
+        bin.add(value());
+        bin.add(7_u8);
+        bin.add(value());
+        assert_eq!(bin.len(), 3);
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 2);
+        assert!(bin.is_empty());
+
+        // The bin fills up, and values that don't fit are dropped straight away.
+        let mut added = 0;
+        while bin.try_add(0_u64).is_ok() {
+            added += 1;
+        }
+        assert!(added > 0);
+        bin.add(value());
+        assert_eq!(dropped.load(SeqCst), 3);
+        assert_eq!(bin.len(), added);
+    }
+}
@@ -0,0 +1,123 @@
+use crate::inner::Detached;
+use crate::sync::const_fn;
+use crate::sync::Mutex;
+use crate::sync::MutexGuard;
+use std::sync::PoisonError;
+use std::sync::TryLockError;
+
+/// What a call to [`Bin::end_frame`](crate::Bin::end_frame) did, and how far behind the bin is
+/// falling.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FrameReport {
+    /// The number of values that were dropped.
+    pub dropped: usize,
+    /// The number of values that didn't fit in the budget, which are carried forward to the next
+    /// frame.
+    pub carried: usize,
+    /// The number of frames in a row, including this one, that have ended with values carried
+    /// forward. If this keeps growing, values are being added faster than the frame budgets can
+    /// drop them.
+    pub frames_behind: u32,
+}
+
+/// The values that `end_frame` is part way through dropping.
+#[derive(Debug)]
+pub(crate) struct Frames<'a> {
+    state: Mutex<Frame<'a>>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Frame<'a> {
+    /// The values that earlier frames didn't have time to drop.
+    pub(crate) carried: Option<Detached<'a>>,
+    /// The number of frames in a row that have ended with values carried forward.
+    pub(crate) behind: u32,
+}
+
+impl<'a> Frames<'a> {
+    const_fn! {
+        pub(crate) fn new() -> Self {
+            Self {
+                state: Mutex::new(Frame {
+                    carried: None,
+                    behind: 0,
+                }),
+            }
+        }
+    }
+
+    /// Lock the carried values, unless another thread is already dropping them.
+    pub(crate) fn try_lock(&self) -> Option<MutexGuard<'_, Frame<'a>>> {
+        match self.state.try_lock() {
+            Ok(frame) => Some(frame),
+            // A destructor panicked, but the values left are still fine to drop.
+            Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        }
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut Frame<'a> {
+        (self.state.get_mut()).unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for Frames<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::test_util::CallOnDrop;
+    use crate::Bin;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn carries_forward() {
+        let dropped = AtomicUsize::new(0);
+        let bin = Bin::new();
+        for _ in 0..100 {
+            bin.add(CallOnDrop(|| {
+                thread::sleep(Duration::from_micros(200));
+                dropped.fetch_add(1, SeqCst);
+            }));
+        }
+
+        let report = bin.end_frame(Duration::from_millis(2));
+        assert!(report.dropped < 100);
+        assert_eq!(report.dropped + report.carried, 100);
+        assert_eq!(report.frames_behind, 1);
+
+        // Values added since are left for after the carried ones.
+        bin.add(CallOnDrop(|| _ = dropped.fetch_add(1, SeqCst)));
+        let report = bin.end_frame(Duration::MAX);
+        assert_eq!(report.carried, 0);
+        assert_eq!(report.frames_behind, 0);
+        assert_eq!(dropped.load(SeqCst), 100);
+
+        bin.end_frame(Duration::MAX);
+        assert_eq!(dropped.load(SeqCst), 101);
+    }
+
+    #[test]
+    #[cfg(feature = "drop-times")]
+    fn learned_drop_times() {
+        let slow = || thread::sleep(Duration::from_millis(20));
+        let bin = Bin::new();
+        bin.add(CallOnDrop(slow));
+        bin.clear();
+
+        // The value is expected to take longer than the whole frame.
+        bin.add(CallOnDrop(slow));
+        let report = bin.end_frame(Duration::from_millis(5));
+        assert_eq!((report.dropped, report.carried), (0, 1));
+
+        let report = bin.end_frame(Duration::MAX);
+        assert_eq!((report.dropped, report.carried), (1, 0));
+    }
+}
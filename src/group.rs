@@ -0,0 +1,72 @@
+use crate::Bin;
+use crate::Key;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+
+/// A set of related values in a bin that are dropped together, created by [`Bin::group`].
+///
+/// Partial clears such as [`Bin::clear_oldest`] and the chunks of
+/// [`Bin::clear_yielding`] never split a group: if they drop one of its values, they drop the
+/// rest of the group along with it.
+pub struct Group<'b, 'a> {
+    bin: &'b Bin<'a>,
+    id: Key,
+}
+
+impl<'b, 'a> Group<'b, 'a> {
+    pub(crate) fn new(bin: &'b Bin<'a>) -> Self {
+        Self {
+            bin,
+            id: Key::next(),
+        }
+    }
+
+    /// Add a value to the bin as part of the group.
+    ///
+    /// Like [`Bin::add`], the value may be dropped immediately.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub fn add<T: Send + 'a>(&self, value: T) {
+        if let Some(inner) = self.bin.adding::<T>().inner() {
+            inner.add_grouped(value, self.id);
+        }
+    }
+}
+
+impl Debug for Group<'_, '_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Group").finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::test_util::CallOnDrop;
+    use crate::Bin;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+
+    #[test]
+    fn group() {
+        let dropped = AtomicUsize::new(0);
+        let value = || {
+            CallOnDrop(|| {
+                dropped.fetch_add(1, SeqCst);
+            })
+        };
+        let bin = Bin::new();
+
+        bin.add(value());
+        let group = bin.group();
+        for _ in 0..3 {
+            group.add(value());
+        }
+        bin.add(value());
+
+        // The group is dropped whole, even though only two values were asked for.
+        assert_eq!(bin.clear_oldest(2), 4);
+        assert_eq!(dropped.load(SeqCst), 4);
+        assert_eq!(bin.clear_newest(1), 1);
+        assert_eq!(dropped.load(SeqCst), 5);
+    }
+}
@@ -0,0 +1,124 @@
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::time::Duration;
+use std::time::Instant;
+
+/// How many adds there are between checks of a bin's growth.
+const CHECK_EVERY: usize = 64;
+
+/// A warning that a bin has kept growing without being cleared, given to the callback set with
+/// [`Builder::warn_on_growth`](crate::Builder::warn_on_growth).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct GrowthWarning {
+    /// How long the bin has been growing for.
+    pub duration: Duration,
+    /// The number of bytes of storage that the bin's values take up, as given by
+    /// [`Bin::bytes_used`](crate::Bin::bytes_used).
+    pub bytes: usize,
+}
+
+/// Watches for bins that grow for longer than a window, set with
+/// [`Builder::warn_on_growth`](crate::Builder::warn_on_growth).
+#[derive(Debug)]
+pub(crate) struct GrowthMonitor {
+    window: Duration,
+    on_growth: fn(&GrowthWarning),
+    /// The number of values added since the bin was created, for spacing out checks.
+    adds: AtomicUsize,
+    /// The stretch of growth the bin is in, from when it was first checked.
+    growth: Mutex<Option<Growth>>,
+}
+
+/// A stretch of time over which a bin's epoch hasn't changed and it hasn't shrunk.
+#[derive(Debug)]
+struct Growth {
+    since: Instant,
+    epoch: u64,
+    bytes: usize,
+    /// Whether the callback has been called for this stretch.
+    warned: bool,
+}
+
+impl GrowthMonitor {
+    pub(crate) const fn new(window: Duration, on_growth: fn(&GrowthWarning)) -> Self {
+        Self {
+            window,
+            on_growth,
+            adds: AtomicUsize::new(0),
+            growth: Mutex::new(None),
+        }
+    }
+
+    /// Record that a value has been added, checking every so often whether the bin has been
+    /// growing for too long. `measure` gets the bin's epoch and bytes used.
+    pub(crate) fn added(&self, measure: impl FnOnce() -> (u64, usize)) {
+        if !(self.adds.fetch_add(1, Ordering::Relaxed)).is_multiple_of(CHECK_EVERY) {
+            return;
+        }
+        let (epoch, bytes) = measure();
+        let now = Instant::now();
+
+        let mut guard = self.growth.lock().unwrap_or_else(PoisonError::into_inner);
+        let growth = match &mut *guard {
+            Some(growth) if growth.epoch == epoch && bytes >= growth.bytes => growth,
+            // The bin has been cleared, so it starts growing again from here.
+            growth => growth.insert(Growth {
+                since: now,
+                epoch,
+                bytes,
+                warned: false,
+            }),
+        };
+        growth.bytes = bytes;
+        let duration = now.duration_since(growth.since);
+        if growth.warned || duration < self.window {
+            return;
+        }
+        growth.warned = true;
+        drop(guard);
+
+        (self.on_growth)(&GrowthWarning { duration, bytes });
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::Bin;
+    use crate::GrowthWarning;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn warns_once() {
+        static WARNINGS: AtomicUsize = AtomicUsize::new(0);
+        fn on_growth(_: &GrowthWarning) {
+            WARNINGS.fetch_add(1, SeqCst);
+        }
+        let bin = Bin::builder()
+            .warn_on_growth(Duration::from_millis(20), on_growth)
+            .build();
+        let add_some = || (0..64).for_each(|_| bin.add(5_u64));
+
+        add_some();
+        thread::sleep(Duration::from_millis(30));
+        add_some();
+        assert_eq!(WARNINGS.load(SeqCst), 1);
+        thread::sleep(Duration::from_millis(30));
+        add_some();
+        assert_eq!(WARNINGS.load(SeqCst), 1);
+
+        // Clearing the bin starts a new stretch of growth.
+        bin.clear();
+        add_some();
+        add_some();
+        assert_eq!(WARNINGS.load(SeqCst), 1);
+        thread::sleep(Duration::from_millis(30));
+        add_some();
+        assert_eq!(WARNINGS.load(SeqCst), 2);
+    }
+}
@@ -0,0 +1,166 @@
+use crate::weak_bins::WeakBins;
+use crate::Bin;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::time::Instant;
+
+/// Clears bins while a GUI event loop is idle, as much as fits before a deadline.
+///
+/// Event loops such as winit's, GTK's or egui's have a hook that runs once they have handled
+/// every pending event. Calling [`run_until`](Self::run_until) from there with the time the next
+/// frame or timer is due drops values from the registered bins, a few at a time with
+/// [`Bin::end_frame`], until the deadline. It returns whether there is more to do, so the loop
+/// only needs to keep waking up while there is.
+///
+/// # Example
+///
+/// With winit, this goes in the `about_to_wait` method of the application handler:
+///
+/// ```no_run
+/// # use drop_bin::IdleCleaner;
+/// # use std::time::Duration;
+/// # use std::time::Instant;
+/// # // Stand-ins for winit's types, with the same shape.
+/// # enum ControlFlow {
+/// #     Poll,
+/// #     WaitUntil(Instant),
+/// # }
+/// # struct ActiveEventLoop;
+/// # impl ActiveEventLoop {
+/// #     fn set_control_flow(&self, _: ControlFlow) {}
+/// # }
+/// # trait ApplicationHandler {
+/// #     fn about_to_wait(&mut self, event_loop: &ActiveEventLoop);
+/// # }
+/// struct App {
+///     cleaner: IdleCleaner,
+///     last_frame: Instant,
+/// }
+///
+/// impl ApplicationHandler for App {
+///     fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+///         let next_frame = self.last_frame + Duration::from_millis(16);
+///         if self.cleaner.run_until(next_frame) {
+///             // Come back to the rest straight away if nothing else needs doing.
+///             event_loop.set_control_flow(ControlFlow::Poll);
+///         } else {
+///             event_loop.set_control_flow(ControlFlow::WaitUntil(next_frame));
+///         }
+///     }
+/// }
+/// ```
+///
+/// Elsewhere, the deadline is whenever the loop next has something to do:
+///
+/// ```
+/// use drop_bin::Bin;
+/// use drop_bin::IdleCleaner;
+/// use std::sync::Arc;
+/// use std::time::Duration;
+/// use std::time::Instant;
+///
+/// let cleaner = IdleCleaner::new();
+/// let textures = Arc::new(Bin::new());
+/// cleaner.register(&textures);
+///
+/// textures.add(vec![0_u8; 4096]);
+/// let more = cleaner.run_until(Instant::now() + Duration::from_millis(4));
+/// ```
+pub struct IdleCleaner {
+    bins: WeakBins,
+    /// The index of the bin to start from next time, so that every bin gets a turn.
+    next: Mutex<usize>,
+}
+
+impl IdleCleaner {
+    /// Create a cleaner with no bins registered.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            bins: WeakBins::new(),
+            next: Mutex::new(0),
+        }
+    }
+
+    /// Clear the bin when the event loop is idle, until it is dropped.
+    pub fn register(&self, bin: &Arc<Bin<'static>>) {
+        self.bins.register(bin, ());
+    }
+
+    /// Drop values from the registered bins until the deadline, returning whether any were left.
+    ///
+    /// The bins take turns, starting from where the last call left off. Values that don't fit
+    /// are carried forward to the next call, as with [`Bin::end_frame`].
+    pub fn run_until(&self, deadline: Instant) -> bool {
+        let mut bins = self.bins.alive();
+        let len = bins.len();
+        let start = self.next().min(len);
+        bins.rotate_left(start);
+
+        let mut visited = 0;
+        let mut more = false;
+        for (bin, ()) in &bins {
+            let now = Instant::now();
+            if now >= deadline {
+                more = true;
+                break;
+            }
+            more |= bin.end_frame(deadline - now).carried != 0;
+            visited += 1;
+        }
+        drop(bins);
+
+        // Start with the first bin that didn't get a turn next time.
+        *self.next.lock().unwrap_or_else(PoisonError::into_inner) = (start + visited) % len.max(1);
+        more
+    }
+
+    fn next(&self) -> usize {
+        *self.next.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for IdleCleaner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for IdleCleaner {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IdleCleaner")
+            .field("bins", &self.bins.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::Bin;
+    use crate::IdleCleaner;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    #[test]
+    fn run_until() {
+        let cleaner = IdleCleaner::new();
+        let bins: Vec<_> = (0..2).map(|_| Arc::new(Bin::new())).collect();
+        for bin in &bins {
+            cleaner.register(bin);
+            bin.add(5_u64);
+        }
+
+        // Nothing fits before a deadline that has passed.
+        assert!(cleaner.run_until(Instant::now()));
+        assert!(bins.iter().all(|bin| bin.estimate_clear_cost().items == 1));
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        assert!(!cleaner.run_until(deadline));
+        assert!(bins.iter().all(|bin| bin.estimate_clear_cost().items == 0));
+    }
+}
@@ -0,0 +1,89 @@
+use crate::inner::Detached;
+use crate::sync::atomic::AtomicUsize;
+use crate::sync::const_fn;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+use std::sync::TryLockError;
+
+/// Drops a few of a bin's values every so many adds, set with
+/// [`Builder::incremental`](crate::Builder::incremental).
+///
+/// Like an incremental garbage collector, the work of clearing is spread over the adds instead of
+/// happening all at once. The bin's values are taken out in one go when there are none left to
+/// drop, and then dropped a budget at a time.
+#[derive(Debug)]
+pub(crate) struct Incremental<'a> {
+    every: usize,
+    budget: usize,
+    adds: AtomicUsize,
+    /// The values that are being dropped.
+    retiring: Mutex<Option<Detached<'a>>>,
+}
+
+impl<'a> Incremental<'a> {
+    const_fn! {
+        pub(crate) fn new(every: usize, budget: usize) -> Self {
+            Self {
+                every: if every == 0 { 1 } else { every },
+                budget,
+                adds: AtomicUsize::new(0),
+                retiring: Mutex::new(None),
+            }
+        }
+    }
+
+    /// Record an add, returning whether some values should be dropped.
+    pub(crate) fn should_step(&self) -> bool {
+        let adds = self.adds.fetch_add(1, Ordering::Relaxed);
+        (adds + 1).is_multiple_of(self.every)
+    }
+
+    /// The number of values to drop in each step.
+    pub(crate) fn budget(&self) -> usize {
+        self.budget
+    }
+
+    /// Lock the values that are being dropped, unless another thread is already dropping them.
+    pub(crate) fn try_lock(&self) -> Option<MutexGuard<'_, Option<Detached<'a>>>> {
+        match self.retiring.try_lock() {
+            Ok(retiring) => Some(retiring),
+            // A destructor panicked, but the values left are still fine to drop.
+            Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        }
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut Option<Detached<'a>> {
+        (self.retiring.get_mut()).unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::test_util::CallOnDrop;
+    use crate::Bin;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+
+    #[test]
+    fn incremental() {
+        let dropped = AtomicUsize::new(0);
+        let bin = Bin::builder().incremental(5, 2).build();
+
+        for _ in 0..10 {
+            bin.add(CallOnDrop(|| {
+                dropped.fetch_add(1, SeqCst);
+            }));
+        }
+        // The first five values were taken out on the fifth add, and two were dropped then and
+        // two more on the tenth.
+        assert_eq!(dropped.load(SeqCst), 4);
+        assert_eq!(bin.epoch(), 2);
+
+        // Clearing drops the rest of the batch too.
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 10);
+    }
+}
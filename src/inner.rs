@@ -1,13 +1,404 @@
+use crate::asan;
+#[cfg(feature = "decommit")]
+use crate::decommit;
+#[cfg(feature = "drop-times")]
+use crate::drop_times::DropTimes;
+#[cfg(all(feature = "mmap", unix))]
+use crate::mmap::Bytes;
+use crate::sync::const_fn;
+use crate::sync::thread_local;
+use crate::sync::Mutex;
+use crate::sync::TryMutex;
+use crate::sync::TryMutexGuard;
+#[cfg(feature = "watchdog")]
+use crate::watchdog::Watch;
+#[cfg(feature = "watchdog")]
+use crate::watchdog::Watchdog;
+use crate::zeroize;
+use crate::ClearReport;
 use crate::ConcurrentList;
+#[cfg(not(feature = "crossbeam"))]
 use crate::ConcurrentVec;
+use crate::Key;
+use crate::KeyMap;
+use crate::KeySet;
+use crate::PanicPolicy;
+use crate::Payload;
+use crate::SegmentAlloc;
+use crate::Tag;
+#[cfg(feature = "debug-types")]
+use crate::ValueMeta;
+#[cfg(feature = "boxed-storage")]
+use std::alloc;
+#[cfg(feature = "boxed-storage")]
+use std::alloc::Layout;
+use std::any::type_name;
+use std::any::Any;
+use std::cell::Cell;
+#[cfg(not(feature = "boxed-storage"))]
 use std::cmp::max;
+use std::cmp::min;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::hash::BuildHasherDefault;
 use std::marker::PhantomData;
 use std::mem;
+#[cfg(not(feature = "boxed-storage"))]
+use std::mem::align_of;
+#[cfg(not(feature = "boxed-storage"))]
+use std::mem::size_of;
+#[cfg(not(all(feature = "boxed-storage", feature = "mmap", unix)))]
 use std::mem::MaybeUninit;
+#[cfg(feature = "debug-types")]
+use std::panic::Location;
 use std::ptr;
-use try_mutex::TryMutex;
+use std::ptr::NonNull;
+// Cache IDs only need to be unique, so they are left out of loom's model.
+use std::sync::atomic::AtomicUsize as StdAtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::PoisonError;
+#[cfg(feature = "drop-times")]
+use std::time::Duration;
+use std::time::Instant;
 
-type Destructor = unsafe fn(*mut ());
+/// The cache ID to give to the next bin that needs one.
+static NEXT_CACHE_ID: StdAtomicUsize = StdAtomicUsize::new(1);
+
+// Loom's `thread_local!` doesn't support `const` initializers.
+thread_local! {
+    /// The storage this thread last added a value to, and the cache ID of the bin it is in.
+    #[cfg(not(feature = "boxed-storage"))]
+    #[allow(clippy::missing_const_for_thread_local)]
+    static LAST_STORAGE: Cell<(usize, *const Storage)> = Cell::new((0, ptr::null()));
+
+    /// This thread's buffer of entries, and the cache ID of the bin it is in.
+    #[allow(clippy::missing_const_for_thread_local)]
+    static LAST_BUFFER: Cell<(usize, *const Buffer)> = Cell::new((0, ptr::null()));
+
+    /// The types this thread has recently added, by destructor, with how many of each it added.
+    #[cfg(not(feature = "boxed-storage"))]
+    #[allow(clippy::missing_const_for_thread_local)]
+    static RECENT_TYPES: Cell<[(usize, u32); 4]> = Cell::new([(0, 0); 4]);
+}
+
+/// How many values of a type a thread must add before they are given storages of their own.
+#[cfg(not(feature = "boxed-storage"))]
+const SLAB_THRESHOLD: u32 = 8;
+
+/// The size in bytes above which values are given storages of their own, so that the large
+/// storages they need aren't reused for small values.
+#[cfg(not(feature = "boxed-storage"))]
+const HUGE_VALUE: usize = 64 * 1024;
+
+/// The number of emptied storages a bin keeps for reuse. The smallest are freed past this.
+const MAX_FREE: usize = 16;
+
+pub(crate) type Destructor = unsafe fn(*mut ());
+
+/// A function that drops a slice, given a pointer to its start and its length.
+type SliceDestructor = unsafe fn(*mut (), usize);
+
+/// How an entry's values are dropped.
+#[derive(Debug, Clone, Copy)]
+enum Destroy {
+    /// The given number of values of a kind, stored one after another.
+    Kind(&'static Kind, usize),
+    /// A value added with `add_raw`, dropped by its destructor.
+    Raw(Destructor),
+}
+
+/// The function that drops the values of a kind.
+#[derive(Debug, Clone, Copy)]
+enum DropFn {
+    /// Drops a single value.
+    Value(Destructor),
+    /// Drops a slice of values, given its length.
+    Slice(SliceDestructor),
+}
+
+/// How values of one type that were added in the same way are dropped and inspected, shared by
+/// all of their entries.
+#[derive(Debug)]
+struct Kind {
+    /// The function that drops the values.
+    drop: DropFn,
+    /// The number of bytes of the bin's storage that each value takes up, which is 0 for values
+    /// with allocations of their own.
+    size: usize,
+    /// Turns a pointer to a value into a trait object, for values that can be inspected.
+    as_any: Option<fn(*mut ()) -> *mut (dyn Any + Send)>,
+    /// The function that checks whether a value can be dropped yet, for values added with
+    /// `add_when`.
+    ready: Option<unsafe fn(*mut ()) -> bool>,
+    /// Gets the name of the values' type.
+    #[cfg_attr(not(feature = "debug-types"), allow(dead_code))]
+    type_name: fn() -> &'static str,
+    /// The function that frees a value's allocation without dropping it.
+    #[cfg_attr(not(feature = "boxed-storage"), allow(dead_code))]
+    dealloc: Destructor,
+}
+
+impl Kind {
+    /// A kind of value that can't be inspected or held back from clears.
+    const fn new(
+        drop: DropFn,
+        size: usize,
+        type_name: fn() -> &'static str,
+        dealloc: Destructor,
+    ) -> Self {
+        Self {
+            drop,
+            size,
+            as_any: None,
+            ready: None,
+            type_name,
+            dealloc,
+        }
+    }
+}
+
+/// The kinds of entry for values of type `T`.
+struct KindOf<T>(PhantomData<T>);
+
+impl<T> KindOf<T> {
+    /// A value in the bin's storage.
+    #[cfg(not(feature = "boxed-storage"))]
+    const STORED: Kind = Kind::new(
+        DropFn::Slice(drop_slice::<T>),
+        size_of::<T>(),
+        type_name::<T>,
+        |_| {},
+    );
+    /// A value in an allocation of its own.
+    #[cfg(feature = "boxed-storage")]
+    const STORED: Kind = Kind::new(
+        DropFn::Value(drop_box::<T>),
+        0,
+        type_name::<T>,
+        dealloc_box::<T>,
+    );
+    /// A run of values stored one after another.
+    #[cfg(not(feature = "boxed-storage"))]
+    const RUN: Kind = Kind::new(
+        DropFn::Slice(drop_slice::<T>),
+        size_of::<T>(),
+        type_name::<[T]>,
+        |_| {},
+    );
+    /// A boxed slice, whose elements are left where they are.
+    const BOXED_SLICE: Kind = Kind::new(
+        DropFn::Slice(drop_boxed_slice::<T>),
+        0,
+        type_name::<Box<[T]>>,
+        |_| {},
+    );
+    /// A value placed with `emplace`, whose allocation is never freed without dropping it.
+    const EMPLACED: Kind = Kind::new(DropFn::Value(drop_box::<T>), 0, type_name::<T>, |_| {});
+}
+
+impl<T: Any + Send> KindOf<T> {
+    /// A value that can be inspected with `any_values`.
+    const ANY: Kind = Kind {
+        as_any: Some(as_any::<T>),
+        ..Self::STORED
+    };
+}
+
+impl<T, F: Fn() -> bool> KindOf<(T, F)> {
+    /// A value added with `add_when`, boxed along with its check.
+    const WHEN: Kind = Kind {
+        ready: Some(check_ready::<T, F>),
+        ..Kind::new(
+            DropFn::Value(drop_box::<(T, F)>),
+            0,
+            type_name::<T>,
+            dealloc_box::<(T, F)>,
+        )
+    };
+}
+
+/// What a value was added with besides itself.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Labels {
+    /// The tag the value was added with, if any.
+    tag: Option<Tag>,
+    /// The key the value was added with, if any.
+    key: Option<Key>,
+    /// The key of the value that this one must be dropped after, if any.
+    after: Option<Key>,
+    /// The group the value was added to, if any.
+    group: Option<Key>,
+    /// The region the value was added through, if any.
+    region: Option<Key>,
+}
+
+/// What an entry records about its value beyond how to drop it.
+///
+/// Most values have none of this, so it is kept out of the entry in an allocation that the copies
+/// of the entry share.
+#[derive(Debug)]
+struct Extra {
+    /// What the value was added with.
+    labels: Labels,
+    /// When the value was added.
+    #[cfg(feature = "debug-types")]
+    added: Instant,
+    /// Where the value was added from.
+    #[cfg(feature = "debug-types")]
+    caller: &'static Location<'static>,
+}
+
+/// A value in the bin.
+#[derive(Debug, Clone)]
+struct Entry {
+    /// A pointer to the value.
+    ptr: *mut (),
+    /// How the value is dropped.
+    destructor: Destroy,
+    /// What else is recorded about the value, if anything.
+    extra: Option<Arc<Extra>>,
+}
+
+/// The main list of entries.
+#[cfg(not(feature = "crossbeam"))]
+type Entries = ConcurrentVec<Entry>;
+/// The main list of entries.
+#[cfg(feature = "crossbeam")]
+type Entries = crossbeam_queue::SegQueue<Entry>;
+
+/// Entries added by one thread that have not been moved to the main list yet.
+type Buffer = TryMutex<Vec<Entry>>;
+
+// SAFETY: Entries point to values that can be dropped on any thread, and are only used to drop
+// them.
+unsafe impl Send for Entry {}
+unsafe impl Sync for Entry {}
+
+impl Entry {
+    /// Make an entry for values at the given pointer.
+    ///
+    /// Only values with labels, or with debug information to record, are given an `Extra`.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    fn new(ptr: *mut (), destructor: Destroy, labels: Labels) -> Self {
+        let extra = if cfg!(feature = "debug-types") || labels != Labels::default() {
+            Some(Arc::new(Extra {
+                labels,
+                #[cfg(feature = "debug-types")]
+                added: Instant::now(),
+                #[cfg(feature = "debug-types")]
+                caller: Location::caller(),
+            }))
+        } else {
+            None
+        };
+        Self {
+            ptr,
+            destructor,
+            extra,
+        }
+    }
+
+    /// Drop the value.
+    ///
+    /// # Safety
+    ///
+    /// This must only be called once, while the value's storage is still alive.
+    unsafe fn drop_value(self) {
+        match self.destructor {
+            Destroy::Kind(kind, len) => match kind.drop {
+                DropFn::Value(destructor) => unsafe { destructor(self.ptr) },
+                DropFn::Slice(destructor) => unsafe { destructor(self.ptr, len) },
+            },
+            Destroy::Raw(destructor) => unsafe { destructor(self.ptr) },
+        }
+    }
+
+    /// Get the kind of the values, unless they were added with `add_raw`.
+    fn kind(&self) -> Option<&'static Kind> {
+        match self.destructor {
+            Destroy::Kind(kind, _) => Some(kind),
+            Destroy::Raw(_) => None,
+        }
+    }
+
+    /// Get what the value was added with.
+    fn labels(&self) -> Labels {
+        self.extra
+            .as_ref()
+            .map_or_else(Labels::default, |extra| extra.labels)
+    }
+
+    /// Get the number of bytes of the bin's storage that the values take up.
+    fn size(&self) -> usize {
+        match self.destructor {
+            Destroy::Kind(kind, len) => kind.size * len,
+            Destroy::Raw(_) => 0,
+        }
+    }
+
+    /// Get the function that checks whether the value can be dropped yet, if it has one.
+    fn ready(&self) -> Option<unsafe fn(*mut ()) -> bool> {
+        self.kind().and_then(|kind| kind.ready)
+    }
+
+    /// Check whether the value can be dropped yet.
+    fn is_ready(&self) -> bool {
+        // SAFETY: The check is given the pointer of the value it was added with, which is alive.
+        self.ready().is_none_or(|ready| unsafe { ready(self.ptr) })
+    }
+
+    /// Identify the value's type by the function that drops it.
+    fn destructor_key(&self) -> usize {
+        match self.destructor {
+            Destroy::Kind(kind, _) => match kind.drop {
+                DropFn::Value(destructor) => destructor as usize,
+                DropFn::Slice(destructor) => destructor as usize,
+            },
+            Destroy::Raw(destructor) => destructor as usize,
+        }
+    }
+
+    /// Get the name of the value's type.
+    #[cfg(feature = "debug-types")]
+    fn type_name(&self) -> &'static str {
+        self.kind().map_or("*mut ()", |kind| (kind.type_name)())
+    }
+
+    /// Get the name of the value's type, if it was recorded.
+    #[cfg(feature = "watchdog")]
+    #[cfg_attr(not(feature = "debug-types"), allow(clippy::unused_self))]
+    #[cfg_attr(feature = "debug-types", allow(clippy::unnecessary_wraps))]
+    fn recorded_type_name(&self) -> Option<&'static str> {
+        #[cfg(feature = "debug-types")]
+        return Some(self.type_name());
+        #[cfg(not(feature = "debug-types"))]
+        None
+    }
+
+    /// Get the debug information recorded about the value.
+    #[cfg(feature = "debug-types")]
+    fn debug_info(&self) -> &Extra {
+        // Every entry has debug information when it is recorded.
+        self.extra
+            .as_deref()
+            .expect("entry has no debug information")
+    }
+
+    /// Forget the value without dropping it.
+    ///
+    /// # Safety
+    ///
+    /// This must only be called once, and the value must not be dropped afterward.
+    #[cfg_attr(not(feature = "boxed-storage"), allow(clippy::unused_self))]
+    unsafe fn forget(self) {
+        #[cfg(feature = "boxed-storage")]
+        if let Some(kind) = self.kind() {
+            unsafe { (kind.dealloc)(self.ptr) };
+        }
+    }
+}
 
 /// The inner data of a bin.
 ///
@@ -15,196 +406,1891 @@ type Destructor = unsafe fn(*mut ());
 #[derive(Debug, Default)]
 pub(crate) struct Inner<'a> {
     /// Pointers to the data and its destructors.
-    destructors: ConcurrentVec<(*mut (), Destructor)>,
+    destructors: Entries,
+    /// Entries that have not been moved to `destructors` yet. Each thread adds to its own buffer,
+    /// so that adds don't contend with each other.
+    buffers: ConcurrentList<Buffer>,
     /// The linked list of backing storage behind the pointers in `destructors`.
     data: ConcurrentList<Storage>,
+    /// The entries with keys that are in `destructors` or a buffer, so that they can be found
+    /// without searching.
+    keyed: Mutex<KeyMap<Entry>>,
+    /// The keys of entries that have been removed by `cancel` or `drop_now`, but are still in
+    /// `destructors` or a buffer. They are skipped when the entries are taken out.
+    removed: KeySet,
+    /// Storages that have been emptied by a clear, ready to be reused.
+    free: Mutex<Vec<Storage>>,
+    /// The number of bytes in `data` taken up by values that are no longer in the bin, which
+    /// aren't reused until the bin is emptied.
+    released: usize,
+    /// The size that storages stop growing at.
+    pub(crate) max_segment_size: usize,
+    /// What to do when a destructor panics.
+    pub(crate) on_panic: PanicPolicy,
+    /// Called whenever a new storage is allocated.
+    pub(crate) on_segment_alloc: Option<fn(&SegmentAlloc)>,
+    /// Whether to decommit the pages of storages when they are emptied.
+    #[cfg(feature = "decommit")]
+    pub(crate) decommit: bool,
+    /// Watches for destructors that run for too long.
+    #[cfg(feature = "watchdog")]
+    pub(crate) watchdog: Option<Watchdog>,
+    /// The payloads of destructor panics collected by `PanicPolicy::Collect`.
+    panics: Mutex<Vec<Payload>>,
+    /// Identifies the bin to the thread-local caches of its storages and buffers, or 0 if it has
+    /// not been cached. This is reset whenever storages are removed, so stale cache entries are
+    /// never used.
+    cache_id: StdAtomicUsize,
     invariant_over_lifetime_a: PhantomData<fn(&'a ()) -> &'a ()>,
 }
 
+/// The bytes of a storage.
+#[cfg(not(all(feature = "mmap", unix)))]
+type Bytes = Vec<MaybeUninit<u8>>;
+
 /// A segment of backing storage.
 #[derive(Debug, Default)]
 struct Storage {
-    /// The bytes of data this element contains. This `Vec` must never reallocate.
-    bytes: TryMutex<Vec<MaybeUninit<u8>>>,
+    /// The bytes of data this element contains.
+    bytes: TryMutex<Contents>,
     /// The capacity of the above `Vec`. This is stored separately so it can be accessed without
     /// locking the `TryMutex` as it doesn't change.
     capacity: usize,
+    /// Whether the storage was allocated for a single huge value, and so is freed rather than
+    /// reused once the value has been dropped.
+    dedicated: bool,
+    /// The length of the above `Vec`, which can be read without locking the `TryMutex`. It is
+    /// only written to by whoever has the storage locked.
+    used: StdAtomicUsize,
 }
 
-impl<'a> Inner<'a> {
-    pub(crate) const fn new() -> Self {
-        Self {
-            destructors: ConcurrentVec::new(),
-            data: ConcurrentList::new(),
-            invariant_over_lifetime_a: PhantomData,
+impl Storage {
+    /// Allocate an empty storage with the given capacity.
+    fn new(capacity: usize) -> Self {
+        Self::from_bytes(Bytes::with_capacity(capacity), capacity)
+    }
+
+    /// Allocate an empty storage with the given capacity, or return `None` if the allocation
+    /// fails.
+    #[cfg(not(feature = "boxed-storage"))]
+    fn try_new(capacity: usize) -> Option<Self> {
+        #[cfg(all(feature = "mmap", unix))]
+        let bytes = Bytes::try_with_capacity(capacity).ok()?;
+        #[cfg(not(all(feature = "mmap", unix)))]
+        let bytes = {
+            let mut bytes = Bytes::new();
+            bytes.try_reserve_exact(capacity).ok()?;
+            bytes
+        };
+        Some(Self::from_bytes(bytes, capacity))
+    }
+
+    /// Create an empty storage out of newly allocated bytes.
+    fn from_bytes(bytes: Bytes, capacity: usize) -> Self {
+        // Only the bytes of values are accessible; padding and unused capacity are not.
+        // SAFETY: The bytes are the `Vec`'s capacity, and are unpoisoned before values are written.
+        unsafe { asan::poison(bytes.as_ptr(), bytes.capacity()) };
+
+        Self {
+            bytes: TryMutex::new(Contents {
+                bytes,
+                padding: 0,
+                class: SizeClass::default(),
+                slab: None,
+            }),
+            capacity,
+            dedicated: false,
+            used: StdAtomicUsize::new(0),
+        }
+    }
+
+    /// Lock the storage to add values to it.
+    #[cfg(not(feature = "boxed-storage"))]
+    fn lock(&self) -> Option<Locked<'_>> {
+        Some(Locked {
+            storage: self,
+            contents: self.bytes.try_lock()?,
+        })
+    }
+
+    /// Add a value to the storage before it is shared.
+    #[cfg(not(feature = "boxed-storage"))]
+    fn push_mut<T>(&mut self, value: T, place: Placement) -> Result<*mut T, T> {
+        let contents = self.bytes.get_mut();
+        let value_ptr = push_value(contents, value, place)?;
+        *self.used.get_mut() = contents.bytes.len();
+        Ok(value_ptr)
+    }
+}
+
+/// A storage that a thread has locked to add values to.
+#[cfg_attr(feature = "boxed-storage", allow(dead_code))]
+struct Locked<'i> {
+    storage: &'i Storage,
+    contents: TryMutexGuard<'i, Contents>,
+}
+
+#[cfg(not(feature = "boxed-storage"))]
+impl Locked<'_> {
+    /// Add a value to the storage, returning it back if it doesn't fit.
+    fn push<T>(&mut self, value: T, place: Placement) -> Result<*mut T, T> {
+        let value_ptr = push_value(&mut self.contents, value, place)?;
+        (self.storage.used).store(self.contents.bytes.len(), Ordering::Relaxed);
+        Ok(value_ptr)
+    }
+}
+
+/// The contents of a storage.
+#[derive(Debug, Default)]
+struct Contents {
+    /// The values and the padding between them. This must never reallocate.
+    bytes: Bytes,
+    /// How many of the bytes are padding.
+    padding: usize,
+    /// The class of values the storage holds.
+    #[cfg_attr(feature = "boxed-storage", allow(dead_code))]
+    class: SizeClass,
+    /// The type of values the storage is a slab of, if it is dedicated to one type.
+    #[cfg_attr(feature = "boxed-storage", allow(dead_code))]
+    slab: Option<usize>,
+}
+
+/// A class of values that are stored together.
+///
+/// Values are grouped by alignment, so that a few highly aligned values don't leave large gaps
+/// of padding between many small ones.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "boxed-storage", allow(dead_code))]
+enum SizeClass {
+    /// Aligned to at most 8 bytes, like most values.
+    #[default]
+    Small,
+    /// Aligned to at most 64 bytes, like values padded to a cache line.
+    Medium,
+    /// Aligned to more than 64 bytes, like pages.
+    Large,
+}
+
+impl SizeClass {
+    /// Get the class of values of type `T`.
+    #[cfg_attr(feature = "boxed-storage", allow(dead_code))]
+    fn of<T>() -> Self {
+        match align_of::<T>() {
+            0..=8 => Self::Small,
+            9..=64 => Self::Medium,
+            _ => Self::Large,
+        }
+    }
+}
+
+/// The storages a value can be placed in.
+#[cfg(not(feature = "boxed-storage"))]
+#[derive(Clone, Copy)]
+struct Placement {
+    class: SizeClass,
+    /// The type of the value, identified by its destructor.
+    ty: usize,
+    /// Whether values of the type are added often enough to be kept in slabs, so that they are
+    /// laid out contiguously and dropped together.
+    slab: bool,
+}
+
+#[cfg(not(feature = "boxed-storage"))]
+impl Placement {
+    /// Get the placement of a value of type `T` that this thread is adding.
+    fn of<T>() -> Self {
+        let ty = type_key::<T>();
+        Self {
+            class: SizeClass::of::<T>(),
+            ty,
+            slab: RECENT_TYPES
+                .try_with(|recent| count_type(recent, ty))
+                .is_ok_and(|n| n >= SLAB_THRESHOLD),
+        }
+    }
+
+    /// Check whether a value can be placed in a storage with the given contents.
+    fn accepts(self, contents: &Contents) -> bool {
+        contents.class == self.class
+            && match contents.slab {
+                Some(ty) => ty == self.ty,
+                None => !self.slab,
+            }
+    }
+
+    /// Prepare the contents of an empty storage to hold this value.
+    fn assign(self, contents: &mut Contents) {
+        contents.class = self.class;
+        contents.slab = self.slab.then_some(self.ty);
+    }
+}
+
+/// Identify the type `T` by its destructor.
+///
+/// Distinct types can share a destructor, but they are only grouped together, so this is fine.
+#[cfg(not(feature = "boxed-storage"))]
+fn type_key<T>() -> usize {
+    ptr::drop_in_place::<T> as unsafe fn(*mut T) as usize
+}
+
+/// Count a value of the given type in a thread's recent types, returning how many of that type
+/// have been counted.
+#[cfg(not(feature = "boxed-storage"))]
+fn count_type(recent: &Cell<[(usize, u32); 4]>, ty: usize) -> u32 {
+    let mut types = recent.get();
+    let i = types.iter().position(|&(t, _)| t == ty).unwrap_or_else(|| {
+        // Replace the least added type, and age the rest so that types which are no longer
+        // added are eventually forgotten.
+        let (i, _) = (types.iter().enumerate())
+            .min_by_key(|(_, &(_, n))| n)
+            .unwrap();
+        for (_, n) in &mut types {
+            *n /= 2;
+        }
+        types[i] = (ty, 0);
+        i
+    });
+    types[i].1 = types[i].1.saturating_add(1);
+    recent.set(types);
+    types[i].1
+}
+
+/// The storage that a sequence of adds is currently writing to.
+///
+/// Keeping it locked between adds saves searching the storage list for every value.
+#[derive(Default)]
+pub(crate) struct Cursor<'i> {
+    #[cfg_attr(feature = "boxed-storage", allow(dead_code))]
+    storage: Option<Locked<'i>>,
+    /// Whether values are given back if there isn't the memory to store them, rather than the
+    /// process aborting.
+    fallible: bool,
+}
+
+#[cfg(not(feature = "boxed-storage"))]
+impl Cursor<'_> {
+    /// Allocate a new storage for the cursor's adds, or return `None` if it fails and the cursor
+    /// is fallible.
+    fn allocate(&self, capacity: usize) -> Option<Storage> {
+        if self.fallible {
+            Storage::try_new(capacity)
+        } else {
+            Some(Storage::new(capacity))
+        }
+    }
+}
+
+#[cfg(any(feature = "asan", feature = "zeroize"))]
+impl Drop for Storage {
+    fn drop(&mut self) {
+        // Hand the memory back to the allocator in the state it was given to us.
+        let bytes = &mut self.bytes.get_mut().bytes;
+        // SAFETY: The bytes are the `Vec`'s capacity.
+        unsafe { asan::unpoison(bytes.as_ptr(), bytes.capacity()) };
+        // SAFETY: The bytes are part of the `Vec`, and any values in them are gone.
+        unsafe { zeroize::zero(bytes.as_mut_ptr(), bytes.len()) };
+    }
+}
+
+impl<'a> Inner<'a> {
+    const_fn! {
+        pub(crate) fn new() -> Self {
+            Self {
+                destructors: Entries::new(),
+                buffers: ConcurrentList::new(),
+                data: ConcurrentList::new(),
+                keyed: Mutex::new(KeyMap::with_hasher(BuildHasherDefault::new())),
+                removed: KeySet::with_hasher(BuildHasherDefault::new()),
+                free: Mutex::new(Vec::new()),
+                released: 0,
+                max_segment_size: usize::MAX,
+                on_panic: PanicPolicy::Propagate,
+                on_segment_alloc: None,
+                #[cfg(feature = "decommit")]
+                decommit: false,
+                #[cfg(feature = "watchdog")]
+                watchdog: None,
+                panics: Mutex::new(Vec::new()),
+                cache_id: StdAtomicUsize::new(0),
+                invariant_over_lifetime_a: PhantomData,
+            }
+        }
+    }
+
+    /// Add the given value to the bin.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub(crate) fn add<T: Send + 'a>(&self, value: T) {
+        self.add_at(value, None, &mut Cursor::default());
+    }
+
+    /// Add the given value to the bin, giving it back if there isn't the memory to store it.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub(crate) fn try_add<T: Send + 'a>(&self, value: T) -> Result<(), T> {
+        let mut cursor = Cursor {
+            fallible: true,
+            ..Cursor::default()
+        };
+        self.push_entry(self.store_entry(
+            value,
+            &KindOf::<T>::STORED,
+            Labels::default(),
+            &mut cursor,
+        )?);
+        Ok(())
+    }
+
+    /// Add the given value to the bin under a tag.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub(crate) fn add_tagged<T: Send + 'a>(&self, value: T, tag: Tag) {
+        self.add_at(value, Some(tag), &mut Cursor::default());
+    }
+
+    /// Add the given value to the bin, so that it can be inspected with `any_values`.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub(crate) fn add_any<T: Any + Send>(&self, value: T) {
+        let kind = &KindOf::<T>::ANY;
+        if let Ok(entry) = self.store_entry(value, kind, Labels::default(), &mut Cursor::default())
+        {
+            self.push_entry(entry);
+        }
+    }
+
+    /// Add the given value to the bin, so that it can be referred to by the given key.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub(crate) fn add_keyed<T: Send + 'a>(&self, value: T, key: Key) {
+        self.add_labelled(
+            value,
+            Labels {
+                key: Some(key),
+                ..Labels::default()
+            },
+        );
+    }
+
+    /// Add the given value to the bin under the given key, so that it is dropped after the value
+    /// with the key `after`.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub(crate) fn add_after<T: Send + 'a>(&self, value: T, key: Key, after: Key) {
+        self.add_labelled(
+            value,
+            Labels {
+                key: Some(key),
+                after: Some(after),
+                ..Labels::default()
+            },
+        );
+    }
+
+    /// Add the given value to the bin as part of the given group.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub(crate) fn add_grouped<T: Send + 'a>(&self, value: T, group: Key) {
+        self.add_labelled(
+            value,
+            Labels {
+                group: Some(group),
+                ..Labels::default()
+            },
+        );
+    }
+
+    /// Add the given value to the bin through the given region.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub(crate) fn add_in_region<T: Send + 'a>(&self, value: T, region: Key) {
+        self.add_labelled(
+            value,
+            Labels {
+                region: Some(region),
+                ..Labels::default()
+            },
+        );
+    }
+
+    /// Add the given value to the bin with the given labels.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    fn add_labelled<T: Send + 'a>(&self, value: T, labels: Labels) {
+        let kind = &KindOf::<T>::STORED;
+        if let Ok(entry) = self.store_entry(value, kind, labels, &mut Cursor::default()) {
+            self.push_entry(entry);
+        }
+    }
+
+    /// Add the given value to the bin, trying the storage at the cursor first.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub(crate) fn add_at<'i, T: Send + 'a>(
+        &'i self,
+        value: T,
+        tag: Option<Tag>,
+        cursor: &mut Cursor<'i>,
+    ) {
+        let labels = Labels {
+            tag,
+            ..Labels::default()
+        };
+        if let Ok(entry) = self.store_entry(value, &KindOf::<T>::STORED, labels, cursor) {
+            self.push_entry(entry);
+        }
+    }
+
+    /// Store the given value, returning the entry that will drop it.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    fn store_entry<'i, T: Send + 'a>(
+        &'i self,
+        value: T,
+        kind: &'static Kind,
+        labels: Labels,
+        cursor: &mut Cursor<'i>,
+    ) -> Result<Entry, T> {
+        let value_ptr = self.store(value, cursor)?;
+        Ok(Entry::new(
+            value_ptr.cast::<()>(),
+            Destroy::Kind(kind, 1),
+            labels,
+        ))
+    }
+
+    /// Add a raw pointer and its destructor to the bin.
+    ///
+    /// # Safety
+    ///
+    /// See [`Bin::add_raw`](crate::Bin::add_raw).
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub(crate) unsafe fn add_raw(&self, ptr: *mut (), destructor: Destructor) {
+        self.push_entry(Entry::new(ptr, Destroy::Raw(destructor), Labels::default()));
+    }
+
+    /// Add the given value to the bin in an allocation of its own, so that clears can leave it
+    /// behind until `ready` returns true.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub(crate) fn add_when<T: Send + 'a, F: Fn() -> bool + Send + 'a>(&self, value: T, ready: F) {
+        self.push_entry(Entry::new(
+            Box::into_raw(Box::new((value, ready))).cast::<()>(),
+            Destroy::Kind(&KindOf::<(T, F)>::WHEN, 1),
+            Labels::default(),
+        ));
+    }
+
+    /// Add a boxed slice to the bin as a single entry, leaving its elements where they are.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub(crate) fn add_slice<T: Send + 'a>(&self, slice: Box<[T]>) {
+        let len = slice.len();
+        self.push_entry(Entry::new(
+            Box::into_raw(slice).cast::<()>(),
+            Destroy::Kind(&KindOf::<T>::BOXED_SLICE, len),
+            Labels::default(),
+        ));
+    }
+
+    /// Add a value to the bin in its own allocation, returning a pointer to it that stays valid
+    /// until the value is dropped.
+    ///
+    /// The allocation is never freed without the value's destructor having run: if the value is
+    /// forgotten, its allocation is leaked.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub(crate) fn emplace<T: Send + 'a>(&self, value: T) -> NonNull<T> {
+        let ptr = NonNull::from(Box::leak(Box::new(value)));
+        self.push_entry(Entry::new(
+            ptr.as_ptr().cast::<()>(),
+            Destroy::Kind(&KindOf::<T>::EMPLACED, 1),
+            Labels::default(),
+        ));
+        ptr
+    }
+
+    /// Record an entry in this thread's buffer, or directly in `destructors` if that fails.
+    fn push_entry(&self, entry: Entry) {
+        if let Some(key) = entry.labels().key {
+            (self.keyed.lock())
+                .unwrap_or_else(PoisonError::into_inner)
+                .insert(key, entry.clone());
+        }
+        if let Some(mut buffer) = self.buffer() {
+            buffer.push(entry);
+        } else {
+            self.destructors.push(entry);
+        }
+    }
+
+    /// Lock this thread's buffer of entries, creating it if it doesn't exist.
+    fn buffer(&self) -> Option<TryMutexGuard<'_, Vec<Entry>>> {
+        let id = self.cache_id();
+        let (cached_id, cached) = LAST_BUFFER.try_with(Cell::get).ok()?;
+        if cached_id == id {
+            // SAFETY: Buffers are only removed when the bin is dropped.
+            if let Some(buffer) = unsafe { &*cached }.try_lock() {
+                return Some(buffer);
+            }
+        }
+
+        // Take over any buffer that isn't in use, such as one from a thread that has exited.
+        let (buffer, guard) = (self.buffers.iter())
+            .find_map(|buffer| Some((buffer, buffer.try_lock()?)))
+            .or_else(|| {
+                let buffer = self.buffers.push(TryMutex::new(Vec::new()));
+                Some((buffer, buffer.try_lock()?))
+            })?;
+        let _ = LAST_BUFFER.try_with(|last| last.set((id, buffer)));
+        Some(guard)
+    }
+
+    /// Check whether the bin has no values in it.
+    pub(crate) fn is_empty(&mut self) -> bool {
+        self.flush_buffers();
+        if self.removed.is_empty() {
+            self.destructors.is_empty()
+        } else {
+            self.destructors.len() == self.removed.len()
+        }
+    }
+
+    /// Get the number of values in the bin.
+    #[cfg(all(test, not(loom)))]
+    fn len(&mut self) -> usize {
+        self.flush_buffers();
+        self.destructors.len() - self.removed.len()
+    }
+
+    /// Move the entries in every thread's buffer to `destructors`.
+    fn flush_buffers(&mut self) {
+        for buffer in self.buffers.iter_mut() {
+            for entry in buffer.get_mut().drain(..) {
+                self.destructors.push(entry);
+            }
+        }
+    }
+
+    /// Store the given value in its own allocation.
+    ///
+    /// This avoids the custom storage segments entirely, so that tools like Miri and sanitizers can
+    /// check every value individually.
+    #[cfg(feature = "boxed-storage")]
+    #[allow(clippy::unused_self)]
+    fn store<T: Send + 'a>(&self, value: T, cursor: &mut Cursor<'_>) -> Result<*mut T, T> {
+        let layout = Layout::new::<T>();
+        if !cursor.fallible || layout.size() == 0 {
+            return Ok(Box::into_raw(Box::new(value)));
+        }
+        // SAFETY: The layout isn't zero-sized.
+        let Some(ptr) = NonNull::new(unsafe { alloc::alloc(layout) }.cast::<T>()) else {
+            return Err(value);
+        };
+        // SAFETY: The memory was allocated for a `T` by the global allocator, like a `Box`.
+        unsafe { ptr.as_ptr().write(value) };
+        Ok(ptr.as_ptr())
+    }
+
+    /// Store the given value in the bin.
+    ///
+    /// Returns a pointer to the value, or gives the value back if it failed. The storage the value
+    /// was placed in is left locked in `cursor`.
+    #[cfg(not(feature = "boxed-storage"))]
+    fn store<'i, T: Send + 'a>(
+        &'i self,
+        mut value: T,
+        cursor: &mut Cursor<'i>,
+    ) -> Result<*mut T, T> {
+        if size_of::<T>() == 0 {
+            mem::forget(value);
+
+            // We can use a dangling pointer for zero sized types, as long as it's property
+            // aligned and non-null.
+            return Ok(NonNull::dangling().as_ptr());
+        }
+        if size_of::<T>() > min(HUGE_VALUE, self.max_segment_size) {
+            return self.add_dedicated_storage(value, cursor);
+        }
+        let place = Placement::of::<T>();
+
+        if let Some(locked) = &mut cursor.storage {
+            match locked.push(value, place) {
+                Ok(value_ptr) => return Ok(value_ptr),
+                Err(returned) => value = returned,
+            }
+        }
+        // Let other threads fill the rest of the full storage.
+        cursor.storage = None;
+
+        // Try the storage this thread last used, which saves searching in the common case.
+        if let Some(mut locked) = self.cached_storage() {
+            match locked.push(value, place) {
+                Ok(value_ptr) => {
+                    cursor.storage = Some(locked);
+                    return Ok(value_ptr);
+                }
+                Err(returned) => value = returned,
+            }
+        }
+
+        // Attempt to reuse an existing storage for the value.
+        for storage in self.data.iter() {
+            // If the storage is being used, just ignore it. We could keep on looping until we've
+            // made sure that none of the storages have space for the value, but the cost is only a
+            // few bytes in some scenarios.
+            let Some(mut locked) = storage.lock() else {
+                continue;
+            };
+
+            match locked.push(value, place) {
+                Ok(value_ptr) => {
+                    cursor.storage = Some(locked);
+                    self.cache_storage(storage);
+                    return Ok(value_ptr);
+                }
+                Err(returned) => value = returned,
+            }
+        }
+
+        // Fall back to adding a new storage.
+        self.add_storage(value, place, cursor)
+    }
+
+    /// Add a storage that contains the given value.
+    ///
+    /// Returns a pointer to the value, or gives the value back if it failed.
+    #[cfg(not(feature = "boxed-storage"))]
+    fn add_storage<'i, T: Send + 'a>(
+        &'i self,
+        value: T,
+        place: Placement,
+        cursor: &mut Cursor<'i>,
+    ) -> Result<*mut T, T> {
+        let size = size_of::<T>();
+        let align = align_of::<T>();
+
+        // Enough space to store the value regardless of the alignment of the storage.
+        let Some(required) = size.checked_add(align) else {
+            return Err(value);
+        };
+
+        let mut storage = if let Some(storage) = self.reuse_storage(required) {
+            storage
+        } else {
+            // The capacity of the storage
+            let capacity = max(
+                required,
+                min(
+                    // Storages of huge values don't count towards the growth.
+                    (self.data.iter()).find(|s| !s.dedicated).map_or(
+                        // The initial storage capacity will be 1024 bytes
+                        1024,
+                        // Storage capacity will double after that
+                        |s| s.capacity.checked_mul(2).unwrap_or(s.capacity),
+                    ),
+                    // Until it reaches the maximum
+                    self.max_segment_size,
+                ),
+            );
+            let Some(storage) = cursor.allocate(capacity) else {
+                return Err(value);
+            };
+            self.allocated(capacity, size);
+            storage
+        };
+
+        place.assign(storage.bytes.get_mut());
+        // This cannot fail as the storage is empty and has at least `size + align` bytes.
+        let value_ptr = storage.push_mut(value, place)?;
+
+        let storage = self.data.push(storage);
+        self.cache_storage(storage);
+        cursor.storage = storage.lock();
+        Ok(value_ptr)
+    }
+
+    /// Tell the callback set with `Builder::on_segment_alloc` that a storage of `size` bytes was
+    /// allocated for a value of `value_size` bytes.
+    fn allocated(&self, size: usize, value_size: usize) {
+        if let Some(on_segment_alloc) = self.on_segment_alloc {
+            on_segment_alloc(&SegmentAlloc { size, value_size });
+        }
+    }
+
+    /// Add a storage that holds only the given value, and is freed once it has been dropped.
+    ///
+    /// Returns a pointer to the value, or gives the value back if it failed.
+    #[cfg(not(feature = "boxed-storage"))]
+    fn add_dedicated_storage<'i, T: Send + 'a>(
+        &'i self,
+        value: T,
+        cursor: &mut Cursor<'i>,
+    ) -> Result<*mut T, T> {
+        let capacity = size_of::<T>().checked_add(align_of::<T>());
+        let Some(mut storage) = capacity.and_then(|capacity| cursor.allocate(capacity)) else {
+            return Err(value);
+        };
+        self.allocated(storage.capacity, size_of::<T>());
+        storage.dedicated = true;
+
+        // Marking the storage as a slab keeps other types from filling its padding.
+        let place = Placement {
+            class: SizeClass::of::<T>(),
+            ty: type_key::<T>(),
+            slab: true,
+        };
+        place.assign(storage.bytes.get_mut());
+        let value_ptr = storage.push_mut(value, place)?;
+
+        cursor.storage = self.data.push(storage).lock();
+        Ok(value_ptr)
+    }
+
+    /// Lock the storage this thread last added a value to, if it is in this bin.
+    #[cfg(not(feature = "boxed-storage"))]
+    fn cached_storage(&self) -> Option<Locked<'_>> {
+        let (id, storage) = LAST_STORAGE.try_with(Cell::get).ok()?;
+        if id == 0 || id != self.cache_id.load(Ordering::Relaxed) {
+            return None;
+        }
+        // SAFETY: The storage is still in `self.data`, since removing it would have reset the
+        // storage ID, and it can't be removed while we have shared access to `self`.
+        unsafe { &*storage }.lock()
+    }
+
+    /// Remember the storage as the one this thread last added a value to.
+    #[cfg(not(feature = "boxed-storage"))]
+    fn cache_storage(&self, storage: &Storage) {
+        let id = self.cache_id();
+        let _ = LAST_STORAGE.try_with(|last| last.set((id, storage)));
+    }
+
+    /// Get the bin's cache ID, assigning one if it hasn't got one.
+    fn cache_id(&self) -> usize {
+        let id = self.cache_id.load(Ordering::Relaxed);
+        if id != 0 {
+            return id;
+        }
+        let new = NEXT_CACHE_ID.fetch_add(1, Ordering::Relaxed);
+        match (self.cache_id).compare_exchange(0, new, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => new,
+            Err(id) => id,
+        }
+    }
+
+    /// Invalidate any cached pointers to the storages in `self.data`, before they are removed.
+    fn uncache_storages(&mut self) {
+        *self.cache_id.get_mut() = 0;
+    }
+
+    /// Take the smallest emptied storage that has at least `required` bytes of capacity.
+    ///
+    /// Returns `None` if there is none, or if the free list is being used.
+    #[cfg(not(feature = "boxed-storage"))]
+    fn reuse_storage(&self, required: usize) -> Option<Storage> {
+        let mut free = self.free.try_lock().ok()?;
+        let (index, _) = (free.iter().enumerate())
+            .filter(|(_, s)| s.capacity >= required)
+            .min_by_key(|(_, s)| s.capacity)?;
+        Some(free.swap_remove(index))
+    }
+
+    /// Add every value from the iterator to the bin, setting aside space for all of them up front.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub(crate) fn add_iter<T: Send + 'a>(&self, iter: impl IntoIterator<Item = T>) {
+        let iter = iter.into_iter();
+        let mut cursor = Cursor::default();
+
+        #[cfg(not(feature = "boxed-storage"))]
+        self.reserve::<T>(iter.size_hint().0, &mut cursor);
+
+        for value in iter {
+            self.add_at(value, None, &mut cursor);
+        }
+    }
+
+    /// Add every value from the iterator to the bin, recording each run of values that were
+    /// placed next to each other as a single entry.
+    #[cfg(not(feature = "boxed-storage"))]
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub(crate) fn add_many<T: Send + 'a>(&self, iter: impl IntoIterator<Item = T>) {
+        let iter = iter.into_iter();
+        let mut cursor = Cursor::default();
+        self.reserve::<T>(iter.size_hint().0, &mut cursor);
+
+        // The storage the run is in, the start of the run and its length.
+        let mut run: Option<(*const MaybeUninit<u8>, *mut T, usize)> = None;
+        for value in iter {
+            let Ok(value_ptr) = self.store(value, &mut cursor) else {
+                continue;
+            };
+            let storage = (cursor.storage.as_ref())
+                .map_or(ptr::null(), |locked| locked.contents.bytes.as_ptr());
+
+            match &mut run {
+                Some((run_storage, start, len))
+                    if *run_storage == storage && start.wrapping_add(*len) == value_ptr =>
+                {
+                    *len += 1;
+                }
+                _ => {
+                    if let Some((_, start, len)) = run.replace((storage, value_ptr, 1)) {
+                        self.add_run(start, len);
+                    }
+                }
+            }
+        }
+        if let Some((_, start, len)) = run {
+            self.add_run(start, len);
+        }
+    }
+
+    /// Add every value from the iterator to the bin.
+    ///
+    /// Each value has its own allocation, so there are no runs to share entries.
+    #[cfg(feature = "boxed-storage")]
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub(crate) fn add_many<T: Send + 'a>(&self, iter: impl IntoIterator<Item = T>) {
+        self.add_iter(iter);
+    }
+
+    /// Record a run of values stored one after another as a single entry.
+    #[cfg(not(feature = "boxed-storage"))]
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    fn add_run<T: Send + 'a>(&self, start: *mut T, len: usize) {
+        self.push_entry(Entry::new(
+            start.cast::<()>(),
+            Destroy::Kind(&KindOf::<T>::RUN, len),
+            Labels::default(),
+        ));
+    }
+
+    /// Point the cursor at a new storage with space for `len` values of type `T`, if more than one
+    /// is expected.
+    #[cfg(not(feature = "boxed-storage"))]
+    fn reserve<'i, T>(&'i self, len: usize, cursor: &mut Cursor<'i>) {
+        let size = size_of::<T>();
+        let align = align_of::<T>();
+        if size == 0 || len <= 1 {
+            return;
+        }
+
+        // Sizes are a multiple of alignment, so only the first value can need padding. Very large
+        // reservations are capped by the segment size limit and left to grow normally.
+        let Some(required) = size.checked_mul(len).and_then(|n| n.checked_add(align)) else {
+            return;
+        };
+        let capacity = min(required, max(self.max_segment_size, size + align));
+
+        let mut storage = self.reuse_storage(capacity).unwrap_or_else(|| {
+            self.allocated(capacity, size);
+            Storage::new(capacity)
+        });
+        // The values will be added together, so they can share a slab.
+        let place = Placement {
+            class: SizeClass::of::<T>(),
+            ty: type_key::<T>(),
+            slab: true,
+        };
+        place.assign(storage.bytes.get_mut());
+        cursor.storage = self.data.push(storage).lock();
+    }
+
+    /// Clear the bin.
+    pub(crate) fn clear(&mut self) -> ClearReport {
+        let start = Instant::now();
+        let mut report = ClearReport::default();
+
+        self.flush_buffers();
+        let entries = order_drops(self.take_newest_first().collect());
+        self.drop_entries(entries, &mut report);
+
+        report.bytes = self.recycle_storage();
+        report.duration = start.elapsed();
+        report
+    }
+
+    /// Drop the `n` least recently added values, or the `n` most recently added if `oldest` is
+    /// false.
+    pub(crate) fn clear_partial(&mut self, n: usize, oldest: bool) -> ClearReport {
+        self.clear_selected(|entries| {
+            let count = min(n, entries.len());
+            if oldest {
+                (0..count).collect()
+            } else {
+                (entries.len() - count..entries.len()).rev().collect()
+            }
+        })
+    }
+
+    /// Drop the least recently added values until the rest take up at most `bytes` bytes of
+    /// storage.
+    pub(crate) fn clear_down_to(&mut self, bytes: usize) -> ClearReport {
+        let mut excess = self.bytes_used().saturating_sub(bytes);
+        self.clear_selected(|entries| {
+            let count = (entries.iter())
+                .take_while(|entry| {
+                    let over = excess != 0;
+                    excess = excess.saturating_sub(entry.size());
+                    over
+                })
+                .count();
+            (0..count).collect()
+        })
+    }
+
+    /// Drop the values that were added with the given tag.
+    pub(crate) fn clear_tag(&mut self, tag: Tag) -> ClearReport {
+        self.clear_selected(|entries| {
+            (0..entries.len())
+                .rev()
+                .filter(|&i| entries[i].labels().tag == Some(tag))
+                .collect()
+        })
+    }
+
+    /// Drop the values that `predicate` picks out.
+    #[cfg(feature = "debug-types")]
+    pub(crate) fn clear_where(
+        &mut self,
+        mut predicate: impl FnMut(&ValueMeta) -> bool,
+    ) -> ClearReport {
+        let now = Instant::now();
+        self.clear_selected(|entries| {
+            (0..entries.len())
+                .rev()
+                .filter(|&i| {
+                    let entry = &entries[i];
+                    let info = entry.debug_info();
+                    predicate(&ValueMeta {
+                        type_name: entry.type_name(),
+                        bytes: entry.size(),
+                        age: now.saturating_duration_since(info.added),
+                        tag: info.labels.tag,
+                        location: info.caller,
+                    })
+                })
+                .collect()
+        })
+    }
+
+    /// Drop the values that were added through the given region.
+    pub(crate) fn clear_region(&mut self, region: Key) -> ClearReport {
+        self.clear_selected(|entries| {
+            (0..entries.len())
+                .rev()
+                .filter(|&i| entries[i].labels().region == Some(region))
+                .collect()
+        })
+    }
+
+    /// Drop the values that `select` picks out of the list of entries, which is given oldest
+    /// first. `select` returns their indices in the order to drop them.
+    ///
+    /// Groups are dropped whole, and values that must be dropped after a value that is being kept
+    /// are kept too.
+    fn clear_selected(&mut self, select: impl FnOnce(&[Entry]) -> Vec<usize>) -> ClearReport {
+        let start = Instant::now();
+        let mut report = ClearReport::default();
+
+        let entries = self.take_entries();
+        let mut selected = select(&entries);
+
+        let mut dropping = vec![false; entries.len()];
+        for &i in &selected {
+            dropping[i] = true;
+        }
+        // Drop the rest of every group that has been started.
+        let groups: HashSet<Key> = (selected.iter())
+            .filter_map(|&i| entries[i].labels().group)
+            .collect();
+        for (i, entry) in entries.iter().enumerate() {
+            if !dropping[i]
+                && entry
+                    .labels()
+                    .group
+                    .is_some_and(|group| groups.contains(&group))
+            {
+                dropping[i] = true;
+                selected.push(i);
+            }
+        }
+        // Hold back values that aren't ready to be dropped along with their groups, and then
+        // values and groups until nothing they must be dropped after is held back.
+        let mut kept_groups = HashSet::new();
+        for (entry, dropping) in entries.iter().zip(&mut dropping) {
+            if *dropping && !entry.is_ready() {
+                *dropping = false;
+                kept_groups.extend(entry.labels().group);
+            }
+        }
+        let mut kept: HashSet<Key> = (entries.iter().zip(&dropping))
+            .filter(|(_, &dropping)| !dropping)
+            .filter_map(|(entry, _)| entry.labels().key)
+            .collect();
+        loop {
+            let mut held = false;
+            for (entry, dropping) in entries.iter().zip(&mut dropping) {
+                let labels = entry.labels();
+                let waits = labels.after.is_some_and(|after| kept.contains(&after))
+                    || (labels.group).is_some_and(|group| kept_groups.contains(&group));
+                if *dropping && waits {
+                    *dropping = false;
+                    kept.extend(labels.key);
+                    kept_groups.extend(labels.group);
+                    held = true;
+                }
+            }
+            if !held {
+                break;
+            }
+        }
+
+        let mut remaining = 0;
+        for (entry, &dropping) in entries.iter().zip(&dropping) {
+            if !dropping {
+                self.restore(entry.clone());
+                remaining += 1;
+            }
+        }
+
+        let dropped = (selected.into_iter())
+            .filter(|&i| dropping[i])
+            .map(|i| entries[i].clone())
+            .collect();
+        self.drop_entries(order_drops(dropped), &mut report);
+
+        if remaining == 0 {
+            report.bytes = self.recycle_storage();
+        }
+
+        report.duration = start.elapsed();
+        report
+    }
+
+    /// Drop the values of entries that have been taken out of the bin, in order.
+    ///
+    /// If a destructor panics and the panic propagates, the entries that are left are put back in
+    /// the bin.
+    fn drop_entries(&mut self, mut entries: Vec<Entry>, report: &mut ClearReport) {
+        struct Remainder<'i, 'a> {
+            inner: &'i mut Inner<'a>,
+            /// The entries that are left, with the next one to drop last.
+            entries: Vec<Entry>,
+        }
+
+        impl Drop for Remainder<'_, '_> {
+            fn drop(&mut self) {
+                for entry in mem::take(&mut self.entries) {
+                    self.inner.released -= entry.size();
+                    self.inner.restore(entry);
+                }
+            }
+        }
+
+        entries.reverse();
+        let mut remainder = Remainder {
+            inner: self,
+            entries,
+        };
+        let Remainder { inner, entries } = &mut remainder;
+        inner.released += entries.iter().map(Entry::size).sum::<usize>();
+        #[cfg(feature = "watchdog")]
+        let watch = (inner.watchdog)
+            .filter(|_| !entries.is_empty())
+            .map(Watch::new);
+        let (on_panic, panics) = inner.panic_handling();
+        while let Some(entry) = entries.pop() {
+            report.record(entry.destructor_key(), || {
+                #[cfg(feature = "watchdog")]
+                let _timer = watch
+                    .as_ref()
+                    .map(|watch| watch.time(entry.recorded_type_name()));
+                on_panic.run(
+                    || unsafe {
+                        // SAFETY: The entries pointed into `self.data`, and have been removed.
+                        entry.drop_value();
+                    },
+                    panics,
+                );
+            });
+        }
+    }
+
+    /// Take all the entries out of the bin, oldest first.
+    fn take_entries(&mut self) -> Vec<Entry> {
+        self.flush_buffers();
+        let mut entries: Vec<_> = self.take_newest_first().collect();
+        entries.reverse();
+        entries
+    }
+
+    /// Take all the entries out of `destructors`, newest first, skipping those that have been
+    /// removed.
+    fn take_newest_first(&mut self) -> impl Iterator<Item = Entry> + '_ {
+        let keyed = self.keyed.get_mut().unwrap_or_else(PoisonError::into_inner);
+        let removed = &mut self.removed;
+        take_newest_first(&mut self.destructors).filter(move |entry| match entry.labels().key {
+            Some(key) if removed.remove(&key) => false,
+            Some(key) => {
+                keyed.remove(&key);
+                true
+            }
+            None => true,
+        })
+    }
+
+    /// Put an entry that was taken out of the bin back in.
+    fn restore(&mut self, entry: Entry) {
+        if let Some(key) = entry.labels().key {
+            let keyed = self.keyed.get_mut().unwrap_or_else(PoisonError::into_inner);
+            keyed.insert(key, entry.clone());
+            // Entries removed by `drop_now` are still in `destructors`, and are revived instead.
+            if self.removed.remove(&key) {
+                return;
+            }
+        }
+        self.destructors.push(entry);
+    }
+
+    /// Get the type names of the values in the bin, oldest first.
+    #[cfg(feature = "debug-types")]
+    pub(crate) fn type_names(&mut self) -> Vec<&'static str> {
+        let entries = self.entries();
+        entries.iter().map(Entry::type_name).collect()
+    }
+
+    /// Get the keys that identify the types of the values in the bin by their destructors, as
+    /// used to learn their drop times.
+    #[cfg(feature = "drop-times")]
+    pub(crate) fn destructors(&mut self) -> Vec<usize> {
+        let entries = self.entries();
+        entries.iter().map(Entry::destructor_key).collect()
+    }
+
+    /// Get where the values in the bin were added from, oldest first.
+    #[cfg(feature = "debug-types")]
+    pub(crate) fn callers(&mut self) -> Vec<&'static Location<'static>> {
+        let entries = self.entries();
+        (entries.iter())
+            .map(|entry| entry.debug_info().caller)
+            .collect()
+    }
+
+    /// Get the policy for destructor panics, and the list of collected panics.
+    fn panic_handling(&mut self) -> (PanicPolicy, &mut Vec<Payload>) {
+        let panics = self
+            .panics
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        (self.on_panic, panics)
+    }
+
+    /// Take the payloads of the destructor panics that have been collected.
+    pub(crate) fn take_panics(&mut self) -> Vec<Payload> {
+        mem::take(self.panic_handling().1)
+    }
+
+    /// Record the payloads of destructor panics that were collected elsewhere.
+    pub(crate) fn add_panics(&mut self, panics: Vec<Payload>) {
+        self.panic_handling().1.extend(panics);
+    }
+
+    /// Get the values in the bin that were added with `add_any`, oldest first.
+    pub(crate) fn any_values(&mut self) -> Vec<&(dyn Any + Send)> {
+        let entries = self.entries();
+        (entries.iter())
+            .filter_map(|entry| {
+                let as_any = entry.kind()?.as_any?;
+                // SAFETY: The value is alive until the bin is next cleared, which can't happen
+                // while it is borrowed.
+                Some(unsafe { &*as_any(entry.ptr) })
+            })
+            .collect()
+    }
+
+    /// Remove the values of type `T` that were added with `add_any` from the bin without dropping
+    /// them, oldest first.
+    pub(crate) fn take_all<T: Any + Send>(&mut self) -> Vec<T> {
+        let mut entries = self.take_entries();
+        let mut taken = Vec::new();
+
+        entries.retain(|entry| {
+            // SAFETY: The value is alive, since the bin hasn't been cleared.
+            let as_any = entry.kind().and_then(|kind| kind.as_any);
+            let is_t = as_any.is_some_and(|as_any| unsafe { &*as_any(entry.ptr) }.is::<T>());
+            if is_t {
+                // SAFETY: The value has type `T`, and its entry is removed so it isn't dropped
+                // again.
+                taken.push(unsafe { take_value::<T>(entry.ptr) });
+                self.released += entry.size();
+            }
+            !is_t
+        });
+
+        let empty = entries.is_empty();
+        for entry in entries {
+            self.restore(entry);
+        }
+        if empty {
+            self.recycle_storage();
+        }
+
+        taken
+    }
+
+    /// Remove the value with the given key from the bin without dropping it, returning whether it
+    /// was found.
+    pub(crate) fn cancel(&mut self, key: Key) -> bool {
+        let Some(entry) = self.remove_keyed(key) else {
+            return false;
+        };
+        self.released += entry.size();
+        // SAFETY: The entry has been removed, so the value won't be dropped.
+        unsafe { entry.forget() };
+        self.recycle_if_empty();
+        true
+    }
+
+    /// Drop the value with the given key, if it is still in the bin, after the values it must be
+    /// dropped after.
+    pub(crate) fn drop_now(&mut self, key: Key) -> ClearReport {
+        let start = Instant::now();
+        let mut report = ClearReport::default();
+
+        let mut chain = Vec::new();
+        let mut next = Some(key);
+        while let Some(entry) = next.and_then(|key| self.remove_keyed(key)) {
+            next = entry.labels().after;
+            chain.push(entry);
+        }
+
+        if !chain.is_empty() {
+            chain.reverse();
+            self.drop_entries(chain, &mut report);
+            self.recycle_if_empty();
+        }
+
+        report.duration = start.elapsed();
+        report
+    }
+
+    /// Remove the entry with the given key, returning it if it was found.
+    ///
+    /// This doesn't search the bin: the entry is left where it is, and skipped when it is taken
+    /// out.
+    fn remove_keyed(&mut self, key: Key) -> Option<Entry> {
+        let keyed = self.keyed.get_mut().unwrap_or_else(PoisonError::into_inner);
+        let entry = keyed.remove(&key)?;
+        self.removed.insert(key);
+        Some(entry)
+    }
+
+    /// Recycle the storage if the only entries left are ones that have been removed.
+    fn recycle_if_empty(&mut self) {
+        if self.is_empty() {
+            // The removed entries point into the storage, so they are taken out first.
+            self.take_entries();
+            self.recycle_storage();
+        }
+    }
+
+    /// Get a copy of the entries, oldest first, leaving them in the bin.
+    fn entries(&mut self) -> Vec<Entry> {
+        let entries = self.take_entries();
+        for entry in &entries {
+            self.restore(entry.clone());
+        }
+        entries
+    }
+
+    /// Forget every value in the bin without dropping it, returning how many there were.
+    ///
+    /// The storage is kept to be reused.
+    pub(crate) fn leak(&mut self) -> usize {
+        self.flush_buffers();
+        let leaked = self.take_newest_first().count();
+        self.recycle_storage();
+        leaked
+    }
+
+    /// Move all the storages to the free list. They must not contain any values.
+    ///
+    /// Returns the number of bytes that were in use.
+    fn recycle_storage(&mut self) -> usize {
+        let mut used = 0;
+        self.released = 0;
+
+        self.uncache_storages();
+
+        // Move all the now-empty storages to the free list, so that they will be reused before
+        // any new storage is allocated.
+        let free = self.free.get_mut().unwrap_or_else(PoisonError::into_inner);
+        while let Some(mut storage) = self.data.pop() {
+            let Contents { bytes, padding, .. } = storage.bytes.get_mut();
+            used += bytes.len();
+            *storage.used.get_mut() = 0;
+            if storage.dedicated {
+                continue;
+            }
+            // The padding between values is poisoned, so it must be unpoisoned to be zeroed.
+            // SAFETY: The bytes are part of the `Vec`.
+            unsafe { asan::unpoison(bytes.as_ptr(), bytes.len()) };
+            // SAFETY: The bytes are part of the `Vec`, and the values in them are gone.
+            unsafe { zeroize::zero(bytes.as_mut_ptr(), bytes.len()) };
+            #[cfg(feature = "decommit")]
+            if self.decommit {
+                // SAFETY: The bytes are part of the storage, and are written before being read.
+                unsafe { decommit::decommit(bytes.as_mut_ptr().cast(), bytes.len()) };
+            }
+            // Catch any use of the values after they have been dropped.
+            // SAFETY: The bytes are part of the `Vec`, and are unpoisoned before values are
+            // written to them.
+            unsafe { asan::poison(bytes.as_ptr(), bytes.len()) };
+            #[cfg(all(feature = "mmap", unix))]
+            bytes.release();
+            bytes.clear();
+            *padding = 0;
+            free.push(storage);
+        }
+        cap_free(free);
+
+        used
+    }
+
+    /// Move the values that are ready to be dropped out of the bin like `detach`. If `compact` is
+    /// set, all of its storage is then replaced with a single segment just large enough to hold
+    /// what the bin contained.
+    ///
+    /// Values added with `add_when` that aren't ready are kept in the bin.
+    pub(crate) fn detach_ready(&mut self, compact: bool) -> Detached<'a> {
+        // Huge values will be given dedicated storages again, so they aren't made room for.
+        let shared: usize = (self.data.iter_mut())
+            .filter(|storage| !storage.dedicated)
+            .map(|storage| storage.bytes.get_mut().bytes.len())
+            .sum();
+
+        self.flush_buffers();
+        let mut entries: Vec<_> = self.take_newest_first().collect();
+        let held = take_unready(&mut entries);
+        let detached = self.detach_entries(entries);
+        // The held values have allocations of their own, so they don't need the storage.
+        for entry in held.into_iter().rev() {
+            self.restore(entry);
+        }
+
+        if compact {
+            let free = self.free.get_mut().unwrap_or_else(PoisonError::into_inner);
+            free.clear();
+            if shared != 0 {
+                let capacity = min(shared, self.max_segment_size);
+                free.push(Storage::new(capacity));
+                self.allocated(capacity, 0);
+            }
         }
+
+        detached
     }
 
-    /// Add the given value to the bin.
-    pub(crate) fn add<T: Send + 'a>(&self, value: T) {
-        let value_ptr = match self.store(value) {
-            Some(value_ptr) => value_ptr,
-            None => return,
-        };
+    /// Free emptied storages, largest first, for as long as at least `target` bytes of storage
+    /// are left.
+    pub(crate) fn shrink_to(&self, target: usize) {
+        let mut size = self.size() + self.free_size();
+        let mut free = self.free.lock().unwrap_or_else(PoisonError::into_inner);
+        free.sort_unstable_by_key(|storage| Reverse(storage.capacity));
+        free.retain(|storage| {
+            let keep = size - storage.capacity < target;
+            if !keep {
+                size -= storage.capacity;
+            }
+            keep
+        });
+    }
 
-        let destructor: Destructor = unsafe {
-            // SAFETY: `*mut T` can be soundly transmuted to `*mut ()`, and so `fn(*mut T)` can be
-            // soundly transmuted to `fn(*mut ())`
-            mem::transmute::<unsafe fn(*mut T), fn(*mut ())>(ptr::drop_in_place::<T>)
-        };
+    /// Move all the values out of the bin, leaving its empty storage and configuration behind.
+    pub(crate) fn detach(&mut self) -> Detached<'a> {
+        self.flush_buffers();
+        let entries = self.take_newest_first().collect();
+        self.detach_entries(entries)
+    }
 
-        self.destructors.push((value_ptr.cast::<()>(), destructor));
+    /// Move the values of the given entries, newest first, and all the storage out of the bin.
+    fn detach_entries(&mut self, entries: Vec<Entry>) -> Detached<'a> {
+        let mut entries = order_drops(entries);
+        entries.reverse();
+        self.uncache_storages();
+        let mut storage = Inner::new();
+        storage.on_panic = self.on_panic;
+        #[cfg(feature = "decommit")]
+        {
+            storage.decommit = self.decommit;
+        }
+        #[cfg(feature = "watchdog")]
+        {
+            storage.watchdog = self.watchdog;
+        }
+        mem::swap(&mut storage.data, &mut self.data);
+        storage.released = mem::take(&mut self.released);
+        Detached { entries, storage }
     }
 
-    /// Store the given value in the bin.
+    /// Put values taken out with `detach` back in the bin, older than the values already in it.
     ///
-    /// Returns a pointer to the value, or `None` if it failed.
-    fn store<T: Send + 'a>(&self, value: T) -> Option<*mut T> {
-        let size = mem::size_of::<T>();
-        let align = mem::align_of::<T>();
-
-        if size > 0 {
-            // Attempt to reuse an existing storage for the value.
-            if let Some((mut storage, value_start_index)) =
-                // Find a storage that has space for the value.
-                self.data.iter().find_map(|storage| {
-                        // If the storage is being used, just ignore it. We could keep on looping until
-                        // we've made sure that none of the storages have space for the value, but the
-                        // cost is only a few bytes in some scenarios.
-                        let storage = storage.bytes.try_lock()?;
-
-                        let storage_end_ptr = storage.as_ptr() as usize + storage.len();
-                        let padding = (align - storage_end_ptr % align) % align;
-
-                        let value_start_index = storage.len().checked_add(padding)?;
-
-                        if value_start_index.checked_add(size)? <= storage.capacity() {
-                            Some((storage, value_start_index))
-                        } else {
-                            None
-                        }
-                    })
-            {
-                unsafe {
-                    // SAFETY: We have checked that there is enough space to store
-                    // `value_start_index + size` bytes, and the inner type is MaybeUninit.
-                    storage.set_len(value_start_index + size);
-                }
-
-                let value_ptr = <*mut MaybeUninit<u8>>::cast::<T>(&mut storage[value_start_index]);
-                unsafe {
-                    // SAFETY: We have mutable access to `storage` and it is aligned.
-                    value_ptr.write(value);
-                }
-                Some(value_ptr)
-            } else {
-                // Fall back to creating a new storage.
-                self.add_storage(value)
-            }
+    /// Their storage goes back in with them, or on the free list if they have all been dropped.
+    pub(crate) fn reattach(&mut self, mut detached: Detached<'a>) {
+        let entries = mem::take(&mut detached.entries);
+        let storage = &mut detached.storage;
+        if entries.is_empty() {
+            storage.recycle_storage();
+            let free = storage
+                .free
+                .get_mut()
+                .unwrap_or_else(PoisonError::into_inner);
+            let own = self.free.get_mut().unwrap_or_else(PoisonError::into_inner);
+            own.append(free);
+            cap_free(own);
         } else {
-            mem::forget(value);
-
-            // We can use a dangling pointer for zero sized types, as long as it's property
-            // aligned and non-null.
-            Some(align as *mut T)
+            while let Some(segment) = storage.data.pop() {
+                self.data.push(segment);
+            }
+            self.released += mem::take(&mut storage.released);
+            let newer = self.take_entries();
+            for entry in entries.into_iter().chain(newer) {
+                self.restore(entry);
+            }
         }
+
+        let panics = storage.take_panics();
+        self.add_panics(panics);
     }
 
-    /// Add a storage that contains the given value.
+    /// Move the emptied storages of `other` to this bin's free list, along with the destructor
+    /// panics it collected. The storages already on the free list are freed if `replace` is set.
     ///
-    /// Returns a pointer to the value, or `None` if it failed.
-    fn add_storage<T: Send + 'a>(&self, value: T) -> Option<*mut T> {
-        let size = mem::size_of::<T>();
-        let align = mem::align_of::<T>();
-
-        // The capacity of the storage
-        let capacity = max(
-            size.checked_add(align)?,
-            self.data.head().map_or(
-                // The initial storage capacity will be 1024 bytes
-                1024,
-                // Storage capacity will double after that
-                |s| s.capacity.checked_mul(2).unwrap_or(s.capacity),
-            ),
-        );
-        let mut bytes = Vec::with_capacity(capacity);
-        // Get the index into `bytes` at which the value starts to make sure it has the correct
-        // alignment
-        let value_start_index = (align - bytes.as_ptr() as usize % align) % align;
-        unsafe {
-            // SAFETY: We have allocated enough space to store `size + align` bytes, and the inner
-            // type is MaybeUninit.
-            bytes.set_len(value_start_index + size);
-        }
-        let value_ptr = <*mut MaybeUninit<u8>>::cast::<T>(&mut bytes[value_start_index]);
-        unsafe {
-            // SAFETY: We have mutable access to `bytes` and it is aligned.
-            value_ptr.write(value);
-        }
-
-        let storage = Storage {
-            bytes: TryMutex::new(bytes),
-            capacity,
-        };
+    /// Unlike `reattach`, this only needs shared access, so values can be added meanwhile.
+    pub(crate) fn take_free(&self, other: &mut Inner<'a>, replace: bool) {
+        let other_free = other.free.get_mut().unwrap_or_else(PoisonError::into_inner);
+        let mut free = self.free.lock().unwrap_or_else(PoisonError::into_inner);
+        if replace {
+            free.clear();
+        }
+        free.append(other_free);
+        cap_free(&mut free);
+        drop(free);
 
-        self.data.push(storage);
-        Some(value_ptr)
+        let panics = other.take_panics();
+        (self.panics.lock())
+            .unwrap_or_else(PoisonError::into_inner)
+            .extend(panics);
     }
 
-    /// Clear the bin.
-    pub(crate) fn clear(&mut self) {
-        for (value, destructor) in std::mem::take(&mut self.destructors).into_iter() {
-            unsafe {
-                // SAFETY: `self.destructors` contains valid indices into `self.data`.
-                // We use pointer arithmetic instead of indexing to avoid panicking when we drop
-                // ZSTs (which are represented as an index 0).
-                destructor(value.cast::<()>());
-            }
+    /// Put the storage of values taken out with `detach` that have all been dropped on the free
+    /// list like `take_free`, or free it if `keep_storage` isn't set.
+    pub(crate) fn recycle(&self, mut detached: Detached<'a>, keep_storage: bool) {
+        debug_assert!(detached.is_empty());
+        if keep_storage {
+            detached.storage.recycle_storage();
         }
+        self.take_free(&mut detached.storage, false);
+    }
 
-        for storage in self.data.iter_mut() {
-            storage.bytes.get_mut().clear();
-        }
+    /// Put values taken out with `detach` back in the bin, newer than the values already in it.
+    pub(crate) fn append(&mut self, detached: Detached<'a>) {
+        let older = self.detach();
+        self.reattach(detached);
+        self.reattach(older);
     }
 
-    /// Get the size of the bin in bytes.
+    /// Get the number of bytes of storage that the values in the bin and the padding between them
+    /// take up.
+    pub(crate) fn bytes_used(&self) -> usize {
+        let used: usize = (self.data.iter())
+            .map(|storage| storage.used.load(Ordering::Relaxed))
+            .sum();
+        used.saturating_sub(self.released)
+    }
+
+    /// Get the size of the bin in bytes, not counting emptied storages.
     pub(crate) fn size(&self) -> usize {
         self.data.iter().map(|s| s.capacity).sum()
     }
+
+    /// Get the size in bytes of the emptied storages kept for reuse.
+    pub(crate) fn free_size(&self) -> usize {
+        let free = self.free.lock().unwrap_or_else(PoisonError::into_inner);
+        free.iter().map(|s| s.capacity).sum()
+    }
+
+    /// Summarize what the bin contains.
+    ///
+    /// Values being added concurrently may or may not be counted, and storages that are being
+    /// written to are left out of the number of bytes used.
+    pub(crate) fn stats(&self) -> Stats {
+        let (used, padding) = (self.data.iter())
+            .filter_map(|storage| storage.bytes.try_lock())
+            .fold((0, 0), |(used, padding), bytes| {
+                (used + bytes.bytes.len(), padding + bytes.padding)
+            });
+        let buffered: usize = (self.buffers.iter())
+            .filter_map(|buffer| buffer.try_lock().map(|buffer| buffer.len()))
+            .sum();
+        // Removed entries may be in buffers that are locked.
+        let values = (self.destructors.len() + buffered).saturating_sub(self.removed.len());
+        Stats {
+            values,
+            segments: self.data.len(),
+            capacity: self.size(),
+            used,
+            padding,
+            entries: values * size_of::<Entry>(),
+            free: self.free_size(),
+        }
+    }
+}
+
+/// Free the smallest emptied storages past the number a bin keeps.
+fn cap_free(free: &mut Vec<Storage>) {
+    if free.len() > MAX_FREE {
+        free.sort_unstable_by_key(|storage| Reverse(storage.capacity));
+        free.truncate(MAX_FREE);
+    }
+}
+
+/// A summary of what a bin contains.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Stats {
+    /// The number of values in the bin.
+    pub(crate) values: usize,
+    /// The number of storages that hold values, not counting emptied ones.
+    pub(crate) segments: usize,
+    /// The total capacity of the storages that hold values.
+    pub(crate) capacity: usize,
+    /// The number of bytes of those storages that are in use.
+    pub(crate) used: usize,
+    /// The number of the used bytes that are padding between values.
+    pub(crate) padding: usize,
+    /// The number of bytes taken up by the values' destructor entries.
+    pub(crate) entries: usize,
+    /// The size of the emptied storages kept for reuse.
+    pub(crate) free: usize,
+}
+
+impl Stats {
+    /// Combine the summaries of two parts of a bin.
+    pub(crate) fn merge(self, other: Self) -> Self {
+        Self {
+            values: self.values + other.values,
+            segments: self.segments + other.segments,
+            capacity: self.capacity + other.capacity,
+            used: self.used + other.used,
+            padding: self.padding + other.padding,
+            entries: self.entries + other.entries,
+            free: self.free + other.free,
+        }
+    }
+}
+
+/// Values taken out of a bin, which are dropped along with this.
+#[derive(Debug)]
+pub(crate) struct Detached<'a> {
+    /// The entries of the values, with the next one to drop last.
+    entries: Vec<Entry>,
+    /// The storage that the entries point into.
+    storage: Inner<'a>,
+}
+
+impl Detached<'_> {
+    /// Drop up to `n` of the values, in the order a clear would, recording them in the report.
+    /// More are dropped if that would leave part of a group behind.
+    ///
+    /// Returns the number of values dropped.
+    pub(crate) fn clear_some(&mut self, n: usize, report: &mut ClearReport) -> usize {
+        let start = Instant::now();
+        let mut dropped = self.entries.len().min(n);
+        let rest = self.entries.len() - dropped;
+        let groups: HashSet<Key> = (self.entries[rest..].iter())
+            .filter_map(|entry| entry.labels().group)
+            .collect();
+        if !groups.is_empty() {
+            // Move the rest of the groups to be dropped next.
+            let (members, others): (Vec<_>, Vec<_>) =
+                (self.entries.drain(..rest)).partition(|entry| {
+                    (entry.labels().group).is_some_and(|group| groups.contains(&group))
+                });
+            dropped += members.len();
+            self.entries.splice(0..0, others.into_iter().chain(members));
+        }
+
+        #[cfg(feature = "watchdog")]
+        let watch = (self.storage.watchdog)
+            .filter(|_| dropped != 0)
+            .map(Watch::new);
+        let (on_panic, panics) = self.storage.panic_handling();
+        // Entries are popped one at a time, so that if a panic propagates the rest are kept.
+        for _ in 0..dropped {
+            let Some(entry) = self.entries.pop() else {
+                break;
+            };
+            report.record(entry.destructor_key(), || {
+                #[cfg(feature = "watchdog")]
+                let _timer = watch
+                    .as_ref()
+                    .map(|watch| watch.time(entry.recorded_type_name()));
+                on_panic.run(
+                    || unsafe {
+                        // SAFETY: The entries point into `self.storage`, and are removed as they
+                        // are dropped.
+                        entry.drop_value();
+                    },
+                    panics,
+                );
+            });
+        }
+        report.duration += start.elapsed();
+        dropped
+    }
+
+    /// Take the payloads of the destructor panics that have been collected.
+    pub(crate) fn take_panics(&mut self) -> Vec<Payload> {
+        self.storage.take_panics()
+    }
+
+    /// Get the number of values that haven't been dropped yet.
+    pub(crate) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check whether every value has been dropped.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Count how many of the next values to drop, up to `max`, are expected to take no longer
+    /// than `time` together. Values of types whose drop times haven't been learned yet are
+    /// counted as taking no time.
+    #[cfg(feature = "drop-times")]
+    pub(crate) fn fitting(&self, mut time: Duration, times: &DropTimes, max: usize) -> usize {
+        (self.entries.iter().rev().take(max))
+            .take_while(|entry| {
+                let expected = times.get(entry.destructor_key()).unwrap_or_default();
+                time.checked_sub(expected).map(|left| time = left).is_some()
+            })
+            .count()
+    }
+
+    /// Get the number of bytes of storage that the values and the padding between them take up.
+    pub(crate) fn bytes_used(&self) -> usize {
+        self.storage.bytes_used()
+    }
+}
+
+impl Drop for Detached<'_> {
+    fn drop(&mut self) {
+        let mut report = ClearReport::default();
+        self.clear_some(usize::MAX, &mut report);
+        #[cfg(feature = "drop-times")]
+        report.samples.learn();
+    }
+}
+
+/// Reorder entries that are in the order to drop them, so that every value added with
+/// `add_after` comes after the value it was added after if that is among them. The order is
+/// otherwise kept.
+fn order_drops(entries: Vec<Entry>) -> Vec<Entry> {
+    if entries.iter().all(|entry| entry.labels().after.is_none()) {
+        return entries;
+    }
+    let keyed: HashMap<Key, usize> = (entries.iter().enumerate())
+        .filter_map(|(i, entry)| Some((entry.labels().key?, i)))
+        .collect();
+
+    let mut placed = vec![false; entries.len()];
+    let mut ordered = Vec::with_capacity(entries.len());
+    for i in 0..entries.len() {
+        // Place the values this one must be dropped after before it.
+        let mut chain = Vec::new();
+        let mut next = Some(i);
+        while let Some(j) = next.filter(|&j| !placed[j]) {
+            placed[j] = true;
+            chain.push(entries[j].clone());
+            next = (entries[j].labels().after).and_then(|after| keyed.get(&after).copied());
+        }
+        ordered.extend(chain.into_iter().rev());
+    }
+    ordered
+}
+
+/// Remove the entries of values that aren't ready to be dropped from a list of entries, and
+/// return them in the same order.
+///
+/// Values added with `add_when` have no key or group, so no other values are held back with them.
+fn take_unready(entries: &mut Vec<Entry>) -> Vec<Entry> {
+    if entries.iter().all(|entry| entry.ready().is_none()) {
+        return Vec::new();
+    }
+    let (held, ready) = mem::take(entries)
+        .into_iter()
+        .partition(|entry| !entry.is_ready());
+    *entries = ready;
+    held
+}
+
+/// Check whether a value added with `add_when` can be dropped yet.
+///
+/// # Safety
+///
+/// `ptr` must point to the value and its check, which must be alive.
+unsafe fn check_ready<T, F: Fn() -> bool>(ptr: *mut ()) -> bool {
+    (unsafe { &*ptr.cast::<(T, F)>() }.1)()
+}
+
+/// Place a value at the end of the given bytes, returning it back if there is not enough space
+/// or the storage is not meant for the value.
+#[cfg(not(feature = "boxed-storage"))]
+fn push_value<T>(contents: &mut Contents, value: T, place: Placement) -> Result<*mut T, T> {
+    if !place.accepts(contents) {
+        return Err(value);
+    }
+    let Contents {
+        bytes,
+        padding: total_padding,
+        ..
+    } = contents;
+    let size = size_of::<T>();
+    let align = align_of::<T>();
+
+    let end_ptr = bytes.as_ptr() as usize + bytes.len();
+    let padding = (align - end_ptr % align) % align;
+
+    let Some(value_start_index) = bytes.len().checked_add(padding).filter(|&start| {
+        start
+            .checked_add(size)
+            .is_some_and(|end| end <= bytes.capacity())
+    }) else {
+        return Err(value);
+    };
+
+    unsafe {
+        // SAFETY: We have checked that there is enough space to store `value_start_index + size`
+        // bytes, and the inner type is MaybeUninit.
+        bytes.set_len(value_start_index + size);
+    }
+    *total_padding += padding;
+
+    let value_ptr = bytes[value_start_index..].as_mut_ptr().cast::<T>();
+    // SAFETY: We have checked that the value's bytes are part of `bytes`.
+    unsafe { asan::unpoison(value_ptr, size) };
+    unsafe {
+        // SAFETY: We have mutable access to `bytes` and it is aligned.
+        value_ptr.write(value);
+    }
+    Ok(value_ptr)
+}
+
+/// Turn a pointer to a value into a trait object.
+fn as_any<T: Any + Send>(ptr: *mut ()) -> *mut (dyn Any + Send) {
+    ptr.cast::<T>()
+}
+
+/// Move a value out of the bin, freeing its allocation if it has its own.
+///
+/// # Safety
+///
+/// `ptr` must point to a value of type `T` that is not used again.
+unsafe fn take_value<T>(ptr: *mut ()) -> T {
+    let value = unsafe { ptr.cast::<T>().read() };
+    #[cfg(feature = "boxed-storage")]
+    unsafe {
+        dealloc_box::<T>(ptr);
+    }
+    value
+}
+
+/// Drop a slice of values in the bin's storage.
+#[cfg(not(feature = "boxed-storage"))]
+unsafe fn drop_slice<T>(ptr: *mut (), len: usize) {
+    unsafe { ptr::drop_in_place(ptr::slice_from_raw_parts_mut(ptr.cast::<T>(), len)) };
+}
+
+/// Drop a slice stored with `Box::into_raw`.
+unsafe fn drop_boxed_slice<T>(ptr: *mut (), len: usize) {
+    let slice = ptr::slice_from_raw_parts_mut(ptr.cast::<T>(), len);
+    #[cfg(feature = "zeroize")]
+    unsafe {
+        ptr::drop_in_place(slice);
+        zeroize::zero(slice.cast::<T>(), size_of::<T>() * len);
+        drop(Box::from_raw(slice as *mut [mem::ManuallyDrop<T>]));
+    }
+    #[cfg(not(feature = "zeroize"))]
+    drop(unsafe { Box::from_raw(slice) });
+}
+
+/// Take all the entries out of a list, newest first.
+#[cfg(not(feature = "crossbeam"))]
+fn take_newest_first(entries: &mut Entries) -> impl Iterator<Item = Entry> {
+    mem::take(entries).into_iter()
+}
+
+/// Take all the entries out of a list, newest first.
+#[cfg(feature = "crossbeam")]
+fn take_newest_first(entries: &mut Entries) -> impl Iterator<Item = Entry> {
+    // The queue is first in, first out.
+    let entries: Vec<_> = mem::take(entries).into_iter().collect();
+    entries.into_iter().rev()
+}
+
+/// Drop a value stored with `Box::into_raw`.
+unsafe fn drop_box<T>(ptr: *mut ()) {
+    #[cfg(feature = "zeroize")]
+    unsafe {
+        ptr::drop_in_place(ptr.cast::<T>());
+        zeroize::zero(ptr, size_of::<T>());
+        dealloc_box::<T>(ptr);
+    }
+    #[cfg(not(feature = "zeroize"))]
+    drop(unsafe { Box::from_raw(ptr.cast::<T>()) });
+}
+
+/// Free the allocation of a value stored with `Box::into_raw` without dropping the value.
+#[cfg_attr(
+    not(any(feature = "boxed-storage", feature = "zeroize")),
+    allow(dead_code)
+)]
+unsafe fn dealloc_box<T>(ptr: *mut ()) {
+    drop(unsafe { Box::from_raw(ptr.cast::<mem::ManuallyDrop<T>>()) });
 }
 
-#[cfg(test)]
+#[cfg(all(test, not(loom)))]
 mod tests {
+    use crate::inner::Cursor;
+    use crate::inner::Entry;
     use crate::inner::Inner;
+    #[cfg(not(feature = "boxed-storage"))]
+    use crate::inner::Storage;
+    #[cfg(not(feature = "boxed-storage"))]
+    use crate::inner::MAX_FREE;
     use crate::test_util::assert_thread_safe;
     use crate::test_util::CallOnDrop;
+    use crate::Key;
+    use crate::Tag;
     use std::cell::Cell;
     use std::marker::PhantomData;
+    use std::mem::size_of;
     use std::sync::atomic::AtomicBool;
+    use std::sync::atomic::AtomicUsize;
     use std::sync::atomic::Ordering::SeqCst;
+    use std::sync::Mutex;
 
     #[test]
     fn bin() {
         let destructor_called = AtomicBool::new(false);
 
         let mut bin = Inner::new();
-        assert!(bin.destructors.is_empty());
+        assert_eq!(bin.len(), 0);
         assert!(bin.data.is_empty());
 
         let val = CallOnDrop(|| assert!(!destructor_called.swap(true, SeqCst)));
         bin.add(val);
-        assert_eq!(bin.destructors.len(), 1);
+        assert_eq!(bin.len(), 1);
         assert!(!destructor_called.load(SeqCst));
 
         bin.add(253_u16);
-        assert_eq!(bin.destructors.len(), 2);
+        assert_eq!(bin.len(), 2);
+        #[cfg(not(feature = "crossbeam"))]
         assert_eq!(
-            unsafe { *(bin.destructors.iter_assume_init_mut().next().unwrap().0 as *const u16) },
+            unsafe { *(bin.destructors.iter_assume_init_mut().next().unwrap().ptr as *const u16) },
             253
         );
 
         bin.add(Box::new(6));
-        assert_eq!(bin.destructors.len(), 3);
+        assert_eq!(bin.len(), 3);
         assert!(!destructor_called.load(SeqCst));
 
         bin.clear();
@@ -245,6 +2331,341 @@ mod tests {
         DESTRUCTOR_CALLED.with(|cell| cell.set(false));
     }
 
+    #[test]
+    #[cfg(not(feature = "boxed-storage"))]
+    fn reuse_storage() {
+        let mut bin = Inner::new();
+
+        bin.add([0_u8; 2000]);
+        bin.add([0_u8; 100]);
+        let size = bin.size();
+
+        for _ in 0..10 {
+            bin.clear();
+            assert_eq!(bin.size(), 0);
+            assert_eq!(bin.free_size(), size);
+            assert!(bin.data.is_empty());
+
+            bin.add([0_u8; 2000]);
+            bin.add([0_u8; 100]);
+            assert_eq!(bin.size(), size);
+            assert!(bin.free.get_mut().unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "boxed-storage"))]
+    fn size_classes() {
+        #[repr(align(256))]
+        struct Page(#[allow(dead_code)] u8);
+
+        let bin = Inner::new();
+        for _ in 0..4 {
+            bin.add(1_u8);
+            bin.add(Page(2));
+        }
+        // The pages are stored together, so only the first one can need padding.
+        assert!(bin.stats().padding < 256);
+        assert_eq!(bin.data.len(), 2);
+    }
+
+    #[test]
+    fn add_many() {
+        let dropped = AtomicUsize::new(0);
+
+        let mut bin = Inner::new();
+        bin.add_many((0..100).map(|_| {
+            CallOnDrop(|| {
+                dropped.fetch_add(1, SeqCst);
+            })
+        }));
+        bin.add_many((0..100).map(|_| ()));
+        // The values are all placed in one storage, so each call only needs one entry.
+        #[cfg(not(feature = "boxed-storage"))]
+        assert_eq!(bin.len(), 2);
+        assert_eq!(dropped.load(SeqCst), 0);
+
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 100);
+    }
+
+    #[test]
+    #[cfg(not(feature = "boxed-storage"))]
+    fn slabs() {
+        let bin = Inner::new();
+        for _ in 0..100 {
+            bin.add([0_u8; 3]);
+            bin.add([0_u16; 5]);
+        }
+        // Once both types have been added often enough, they are given slabs of their own.
+        let slabs: Vec<_> = (bin.data.iter())
+            .filter_map(|storage| storage.bytes.try_lock().unwrap().slab)
+            .collect();
+        assert_eq!(slabs.len(), 2);
+        assert_ne!(slabs[0], slabs[1]);
+    }
+
+    #[test]
+    #[cfg(not(feature = "boxed-storage"))]
+    fn max_segment_size() {
+        let mut bin = Inner::new();
+        bin.max_segment_size = 2048;
+
+        for _ in 0..100 {
+            bin.add([0_u8; 100]);
+        }
+        assert!(bin.data.iter().all(|s| s.capacity <= 2048));
+
+        bin.add([0_u8; 4096]);
+        assert_eq!(bin.data.head().unwrap().capacity, 4096 + 1);
+    }
+
+    #[test]
+    #[cfg(all(feature = "zeroize", not(feature = "boxed-storage")))]
+    fn zeroize() {
+        let mut bin = Inner::new();
+        bin.add([0xAB_u8; 16]);
+        bin.clear();
+
+        let free = bin.free.get_mut().unwrap();
+        let bytes = free[0].bytes.get_mut().bytes.as_ptr().cast::<u8>();
+        // SAFETY: The bytes the value was in have been overwritten with zeros.
+        assert_eq!(unsafe { std::slice::from_raw_parts(bytes, 16) }, [0; 16]);
+    }
+
+    #[test]
+    #[cfg(not(feature = "boxed-storage"))]
+    fn huge_values() {
+        let mut bin = Inner::new();
+        // Values larger than the maximum segment size count as huge too.
+        bin.max_segment_size = 2048;
+
+        bin.add([0_u8; 4096]);
+        bin.add(5_u8);
+        // The small value gets an ordinary storage, rather than one sized after the huge value.
+        assert_eq!(bin.data.len(), 2);
+        assert_eq!(bin.data.head().unwrap().capacity, 1024);
+
+        bin.clear();
+        // Only the ordinary storage is kept for reuse.
+        let free = bin.free.get_mut().unwrap();
+        assert_eq!(free.len(), 1);
+        assert_eq!(free[0].capacity, 1024);
+    }
+
+    #[test]
+    #[cfg(not(feature = "boxed-storage"))]
+    fn clear_compact() {
+        let mut bin = Inner::new();
+
+        for _ in 0..100 {
+            bin.add([0_u8; 100]);
+        }
+        assert!(bin.data.len() > 1);
+
+        drop(bin.detach_ready(true));
+        assert!(bin.data.is_empty());
+        let free = bin.free.get_mut().unwrap();
+        assert_eq!(free.len(), 1);
+        assert_eq!(free[0].capacity, 100 * 100);
+
+        drop(bin.detach_ready(true));
+        assert_eq!(bin.size() + bin.free_size(), 0);
+    }
+
+    #[test]
+    #[cfg(not(feature = "boxed-storage"))]
+    fn cap_free() {
+        let mut bin = Inner::new();
+        bin.max_segment_size = 1024;
+
+        for _ in 0..MAX_FREE * 2 {
+            bin.add([0_u8; 1000]);
+        }
+        assert_eq!(bin.data.len(), MAX_FREE * 2);
+
+        bin.clear();
+        assert_eq!(bin.free.get_mut().unwrap().len(), MAX_FREE);
+        assert_eq!(bin.free_size(), MAX_FREE * 1024);
+    }
+
+    #[test]
+    #[cfg(not(feature = "boxed-storage"))]
+    fn add_iter() {
+        let mut bin = Inner::new();
+
+        bin.add_iter(0..10_000_u64);
+        assert_eq!(bin.len(), 10_000);
+        assert_eq!(bin.data.len(), 1);
+        assert_eq!(bin.size(), 10_000 * 8 + 8);
+
+        bin.clear();
+    }
+
+    #[test]
+    #[cfg(not(feature = "boxed-storage"))]
+    fn try_add() {
+        assert!(Storage::try_new(usize::MAX).is_none());
+
+        let mut bin = Inner::new();
+        assert_eq!(bin.try_add(5_u64), Ok(()));
+        assert_eq!(bin.len(), 1);
+
+        bin.clear();
+    }
+
+    #[test]
+    #[cfg(not(feature = "boxed-storage"))]
+    fn stats() {
+        let mut bin = Inner::new();
+        bin.add(5_u64);
+        bin.add(6_u64);
+
+        let stats = bin.stats();
+        assert_eq!(stats.values, 2);
+        assert_eq!(stats.segments, 1);
+        assert_eq!(stats.capacity, 1024);
+        assert!((16..24).contains(&stats.used));
+        assert_eq!(stats.free, 0);
+
+        bin.clear();
+        let stats = bin.stats();
+        assert_eq!((stats.values, stats.segments, stats.used), (0, 0, 0));
+        assert_eq!((stats.capacity, stats.free), (0, 1024));
+    }
+
+    #[test]
+    #[cfg(not(feature = "boxed-storage"))]
+    fn storage_cache() {
+        use crate::inner::LAST_STORAGE;
+        use std::ptr;
+        use std::sync::atomic::Ordering::Relaxed;
+
+        let mut bin = Inner::new();
+        bin.add(5_u64);
+        let id = bin.cache_id.load(Relaxed);
+        let (cached_id, cached) = LAST_STORAGE.with(Cell::get);
+        assert_eq!(cached_id, id);
+        assert!(ptr::eq(cached, bin.data.head().unwrap()));
+        assert!(bin.cached_storage().is_some());
+
+        bin.clear();
+        assert_eq!(bin.cache_id.load(Relaxed), 0);
+        assert!(bin.cached_storage().is_none());
+
+        bin.add(5_u64);
+        assert_ne!(bin.cache_id.load(Relaxed), id);
+        bin.clear();
+    }
+
+    #[test]
+    fn buffers() {
+        let dropped = AtomicUsize::new(0);
+
+        let mut bin = Inner::new();
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    for _ in 0..100 {
+                        bin.add(CallOnDrop(|| {
+                            dropped.fetch_add(1, SeqCst);
+                        }));
+                    }
+                });
+            }
+        });
+        assert!(bin.buffers.iter().count() <= 4);
+        assert_eq!(bin.stats().values, 400);
+        assert_eq!(bin.len(), 400);
+
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 400);
+    }
+
+    #[test]
+    fn clear_partial() {
+        let dropped = Mutex::new(Vec::new());
+
+        let mut bin = Inner::new();
+        for i in 0..10 {
+            let dropped = &dropped;
+            bin.add(CallOnDrop(move || dropped.lock().unwrap().push(i)));
+        }
+
+        assert_eq!(bin.clear_partial(3, true).values, 3);
+        assert_eq!(*dropped.lock().unwrap(), [0, 1, 2]);
+
+        assert_eq!(bin.clear_partial(2, false).values, 2);
+        assert_eq!(*dropped.lock().unwrap(), [0, 1, 2, 9, 8]);
+        assert_eq!(bin.len(), 5);
+
+        assert_eq!(bin.clear_partial(10, true).values, 5);
+        assert_eq!(*dropped.lock().unwrap(), [0, 1, 2, 9, 8, 3, 4, 5, 6, 7]);
+        assert!(bin.data.is_empty());
+    }
+
+    #[test]
+    fn clear_tag() {
+        let dropped = Mutex::new(Vec::new());
+        let a = Tag::new("a");
+        let b = Tag::new("b");
+
+        let mut bin = Inner::new();
+        for (i, tag) in [Some(a), Some(b), None, Some(a)].into_iter().enumerate() {
+            let dropped = &dropped;
+            let value = CallOnDrop(move || dropped.lock().unwrap().push(i));
+            bin.add_at(value, tag, &mut Cursor::default());
+        }
+
+        assert_eq!(bin.clear_tag(a).values, 2);
+        assert_eq!(*dropped.lock().unwrap(), [3, 0]);
+        assert_eq!(bin.clear_tag(a).values, 0);
+
+        bin.clear();
+        assert_eq!(*dropped.lock().unwrap(), [3, 0, 2, 1]);
+    }
+
+    #[test]
+    fn remove_keyed() {
+        let dropped = AtomicUsize::new(0);
+        let drop = |n| {
+            let dropped = &dropped;
+            CallOnDrop(move || _ = dropped.fetch_add(n, SeqCst))
+        };
+        let cancelled = Key::next();
+        let dropped_now = Key::next();
+
+        let mut bin = Inner::new();
+        bin.add_keyed(drop(1), cancelled);
+        bin.add_keyed(drop(10), dropped_now);
+        bin.add(drop(100));
+
+        // The entries are left in place, and skipped once they are taken out.
+        assert!(bin.cancel(cancelled));
+        assert_eq!(bin.drop_now(dropped_now).values, 1);
+        assert_eq!(dropped.load(SeqCst), 10);
+        assert_eq!(bin.len(), 1);
+        assert_eq!(bin.removed.len(), 2);
+
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 110);
+        assert!(bin.removed.is_empty());
+        assert!(bin.keyed.get_mut().unwrap().is_empty());
+    }
+
+    #[test]
+    fn entry_extra() {
+        let mut bin = Inner::new();
+        bin.add(5_u32);
+        bin.add_keyed(6_u32, Key::next());
+        let entries = bin.entries();
+        // Only values with labels need more than a pointer and how to drop them.
+        assert!(size_of::<Entry>() <= 5 * size_of::<usize>());
+        assert_eq!(entries[0].extra.is_some(), cfg!(feature = "debug-types"));
+        assert!(entries[1].extra.as_ref().unwrap().labels.key.is_some());
+        bin.clear();
+    }
+
     #[test]
     fn thread_safe() {
         assert_thread_safe::<Inner<'_>>();
@@ -0,0 +1,55 @@
+// Keys only need to be unique, so they are left out of loom's model.
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::hash::BuildHasherDefault;
+use std::hash::Hasher;
+use std::num::NonZeroU64;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+/// The key to give to the next value that is added with one.
+static NEXT_KEY: AtomicU64 = AtomicU64::new(1);
+
+/// A handle to a value in a bin, returned by [`Bin::add_keyed`](crate::Bin::add_keyed).
+///
+/// Keys are unique across all bins, so a key never refers to a different value than the one it
+/// was created for, even after that value has been dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Key(NonZeroU64);
+
+impl Key {
+    /// Create a key that has not been used before.
+    pub(crate) fn next() -> Self {
+        let key = NEXT_KEY.fetch_add(1, Ordering::Relaxed);
+        Self(NonZeroU64::new(key).expect("ran out of keys"))
+    }
+}
+
+/// A map from keys, which unlike a `HashMap` with the default hasher can be created in a constant.
+pub(crate) type KeyMap<V> = HashMap<Key, V, BuildHasherDefault<KeyHasher>>;
+
+/// A set of keys, which can be created in a constant like [`KeyMap`].
+pub(crate) type KeySet = HashSet<Key, BuildHasherDefault<KeyHasher>>;
+
+/// Hashes keys by spreading out the bits of their number.
+///
+/// Keys are never chosen by an attacker, so they don't need a randomly seeded hasher.
+#[derive(Debug, Default)]
+pub(crate) struct KeyHasher(u64);
+
+impl Hasher for KeyHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write_u64(self.0.rotate_left(8) ^ u64::from(byte));
+        }
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        // Consecutive keys get hashes that differ in their top bits, which the map looks at.
+        self.0 = n.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    }
+}
@@ -7,6 +7,7 @@
 //! # Example
 //!
 //! ```
+//! # #[cfg(feature = "std")] {
 //! let bin = drop_bin::Bin::new();
 //!
 //! let some_data = "Hello World!".to_owned();
@@ -15,7 +16,59 @@
 //!
 //! bin.clear();
 //! // `some_data`'s destructor has been run.
+//! # }
 //! ```
+//!
+//! # Features
+//!
+//! - `asan`: Annotate storage segments for the address sanitizer, so that overruns and uses of
+//!   values after they have been cleared are caught at the exact access. The crate must then be
+//!   built with `-Zsanitizer=address`.
+//! - `boxed-storage`: Store every value in its own `Box` instead of packing values into shared
+//!   storage segments. This is slower, but lets tools like Miri and sanitizers check each value
+//!   individually, which is useful in downstream test suites.
+//! - `cgroup`: Clear bins as the process's cgroup nears its memory limit on Linux, using the
+//!   `cgroup` module.
+//! - `crossbeam`: Keep track of values with `crossbeam-queue`'s `SegQueue` instead of this crate's
+//!   own concurrent list, for programs that already depend on it.
+//! - `debug-types`: Record the type name of every value added to a bin, when it was added and
+//!   where it was added from, so that they can be listed with [`Bin::pending_types`] and
+//!   [`Bin::pending_callers`] and cleared with [`Bin::clear_where`].
+//! - `decommit`: Let bins tell the operating system that the pages of their emptied storage are
+//!   disposable, with [`Builder::decommit_after_clear`].
+//! - `drop-histogram`: Record how long each destructor takes to run in a [`DropHistogram`], which
+//!   is included in every [`ClearReport`].
+//! - `drop-times`: Learn how long each type's destructor takes to run, so that
+//!   [`Bin::estimate_clear_cost`] can estimate how long a clear would take and [`Bin::end_frame`]
+//!   can keep to its budget. [`Builder::incremental`] and [`Builder::max_pause`] size their steps
+//!   by count and by measured pauses instead.
+//! - `ffi`: Expose a C interface in the `ffi` module.
+//! - `futures`: Add [`Bin::sink`], which implements `futures::Sink` so that a bin can be the last
+//!   stage of a stream pipeline.
+//! - `jemalloc`: Provide a hook to purge jemalloc's arenas after a clear in the `trim` module.
+//! - `malloc-trim`: Provide a hook to call glibc's `malloc_trim` after a clear in the `trim`
+//!   module.
+//! - `memory-stats`: Clear bins when the process's resident set size is too large, using the
+//!   `rss` module or [`Builder::clear_above_rss`].
+//! - `mmap`: Back storage segments of a megabyte or more with anonymous memory mappings on Unix.
+//!   Clears tell the operating system that the pages they emptied are disposable, instead of
+//!   relying on the allocator to do it, and the mappings are unmapped once the bin drops the
+//!   segments. It is an error to enable it on other platforms.
+//! - `low-memory`: Clear bins when Windows reports low memory, using the `low_memory` module.
+//! - `signal`: Clear bins when the process receives a Unix signal, using the `signal` module.
+//! - `std` (default): Everything but [`FixedBin`], which only needs `core`. Without it the crate
+//!   is `no_std`, for targets with neither an operating system nor a heap. The other features
+//!   need it, apart from `zeroize`.
+//! - `thread-priority`: Set the priority and CPU affinity of a [`CleanerPool`]'s threads, with
+//!   [`CleanerPool::with_scheduling`].
+//! - `tokio`: Clear bins in step with Tokio's cooperative scheduling budget, using
+//!   [`Bin::clear_cooperative`].
+//! - `watchdog`: Notice destructors that run for too long during a clear, with
+//!   [`Builder::watchdog`].
+//! - `zeroize`: Overwrite the memory that values were stored in with zeros once they have been
+//!   dropped, so that secrets that passed through a bin don't linger in reused storage or freed
+//!   pages, and add values that implement `zeroize::Zeroize` with [`Bin::add_zeroizing`].
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 #![warn(
     clippy::pedantic,
     rust_2018_idioms,
@@ -24,128 +77,2858 @@
     unsafe_op_in_unsafe_fn
 )]
 
-use std::sync::atomic;
-use std::sync::atomic::AtomicBool;
-use try_rwlock::TryRwLock;
+#[cfg(feature = "std")]
+use std::any::Any;
+#[cfg(feature = "debug-types")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::collections::VecDeque;
+#[cfg(feature = "std")]
+use std::fmt;
+#[cfg(feature = "std")]
+use std::fmt::Debug;
+#[cfg(feature = "std")]
+use std::fmt::Formatter;
+#[cfg(feature = "std")]
+use std::future;
+#[cfg(feature = "std")]
+use std::future::Future;
+#[cfg(feature = "std")]
+use std::io;
+#[cfg(feature = "std")]
+use std::mem;
+#[cfg(feature = "std")]
+use std::mem::size_of;
+#[cfg(feature = "debug-types")]
+use std::panic::Location;
+#[cfg(feature = "std")]
+use std::pin::Pin;
+#[cfg(feature = "std")]
+use std::sync::mpsc;
+#[cfg(feature = "std")]
+use std::sync::Arc;
+#[cfg(feature = "std")]
+use std::sync::PoisonError;
+#[cfg(feature = "std")]
+use std::task::Poll;
+#[cfg(feature = "std")]
+use std::thread;
+#[cfg(feature = "std")]
+use std::thread::JoinHandle;
+#[cfg(feature = "std")]
+use std::time::Duration;
+#[cfg(feature = "std")]
+use std::time::Instant;
+#[cfg(feature = "std")]
+use sync::atomic;
+#[cfg(feature = "std")]
+use sync::atomic::AtomicBool;
+#[cfg(feature = "std")]
+use sync::atomic::AtomicU64;
+#[cfg(feature = "std")]
+use sync::const_fn;
+#[cfg(feature = "std")]
+use sync::AtomicMut;
+#[cfg(feature = "std")]
+use sync::Mutex;
+
+mod sync;
+
+mod zeroize;
+
+mod fixed;
+pub use fixed::FixedBin;
+
+#[cfg(feature = "std")]
+mod slot_lock;
+#[cfg(feature = "std")]
+use slot_lock::ReadGuard;
+#[cfg(feature = "std")]
+use slot_lock::WriteGuard;
+
+#[cfg(feature = "std")]
+mod arenas;
+#[cfg(feature = "std")]
+use arenas::Arenas;
+
+#[cfg(feature = "std")]
+mod asan;
 
+#[cfg(feature = "decommit")]
+mod decommit;
+
+#[cfg(all(feature = "mmap", unix))]
+mod mmap;
+#[cfg(all(feature = "mmap", not(unix)))]
+compile_error!("the `mmap` feature is only supported on Unix");
+
+#[cfg(feature = "std")]
 mod concurrent_list;
+#[cfg(feature = "std")]
 use concurrent_list::ConcurrentList;
 
+#[cfg(all(feature = "std", not(feature = "crossbeam")))]
 mod concurrent_slice;
+#[cfg(all(feature = "std", not(feature = "crossbeam")))]
 use concurrent_slice::ConcurrentSlice;
 
+#[cfg(all(feature = "std", not(feature = "crossbeam")))]
 mod concurrent_vec;
+#[cfg(all(feature = "std", not(feature = "crossbeam")))]
 use concurrent_vec::ConcurrentVec;
 
+#[cfg(feature = "std")]
 mod inner;
+#[cfg(feature = "std")]
+use inner::Detached;
+#[cfg(feature = "std")]
 use inner::Inner;
 
+#[cfg(feature = "std")]
+mod deferred;
+#[cfg(feature = "std")]
+pub use deferred::Deferred;
+
+#[cfg(feature = "std")]
+mod builder;
+#[cfg(feature = "std")]
+pub use builder::Builder;
+
+#[cfg(feature = "std")]
+mod batch;
+#[cfg(feature = "std")]
+pub use batch::BinWriteGuard;
+
+#[cfg(feature = "std")]
+mod emplaced;
+#[cfg(feature = "std")]
+pub use emplaced::Emplaced;
+
+#[cfg(feature = "std")]
+mod group;
+#[cfg(feature = "std")]
+pub use group::Group;
+
+#[cfg(feature = "std")]
+mod region;
+#[cfg(feature = "std")]
+pub use region::Region;
+
+#[cfg(feature = "std")]
+mod tag;
+#[cfg(feature = "std")]
+pub use tag::Tag;
+
+#[cfg(feature = "std")]
+mod key;
+#[cfg(feature = "std")]
+pub use key::Key;
+#[cfg(feature = "std")]
+use key::KeyMap;
+#[cfg(feature = "std")]
+use key::KeySet;
+
+#[cfg(feature = "std")]
+mod limit;
+#[cfg(feature = "std")]
+use limit::Limit;
+#[cfg(feature = "std")]
+pub use limit::Overflow;
+#[cfg(feature = "std")]
+use limit::Reservation;
+
+#[cfg(feature = "std")]
+mod pacer;
+#[cfg(feature = "std")]
+use pacer::Pacer;
+
+#[cfg(feature = "std")]
+mod incremental;
+#[cfg(feature = "std")]
+use incremental::Incremental;
+
+#[cfg(feature = "std")]
+mod shrink;
+#[cfg(feature = "std")]
+use shrink::Shrinker;
+
+#[cfg(feature = "std")]
+mod debounce;
+#[cfg(feature = "std")]
+use debounce::Debounce;
+
+#[cfg(feature = "std")]
+mod growth;
+#[cfg(feature = "std")]
+use growth::GrowthMonitor;
+#[cfg(feature = "std")]
+pub use growth::GrowthWarning;
+
+#[cfg(feature = "std")]
+mod frame;
+#[cfg(feature = "std")]
+pub use frame::FrameReport;
+#[cfg(feature = "std")]
+use frame::Frames;
+
+#[cfg(feature = "std")]
+mod pause;
+#[cfg(feature = "std")]
+use pause::Slicer;
+
+#[cfg(feature = "watchdog")]
+mod watchdog;
+#[cfg(feature = "watchdog")]
+pub use watchdog::HungDestructor;
+
+#[cfg(feature = "std")]
+mod panic_policy;
+#[cfg(feature = "std")]
+pub use panic_policy::PanicPolicy;
+
+#[cfg(feature = "std")]
+mod drop_behavior;
+#[cfg(feature = "std")]
+pub use drop_behavior::DropBehavior;
+#[cfg(feature = "std")]
+use panic_policy::Payload;
+#[cfg(feature = "std")]
+use panic_policy::PoisonOnUnwind;
+
+#[cfg(feature = "std")]
+mod cleanup;
+#[cfg(feature = "std")]
+use cleanup::Cleanups;
+
+#[cfg(feature = "std")]
+mod cleared;
+#[cfg(feature = "std")]
+pub use cleared::Cleared;
+#[cfg(feature = "std")]
+use cleared::Waiters;
+
+#[cfg(feature = "futures")]
+mod sink;
+#[cfg(feature = "futures")]
+pub use sink::BinSink;
+
+#[cfg(feature = "std")]
+mod dropper;
+#[cfg(feature = "std")]
+pub use dropper::Dropper;
+
+#[cfg(feature = "drop-times")]
+mod drop_times;
+#[cfg(feature = "drop-times")]
+use drop_times::DropTimes;
+
+#[cfg(feature = "std")]
+mod report;
+#[cfg(feature = "std")]
+pub use report::ClearAttempt;
+#[cfg(feature = "std")]
+pub use report::ClearCost;
+#[cfg(feature = "std")]
+pub use report::ClearPause;
+#[cfg(feature = "std")]
+pub use report::ClearReport;
+#[cfg(feature = "drop-histogram")]
+pub use report::DropHistogram;
+#[cfg(feature = "std")]
+pub use report::MemoryUsage;
+#[cfg(feature = "std")]
+pub use report::SegmentAlloc;
+#[cfg(feature = "debug-types")]
+pub use report::ValueMeta;
+
+#[cfg(feature = "std")]
+mod weak_bins;
+
+#[cfg(feature = "std")]
+mod pool;
+#[cfg(feature = "std")]
+pub use pool::CleanerPool;
+
+#[cfg(feature = "std")]
+mod idle;
+#[cfg(feature = "std")]
+pub use idle::IdleCleaner;
+
+#[cfg(feature = "std")]
+mod bin_pool;
+#[cfg(feature = "std")]
+pub use bin_pool::BinPool;
+#[cfg(feature = "std")]
+pub use bin_pool::PooledBin;
+
+#[cfg(feature = "thread-priority")]
+mod scheduling;
+#[cfg(feature = "thread-priority")]
+pub use scheduling::Scheduling;
+#[cfg(feature = "thread-priority")]
+pub use scheduling::ThreadPriority;
+
+#[cfg(feature = "std")]
+mod coordinator;
+#[cfg(feature = "std")]
+pub use coordinator::BinUsage;
+#[cfg(feature = "std")]
+pub use coordinator::ClearOrder;
+#[cfg(feature = "std")]
+pub use coordinator::Coordinator;
+
+#[cfg(feature = "std")]
+mod channel;
+#[cfg(feature = "std")]
+pub use channel::channel;
+#[cfg(feature = "std")]
+pub use channel::BinCleaner;
+#[cfg(feature = "std")]
+pub use channel::BinSender;
+
+#[cfg(feature = "std")]
+pub mod sharded;
+
+#[cfg(feature = "std")]
+pub mod realtime;
+
+#[cfg(feature = "std")]
+pub mod qsbr;
+
+#[cfg(feature = "std")]
+pub mod epoch;
+
+#[cfg(all(feature = "cgroup", target_os = "linux"))]
+pub mod cgroup;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
+
+#[cfg(all(feature = "low-memory", windows))]
+pub mod low_memory;
+
+#[cfg(feature = "std")]
+pub mod registry;
+
+#[cfg(feature = "memory-stats")]
+pub mod rss;
+
+#[cfg(all(feature = "signal", unix))]
+pub mod signal;
+
+#[cfg(any(
+    all(feature = "malloc-trim", target_os = "linux", target_env = "gnu"),
+    feature = "jemalloc"
+))]
+pub mod trim;
+
 /// A container that holds values for later destruction.
 ///
 /// It is automatically cleared when it is dropped.
-#[derive(Debug, Default)]
+#[cfg(feature = "std")]
+#[derive(Default)]
 pub struct Bin<'a> {
-    /// The inner data of the bin. If this is locked for writing, the bin is being cleared.
-    inner: TryRwLock<Inner<'a>>,
+    /// The values in the bin, in two arenas that clears take turns with.
+    arenas: Arenas<'a>,
     /// Whether the bin needs to be cleared.
     clear: AtomicBool,
+    /// Whether the storage should be compacted when the bin is next cleared.
+    compact: AtomicBool,
+    /// The number of clears that have completed.
+    epoch: AtomicU64,
+    /// Asynchronous cleanups, which are only run by `clear_async`.
+    cleanups: Cleanups<'a>,
+    /// Tasks waiting for the epoch to advance.
+    waiters: Waiters,
+    /// The threshold in bytes and function to call to trim the allocator after a clear.
+    trim_after_clear: Option<(usize, fn())>,
+    /// The function to call with the report of every clear.
+    on_clear: Option<fn(&ClearReport)>,
+    /// Clears the bin when the RSS gets too high.
+    #[cfg(feature = "memory-stats")]
+    rss_trigger: Option<rss::RssTrigger>,
+    /// The cap on the number of bytes of values in the bin.
+    limit: Option<Limit>,
+    /// Schedules clears to keep the bin near a target size.
+    pacer: Option<Pacer>,
+    /// Drops values a few at a time as others are added.
+    incremental: Option<Incremental<'a>>,
+    /// Frees storage after clears that find the bin underused.
+    shrinker: Option<Shrinker>,
+    /// Puts off clears that are asked for too soon after the last one.
+    debounce: Option<Debounce>,
+    /// Warns when the bin grows for too long without being cleared.
+    growth: Option<GrowthMonitor>,
+    /// Splits clears into slices that fit a target pause.
+    slicer: Option<Slicer<'a>>,
+    /// The values that `end_frame` didn't have time to drop.
+    frames: Frames<'a>,
+    /// The number of values above which clears hand them to the function to be dropped
+    /// elsewhere.
+    offload: Option<(usize, fn(Detached<'a>))>,
+    /// Whether a destructor panic unwound out of a clear.
+    poisoned: AtomicBool,
+    /// Whether the bin's values are leaked rather than dropped when it is dropped.
+    leak_on_drop: AtomicBool,
+    /// Whether the bin has stopped accepting values.
+    sealed: AtomicBool,
+    /// The senders of the channels returned by `subscribe_cleared`.
+    subscribers: Mutex<Vec<mpsc::Sender<ClearReport>>>,
+    /// The pauses of the most recent clears and when they finished, oldest first.
+    history: Mutex<VecDeque<(Instant, ClearPause)>>,
 }
 
-impl<'a> Bin<'a> {
-    /// Create a new bin.
-    #[must_use]
-    pub const fn new() -> Self {
+/// The number of recent clears whose pauses a bin remembers.
+#[cfg(feature = "std")]
+const CLEAR_HISTORY: usize = 64;
+
+/// The number of values `end_frame` drops between checks of the time.
+#[cfg(feature = "std")]
+const FRAME_CHUNK: usize = 16;
+
+/// Return `Pending` once, letting other tasks run.
+#[cfg(feature = "std")]
+async fn yield_now() {
+    let mut yielded = false;
+    future::poll_fn(|cx| {
+        if yielded {
+            return Poll::Ready(());
+        }
+        yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    })
+    .await;
+}
+
+/// Values taken out of a bin to be dropped.
+///
+/// Those that are left are put back in the bin if a destructor panic unwinds or the clear is
+/// cancelled. A cancelled clear still counts the values it did drop.
+#[cfg(feature = "std")]
+struct Remainder<'b, 'a> {
+    bin: &'b Bin<'a>,
+    detached: Option<Detached<'a>>,
+    report: ClearReport,
+}
+
+#[cfg(feature = "std")]
+impl<'b, 'a> Remainder<'b, 'a> {
+    fn new(bin: &'b Bin<'a>, detached: Detached<'a>) -> Self {
         Self {
-            inner: TryRwLock::new(Inner::new()),
-            clear: AtomicBool::new(false),
+            bin,
+            detached: Some(detached),
+            report: ClearReport::default(),
+        }
+    }
+
+    /// Drop up to `n` of the values, poisoning the bin if a destructor panic unwinds.
+    fn clear_some(&mut self, n: usize) -> usize {
+        let Some(detached) = &mut self.detached else {
+            return 0;
+        };
+        let _poison = PoisonOnUnwind::new(&self.bin.poisoned);
+        detached.clear_some(n, &mut self.report)
+    }
+
+    /// Stop putting the values back, returning those left and what was dropped.
+    fn finish(&mut self) -> (Option<Detached<'a>>, ClearReport) {
+        (self.detached.take(), mem::take(&mut self.report))
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for Remainder<'_, '_> {
+    fn drop(&mut self) {
+        if let Some(detached) = self.detached.take() {
+            self.bin.with_inner(|inner| inner.reattach(detached));
+        }
+        if self.report.values != 0 && !thread::panicking() {
+            self.bin.advance_epoch();
+            self.bin.report(&self.report);
+        }
+    }
+}
+
+/// A bin for values that don't borrow anything, which needs no lifetime annotations.
+///
+/// # Example
+///
+/// ```
+/// use drop_bin::OwnedBin;
+///
+/// struct Renderer {
+///     garbage: OwnedBin,
+/// }
+///
+/// let renderer = Renderer {
+///     garbage: OwnedBin::new(),
+/// };
+/// renderer.garbage.add(vec![0_u8; 1024]);
+/// ```
+#[cfg(feature = "std")]
+pub type OwnedBin = Bin<'static>;
+
+#[cfg(feature = "std")]
+impl<'a> Bin<'a> {
+    const_fn! {
+        /// Create a new bin.
+        #[must_use]
+        pub fn new() -> Self {
+            Self::from_arenas(Arenas::new(Inner::new(), Inner::new()), None, None)
+        }
+    }
+
+    const_fn! {
+        /// Create a builder to configure a new bin.
+        pub fn builder() -> Builder {
+            Builder::new()
+        }
+    }
+
+    const_fn! {
+        pub(crate) fn from_arenas(
+            arenas: Arenas<'a>,
+            incremental: Option<Incremental<'a>>,
+            slicer: Option<Slicer<'a>>,
+        ) -> Self {
+            Self {
+                arenas,
+                clear: AtomicBool::new(false),
+                compact: AtomicBool::new(false),
+                epoch: AtomicU64::new(0),
+                cleanups: Cleanups::new(),
+                waiters: Waiters::new(),
+                trim_after_clear: None,
+                on_clear: None,
+                #[cfg(feature = "memory-stats")]
+                rss_trigger: None,
+                limit: None,
+                pacer: None,
+                incremental,
+                shrinker: None,
+                debounce: None,
+                growth: None,
+                slicer,
+                frames: Frames::new(),
+                offload: None,
+                poisoned: AtomicBool::new(false),
+                leak_on_drop: AtomicBool::new(false),
+                sealed: AtomicBool::new(false),
+                subscribers: Mutex::new(Vec::new()),
+                history: Mutex::new(VecDeque::new()),
+            }
         }
     }
 
     /// Add a value to the bin.
     ///
     /// This may drop the value immediately, but will attempt to store it so that it can be dropped
-    /// later.
+    /// later. The bin keeps its values in two arenas, and a clear switches adds over to the other
+    /// one before taking the values out of the first, so adds carry on storing values while it
+    /// waits for adds on other threads and while the destructors run. Values are only dropped
+    /// immediately while the whole bin is locked, such as by [`clear_tag`](Self::clear_tag).
+    ///
+    /// If the bin has a [limit](Builder::limit), its [`Overflow`] policy applies to values that
+    /// would take the bin over it.
+    #[cfg_attr(feature = "debug-types", track_caller)]
     pub fn add<T: Send + 'a>(&self, value: T) {
-        if let Some(inner) = self.inner.try_read() {
+        if let Some(inner) = self.adding::<T>().inner() {
             inner.add(value);
+        }
+    }
+
+    /// Add a value to the bin that is zeroized just before it is dropped, such as a key or a
+    /// password.
+    ///
+    /// Like [`add`](Self::add), the value may be dropped immediately, in which case it is zeroized
+    /// then.
+    #[cfg(feature = "zeroize")]
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub fn add_zeroizing<T: ::zeroize::Zeroize + Send + 'a>(&self, value: T) {
+        self.add(::zeroize::Zeroizing::new(value));
+    }
+
+    /// Add a value to the bin under a tag, so that it can be dropped separately from the rest of
+    /// the bin with [`clear_tag`](Self::clear_tag).
+    ///
+    /// Like [`add`](Self::add), the value may be dropped immediately.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub fn add_tagged<T: Send + 'a>(&self, value: T, tag: Tag) {
+        if let Some(inner) = self.adding::<T>().inner() {
+            inner.add_tagged(value, tag);
+        }
+    }
+
+    /// Add a value to the bin that can be looked at with [`iter_any`](Self::iter_any) until it is
+    /// dropped.
+    ///
+    /// Like [`add`](Self::add), the value may be dropped immediately.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub fn add_any<T: Any + Send>(&self, value: T) {
+        if let Some(inner) = self.adding::<T>().inner() {
+            inner.add_any(value);
+        }
+    }
+
+    /// Add a value to the bin, returning a key that refers to it until it is dropped.
+    ///
+    /// The key can be passed to [`cancel`](Self::cancel) to take the value back out. Like
+    /// [`add`](Self::add), the value may be dropped immediately, in which case the key doesn't
+    /// refer to anything.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub fn add_keyed<T: Send + 'a>(&self, value: T) -> Key {
+        let key = Key::next();
+        if let Some(inner) = self.adding::<T>().inner() {
+            inner.add_keyed(value, key);
+        }
+        key
+    }
+
+    /// Add a value to the bin that must be dropped after the value with the given key, returning
+    /// a key for the new value.
+    ///
+    /// This is for values that depend on another, such as a buffer that must be returned after
+    /// the pool it came from is dropped. Every kind of clear respects the order: partial clears
+    /// keep the value in the bin for as long as the one it must be dropped after is kept, and
+    /// [`drop_now`](Self::drop_now) on the new value drops that one too, first. Like
+    /// [`add`](Self::add), the value may be dropped immediately, and if the other value has
+    /// already been dropped there is nothing to wait for.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub fn add_after<T: Send + 'a>(&self, after: Key, value: T) -> Key {
+        let key = Key::next();
+        if let Some(inner) = self.adding::<T>().inner() {
+            inner.add_after(value, key, after);
+        }
+        key
+    }
+
+    /// Add a value to the bin that clears leave behind until `ready` returns true, such as a
+    /// resource that can only be destroyed once some external acknowledgment has arrived.
+    ///
+    /// Every clear calls `ready` before dropping the value, and keeps the value for a later clear
+    /// if it returns false. The value is put in an allocation of its own, and isn't counted
+    /// towards the bin's [limit](Builder::limit). Unlike [`add`](Self::add), this waits for the
+    /// bin if it is locked rather than dropping the value, like the methods that wait for
+    /// [adds in progress](Self::batch). When the bin itself is dropped, the value is dropped
+    /// whether or not it is ready.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the bin is [sealed](Self::seal), giving the value back rather than dropping it
+    /// before it is ready.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub fn add_when<T: Send + 'a, F: Fn() -> bool + Send + 'a>(
+        &self,
+        value: T,
+        ready: F,
+    ) -> Result<(), T> {
+        if let Some(inner) = self.read_for_add() {
+            inner.add_when(value, ready);
+        } else {
+            let inner = self.write();
+            if self.is_sealed() {
+                return Err(value);
+            }
+            inner.add_when(value, ready);
+        }
+        self.added();
+        Ok(())
+    }
+
+    /// Add a value to the bin unless that would take it over its [limit](Builder::limit), in
+    /// which case the value is given back.
+    ///
+    /// This never blocks, whatever the bin's [`Overflow`] policy is. With
+    /// [`Overflow::Reject`], the overflow callback is called before the value is given back. Like
+    /// [`add`](Self::add), the value may still be dropped immediately if the bin is locked.
+    ///
+    /// Where [`add`](Self::add) aborts the process if the bin's storage can't grow, this gives
+    /// the value back instead.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the bin is full or [sealed](Self::seal), or its storage can't grow.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub fn try_add<T: Send + 'a>(&self, value: T) -> Result<(), T> {
+        if self.is_sealed() {
+            return Err(value);
+        }
+        let Some(reservation) = self.try_reserve(size_of::<T>()) else {
+            return Err(value);
+        };
+        if let Some(inner) = self.read_for_add() {
+            inner.try_add(value)?;
+            reservation.keep();
+            if let Some(pacer) = &self.pacer {
+                pacer.add(size_of::<T>());
+            }
+        } else {
+            // Just drop the value if the whole bin is locked.
+        }
+
+        self.added();
+        Ok(())
+    }
+
+    /// Make room for a value of type `T` and get shared access to the bin to add it, respecting
+    /// the bin's limit.
+    ///
+    /// The value should just be dropped if the bin is full or the whole bin is locked.
+    pub(crate) fn adding<T>(&self) -> Adding<'_, 'a> {
+        match self.reserve(size_of::<T>()) {
+            Some(reservation) => self.adding_reserved::<T>(reservation),
+            None => Adding {
+                bin: None,
+                inner: None,
+            },
+        }
+    }
+
+    /// Get shared access to the bin to add a value of type `T` that room has been made for.
+    pub(crate) fn adding_reserved<T>(&self, reservation: Reservation<'_>) -> Adding<'_, 'a> {
+        // If the value is dropped instead, the room made for it is given back.
+        let inner = self.read_for_add();
+        if inner.is_some() {
+            reservation.keep();
+            if let Some(pacer) = &self.pacer {
+                pacer.add(size_of::<T>());
+            }
+        }
+        Adding {
+            bin: Some(self),
+            inner,
+        }
+    }
+
+    /// Add an asynchronous cleanup to the bin, such as gracefully closing a connection.
+    ///
+    /// The future is not polled until [`clear_async`](Self::clear_async) is awaited; other kinds
+    /// of clear leave it in the bin. If the bin is dropped first, the future is dropped without
+    /// being run.
+    pub fn add_async<F: Future<Output = ()> + Send + 'a>(&self, future: F) {
+        if self.is_sealed() {
+            return;
+        }
+        self.cleanups.push(future);
+    }
+
+    /// Add every value from an iterator to the bin.
+    ///
+    /// The iterator's size hint is used to allocate space for all of the values at once, which is
+    /// cheaper than growing the bin's storage one value at a time. Like [`add`](Self::add), the
+    /// values may be dropped immediately. If the bin has a [limit](Builder::limit), the values are
+    /// added one at a time instead.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub fn add_iter<T: Send + 'a, I: IntoIterator<Item = T>>(&self, iter: I) {
+        if self.limit.is_some() {
+            for value in iter {
+                self.add(value);
+            }
+            return;
+        }
+
+        if let Some(inner) = self.read_for_add() {
+            if let Some(pacer) = &self.pacer {
+                let mut count = 0;
+                inner.add_iter(iter.into_iter().inspect(|_| count += 1));
+                pacer.add(count * size_of::<T>());
+            } else {
+                inner.add_iter(iter);
+            }
+        } else {
+            iter.into_iter().for_each(drop);
+        }
+
+        self.added();
+    }
+
+    /// Add a boxed value to the bin, which may be unsized.
+    ///
+    /// Only the box's pointer is stored, along with whatever it needs to drop the value, so
+    /// values like `Box<dyn Trait + Send>` and `Box<[u8]>` can be added without first wrapping
+    /// them in another box. To drop the elements of a boxed slice in one go, use
+    /// [`add_slice`](Self::add_slice) instead.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub fn add_box<T: ?Sized + Send + 'a>(&self, value: Box<T>) {
+        self.add(value);
+    }
+
+    /// Add every value from an iterator to the bin, to be dropped in one go.
+    ///
+    /// Like [`add_iter`](Self::add_iter), space for the values is set aside up front, but values
+    /// that end up next to each other are also recorded together, so a batch of values takes up
+    /// a single entry and their destructors are run in a tight loop.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub fn add_many<T: Send + 'a, I: IntoIterator<Item = T>>(&self, iter: I) {
+        if self.limit.is_some() {
+            for value in iter {
+                self.add(value);
+            }
+            return;
+        }
+
+        if let Some(inner) = self.read_for_add() {
+            if let Some(pacer) = &self.pacer {
+                let mut count = 0;
+                inner.add_many(iter.into_iter().inspect(|_| count += 1));
+                pacer.add(count * size_of::<T>());
+            } else {
+                inner.add_many(iter);
+            }
+        } else {
+            iter.into_iter().for_each(drop);
+        }
+
+        self.added();
+    }
+
+    /// Add a boxed slice to the bin, to be dropped in one go.
+    ///
+    /// The elements are left in the slice's allocation rather than being copied into the bin's
+    /// storage, and the whole slice takes up a single entry. Like [`add`](Self::add), the slice
+    /// may be dropped immediately.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub fn add_slice<T: Send + 'a>(&self, slice: Box<[T]>) {
+        if let Some(inner) = self.adding::<Box<[T]>>().inner() {
+            inner.add_slice(slice);
+        }
+    }
+
+    /// Add a vector to the bin, to be dropped in one go.
+    ///
+    /// This is [`add_slice`](Self::add_slice) for vectors, so any spare capacity the vector has is
+    /// freed first.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub fn add_vec<T: Send + 'a>(&self, vec: Vec<T>) {
+        self.add_slice(vec.into_boxed_slice());
+    }
+
+    /// Add a reference-counted value to the bin only if this is its last strong reference.
+    ///
+    /// Dropping an `Arc` is cheap unless it is the last one, so other references are dropped
+    /// straight away. The last one has its value taken out and added like [`add`](Self::add);
+    /// when several threads drop references at once, exactly one of them adds the value.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub fn add_shared<T: Send + Sync + 'a>(&self, arc: Arc<T>) {
+        if let Some(value) = Arc::into_inner(arc) {
+            self.add(value);
+        }
+    }
+
+    /// Add a value to the bin and get pinned access to it, so that self-referential and
+    /// intrusive types can live in the bin.
+    ///
+    /// The value is put in its own allocation and never moved. Its memory isn't reused until its
+    /// destructor has run at a clear: if the bin is [leaked](Self::leak) or poisoned instead, the
+    /// allocation is leaked along with the value. Like a [`batch`](Self::batch) guard, the
+    /// returned guard puts off clears until it is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the bin is full, [sealed](Self::seal) or locked by another thread, giving the
+    /// value back.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub fn emplace_pinned<T: Send + 'a>(&self, value: T) -> Result<Pin<Emplaced<'_, 'a, T>>, T> {
+        let Some(inner) = self.read_for_add() else {
+            return Err(value);
+        };
+        let Some(reservation) = self.try_reserve(size_of::<T>()) else {
+            return Err(value);
+        };
+        reservation.keep();
+        if let Some(pacer) = &self.pacer {
+            pacer.add(size_of::<T>());
+        }
+        let value = inner.emplace(value);
+        // SAFETY: The value is in its own allocation, which is only freed after the value is
+        // dropped at a clear, and the guard holds the read lock that keeps clears from taking the
+        // value out until it is dropped.
+        Ok(unsafe { Pin::new_unchecked(Emplaced::new(self, inner, value)) })
+    }
+
+    /// Start adding many values to the bin at once.
+    ///
+    /// The returned guard holds on to the bin's lock and the storage it last wrote to, so adding
+    /// thousands of values through it avoids most of the synchronization and searching that
+    /// [`add`](Self::add) does for each one. Clears are put off until the guard is dropped.
+    ///
+    /// Methods that need the whole bin to themselves, such as [`seal`](Self::seal) and
+    /// [`leak`](Self::leak), wait for adds in progress to finish, and the guard counts as one for
+    /// as long as it lives. Calling one of them on the thread that holds the guard never returns.
+    pub fn batch(&self) -> BinWriteGuard<'_, 'a> {
+        self.check_poisoned();
+        // Let a pending clear through before holding on to the lock.
+        self.run_pending_clear();
+        BinWriteGuard::new(self, self.read_unsealed())
+    }
+
+    /// Start a group of values that are dropped together.
+    ///
+    /// Partial clears never drop some of a group's values while keeping others, which matters
+    /// when the values refer to shared state outside the bin. See [`Group`] for details.
+    pub fn group(&self) -> Group<'_, 'a> {
+        Group::new(self)
+    }
+
+    /// Start a region of the bin that can be cleared without touching the rest of it.
+    ///
+    /// See [`Region`] for details.
+    pub fn region(&self) -> Region<'_, 'a> {
+        Region::new(self)
+    }
+
+    /// Add a raw pointer to the bin, to be cleaned up by calling `destructor` on it.
+    ///
+    /// This allows resources that aren't Rust values, such as those owned by C libraries or custom
+    /// allocators, to be destroyed along with the rest of the bin's contents. Like [`add`](Self::add),
+    /// the destructor may be called immediately if the bin is currently locked.
+    ///
+    /// # Safety
+    ///
+    /// It must be safe to call `destructor` with `ptr` exactly once, on any thread, at any point
+    /// during `'a`.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub unsafe fn add_raw(&self, ptr: *mut (), destructor: unsafe fn(*mut ())) {
+        if let Some(inner) = self.read_for_add() {
+            // SAFETY: Upheld by the caller.
+            unsafe { inner.add_raw(ptr, destructor) };
         } else {
-            // Just drop the value if the bin is being cleared.
+            // SAFETY: Upheld by the caller.
+            unsafe { destructor(ptr) };
         }
 
-        self.try_clear();
+        self.added();
     }
 
     /// Clear the bin, dropping all values that have been previously added to it.
     ///
     /// This may not clear the bin immediately if another thread is currently adding a value to the
-    /// bin.
+    /// bin, in which case the clear finishes once it is done. Values added from then on are kept
+    /// for the next clear, and other threads can keep adding them while the destructors run.
+    ///
+    /// If the bin has a [minimum interval between clears](Builder::min_clear_interval), this
+    /// does nothing if the bin was cleared too recently, leaving a clear to run on the first add
+    /// after the interval is up.
     pub fn clear(&self) {
+        self.check_poisoned();
+        if !(self.debounce.as_ref()).is_none_or(Debounce::try_start) {
+            return;
+        }
         self.clear.store(true, atomic::Ordering::Relaxed);
 
-        self.try_clear();
+        self.run_pending_clear();
     }
 
-    /// Attempt to the clear the bin.
-    fn try_clear(&self) {
-        if self.clear.load(atomic::Ordering::Relaxed) {
-            if let Some(mut inner) = self.inner.try_write() {
-                self.clear.store(false, atomic::Ordering::Relaxed);
-                inner.clear();
-            }
+    /// Clear the bin like [`clear`](Self::clear), even if it was cleared less than its
+    /// [minimum interval](Builder::min_clear_interval) ago.
+    pub fn force_clear(&self) {
+        self.check_poisoned();
+        if let Some(debounce) = &self.debounce {
+            debounce.start();
         }
-    }
+        self.clear.store(true, atomic::Ordering::Relaxed);
 
-    /// Get the size of the bin in bytes.
-    #[must_use]
-    pub fn size(&self) -> usize {
-        self.inner.try_read().map_or(0, |inner| inner.size())
+        self.run_pending_clear();
     }
-}
 
-impl<'a> Drop for Bin<'a> {
-    fn drop(&mut self) {
-        self.inner.get_mut().clear();
+    /// Clear the bin like [`clear`](Self::clear), then shrink its storage down to a single segment
+    /// sized to fit what the bin held.
+    ///
+    /// This returns memory left over from a spike in usage to the allocator, while keeping enough
+    /// space that a similar workload won't need to allocate again.
+    pub fn clear_compact(&self) {
+        self.compact.store(true, atomic::Ordering::Relaxed);
+        self.clear();
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use crate::test_util::assert_thread_safe;
-    use crate::test_util::CallOnDrop;
-    use crate::Bin;
-    use std::sync::atomic::AtomicBool;
-    use std::sync::atomic::Ordering::SeqCst;
 
-    #[test]
-    fn clear() {
-        let destructor_called = AtomicBool::new(false);
+    /// Clear the bin if that can be done without waiting, returning what happened.
+    ///
+    /// Unlike [`clear`](Self::clear), this doesn't leave a clear pending when adds in progress are
+    /// in the way, so schedulers can check whether clearing now is possible without committing to
+    /// it. A clear that is already pending is finished if it can be.
+    pub fn try_clear(&self) -> ClearAttempt {
+        self.check_poisoned();
+        let pending = self.clear.load(atomic::Ordering::Relaxed)
+            || self.arenas.is_retired()
+            || self.slicer.as_ref().is_some_and(Slicer::has_rest);
+        let mut inner = match self.arenas.try_lock(&self.clear) {
+            Ok(inner) => inner,
+            Err(readers) => return ClearAttempt::Busy { readers },
+        };
+        if let Some(detached) = self.take_retiring() {
+            inner.reattach(detached);
+        }
+        if let Some(detached) = self.take_carried() {
+            inner.reattach(detached);
+        }
+        if !pending && inner.is_empty() {
+            return ClearAttempt::Empty;
+        }
+        let items = self.clear_retired(inner);
+        ClearAttempt::Cleared { items }
+    }
 
-        let bin = Bin::new();
+    /// Clear the bin like [`try_clear`](Self::try_clear), retrying for up to `timeout` while adds
+    /// in progress are in the way.
+    ///
+    /// This is for shutdown paths that must not hang: if the adds haven't finished by the
+    /// deadline, it gives up and returns [`ClearAttempt::Busy`] without leaving a clear pending.
+    /// It always times out if this thread holds a [`batch`](Self::batch) guard for the bin.
+    pub fn clear_blocking_with_timeout(&self, timeout: Duration) -> ClearAttempt {
+        let deadline = Instant::now().checked_add(timeout);
+        loop {
+            let attempt = self.try_clear();
+            let ClearAttempt::Busy { .. } = attempt else {
+                return attempt;
+            };
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return attempt;
+            }
+            sync::yield_now();
+        }
+    }
 
-        bin.add(CallOnDrop(
-            || assert!(!destructor_called.swap(true, SeqCst)),
-        ));
-        assert!(!destructor_called.load(SeqCst));
+    /// Clear the bin, then run every asynchronous cleanup added with
+    /// [`add_async`](Self::add_async) to completion.
+    ///
+    /// The cleanups are driven concurrently, and the returned future resolves once all of them
+    /// have finished. Cleanups added while it is running are left for the next call, as are those
+    /// that haven't finished if the future is dropped.
+    pub async fn clear_async(&self) {
+        self.clear();
+        self.cleanups.run().await;
+    }
 
-        bin.clear();
-        assert!(destructor_called.load(SeqCst));
+    /// Clear the bin, yielding to the executor after every `every` destructors.
+    ///
+    /// This lets a large clear run on an ordinary task without starving other tasks on the same
+    /// worker thread. The values are taken out of the bin first, once
+    /// [adds in progress](Self::batch) have finished, so it can be used again straight away. Unlike
+    /// [`clear_async`](Self::clear_async), asynchronous cleanups are left in the bin.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `every` is zero.
+    pub async fn clear_yielding(&self, every: usize) {
+        assert!(every != 0, "must drop at least one value between yields");
+        self.clear_paused(every, yield_now).await;
     }
 
-    #[test]
-    #[allow(clippy::extra_unused_lifetimes)]
-    fn thread_safe<'a>() {
-        assert_thread_safe::<Bin<'a>>();
+    /// Clear the bin like [`clear_yielding`](Self::clear_yielding), but consuming Tokio's
+    /// cooperative scheduling budget for every destructor instead of yielding at a fixed
+    /// interval. Enabled by the `tokio` feature.
+    ///
+    /// This interleaves the clear with other work on the runtime the same way Tokio's own
+    /// resources do. Outside of a Tokio runtime, it never yields.
+    #[cfg(feature = "tokio")]
+    pub async fn clear_cooperative(&self) {
+        self.clear_paused(1, tokio::task::coop::consume_budget)
+            .await;
     }
-}
 
-#[cfg(test)]
-mod test_util {
-    pub(crate) fn assert_thread_safe<T: Send + Sync>() {}
+    /// Take the values out of the bin and drop them `chunk` at a time, awaiting `pause` between
+    /// chunks.
+    ///
+    /// Destructor panics collected along the way are put back in the bin. If the future is dropped
+    /// before it finishes, the values it hasn't dropped yet are put back in the bin too.
+    async fn clear_paused<F: Future<Output = ()>>(
+        &self,
+        chunk: usize,
+        mut pause: impl FnMut() -> F,
+    ) {
+        self.check_poisoned();
+        let detached = self.with_inner(|inner| inner.detach_ready(false));
+        self.emptied();
 
-    pub(crate) struct CallOnDrop<T: FnMut()>(pub(crate) T);
-    impl<T: FnMut()> Drop for CallOnDrop<T> {
-        fn drop(&mut self) {
-            self.0();
+        let mut remainder = Remainder::new(self, detached);
+        // More than `chunk` values are dropped when a chunk ends partway through a group.
+        while remainder.clear_some(chunk) >= chunk {
+            pause().await;
         }
+        let (detached, report) = remainder.finish();
+        let panics = detached.map(|mut detached| detached.take_panics());
+        if let Some(panics) = panics.filter(|panics| !panics.is_empty()) {
+            self.with_inner(|inner| inner.add_panics(panics));
+        }
+
+        self.advance_epoch();
+        self.report(&report);
+    }
+
+    /// Drop the `n` values that were added to the bin longest ago, keeping the rest.
+    ///
+    /// This returns the number of values dropped. Unlike [`clear`](Self::clear) it is not retried
+    /// later, so nothing is dropped if another thread is currently adding a value. The storage of
+    /// the dropped values is only reused once the bin is empty.
+    ///
+    /// Values that different threads added since the last clear are ordered by thread rather than
+    /// exactly by when they were added, since each thread records its values separately.
+    pub fn clear_oldest(&self, n: usize) -> usize {
+        self.clear_some(|inner| inner.clear_partial(n, true))
+    }
+
+    /// Drop the `n` values that were most recently added to the bin, keeping the rest.
+    ///
+    /// This behaves like [`clear_oldest`](Self::clear_oldest) otherwise.
+    pub fn clear_newest(&self, n: usize) -> usize {
+        self.clear_some(|inner| inner.clear_partial(n, false))
+    }
+
+    /// Clear the bin if its values take up more than `high` bytes, returning the number of values
+    /// dropped.
+    ///
+    /// Without a `low` watermark the bin is emptied. With one, only the values added longest ago
+    /// are dropped, until the rest take up at most `low` bytes, so that a bin hovering around
+    /// `high` isn't cleared again until it has grown by `high - low` bytes. Bytes are counted like
+    /// [`bytes_used`](Self::bytes_used), and like [`clear_oldest`](Self::clear_oldest) this does
+    /// nothing if another thread is currently adding a value.
+    pub fn clear_if_above(&self, high: usize, low: Option<usize>) -> usize {
+        let Some(low) = low else {
+            if self.bytes_used() <= high {
+                return 0;
+            }
+            // Values that aren't ready are kept, like in a full clear.
+            return match self.try_clear() {
+                ClearAttempt::Cleared { items } => items,
+                ClearAttempt::Busy { .. } | ClearAttempt::Empty => 0,
+            };
+        };
+        self.clear_some(|inner| {
+            if inner.bytes_used() <= high {
+                ClearReport::default()
+            } else {
+                inner.clear_down_to(low)
+            }
+        })
+    }
+
+    /// Drop only the values that `predicate` picks out, going by their type, size, age and tag.
+    /// Enabled by the `debug-types` feature.
+    ///
+    /// This returns the number of values dropped, and like [`clear_oldest`](Self::clear_oldest)
+    /// does nothing if another thread is currently adding a value. The predicate is called once
+    /// for each value, newest first, while the bin is locked, so it must not use the bin.
+    ///
+    /// ```
+    /// use drop_bin::Bin;
+    /// use std::time::Duration;
+    ///
+    /// let bin = Bin::new();
+    /// bin.add(vec![0_u8; 4096]);
+    /// bin.clear_where(|meta| meta.bytes > 1_000_000 || meta.age > Duration::from_secs(30));
+    /// ```
+    #[cfg(feature = "debug-types")]
+    pub fn clear_where(&self, predicate: impl FnMut(&ValueMeta) -> bool) -> usize {
+        self.clear_some(|inner| inner.clear_where(predicate))
+    }
+
+    /// Drop only the values that were added with the given tag.
+    ///
+    /// This returns the number of values dropped, and like [`clear_oldest`](Self::clear_oldest)
+    /// does nothing if another thread is currently adding a value.
+    pub fn clear_tag(&self, tag: impl Into<Tag>) -> usize {
+        let tag = tag.into();
+        self.clear_some(|inner| inner.clear_tag(tag))
+    }
+
+    /// Drop as many values as fit in the time left in a frame, carrying the rest forward to the
+    /// next call.
+    ///
+    /// This is meant to be called at the end of every frame of a game or render loop, with the
+    /// time left before the frame's deadline. Once the values carried forward from earlier frames
+    /// have been dropped, the bin's values are taken out like a clear and dropped until the budget
+    /// runs out, a few at a time. Values that are carried forward are also dropped by the next
+    /// [`clear`](Self::clear). The returned report says how far behind the bin is falling.
+    ///
+    /// With the `drop-times` feature, values are only dropped if their types' learned drop times
+    /// say they will fit in what is left of the budget, so a value expected to take longer than a
+    /// whole frame is only dropped by a clear.
+    ///
+    /// Like [`clear_oldest`](Self::clear_oldest), this doesn't wait for other threads: it takes no
+    /// values out if another thread is currently adding one.
+    pub fn end_frame(&self, budget: Duration) -> FrameReport {
+        let deadline = Instant::now().checked_add(budget);
+        self.check_poisoned();
+        let Some(mut frame) = self.frames.try_lock() else {
+            return FrameReport::default();
+        };
+        if frame.carried.is_none() {
+            if let Some(mut inner) = self.arenas.try_write() {
+                if !inner.is_empty() {
+                    frame.carried = Some(inner.detach_ready(false));
+                    drop(inner);
+                    self.emptied();
+                }
+            }
+        }
+
+        let mut report = ClearReport::default();
+        if let Some(detached) = &mut frame.carried {
+            #[cfg(feature = "drop-times")]
+            let times = DropTimes::learned();
+            let poison = PoisonOnUnwind::new(&self.poisoned);
+            while !detached.is_empty() {
+                let left =
+                    deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+                if left == Some(Duration::ZERO) {
+                    break;
+                }
+                // Values that are expected to overrun the frame are left for the next one.
+                #[cfg(feature = "drop-times")]
+                let chunk = left.map_or(FRAME_CHUNK, |left| {
+                    detached.fitting(left, &times, FRAME_CHUNK)
+                });
+                #[cfg(not(feature = "drop-times"))]
+                let chunk = FRAME_CHUNK;
+                if chunk == 0 {
+                    break;
+                }
+                detached.clear_some(chunk, &mut report);
+            }
+            drop(poison);
+        }
+        // Hand the storage back to be reused. If the bin is in use, this is tried again next frame.
+        if let Some(detached) = frame.carried.take_if(|detached| detached.is_empty()) {
+            match self.arenas.try_write() {
+                Some(mut inner) => inner.reattach(detached),
+                None => frame.carried = Some(detached),
+            }
+        }
+        let carried = frame.carried.as_ref().map_or(0, Detached::len);
+        frame.behind = if carried == 0 { 0 } else { frame.behind + 1 };
+        let frames_behind = frame.behind;
+        drop(frame);
+
+        if report.values != 0 {
+            self.advance_epoch();
+            self.report(&report);
+        }
+        FrameReport {
+            dropped: report.values,
+            carried,
+            frames_behind,
+        }
+    }
+
+    /// Run a partial clear if the bin isn't in use, returning the number of values it dropped.
+    fn clear_some(&self, clear: impl FnOnce(&mut Inner<'a>) -> ClearReport) -> usize {
+        self.check_poisoned();
+        let Some(mut inner) = self.arenas.try_write() else {
+            return 0;
+        };
+        let before = inner.bytes_used();
+        let poison = PoisonOnUnwind::new(&self.poisoned);
+        let report = clear(&mut inner);
+        drop(poison);
+        let empty = inner.is_empty();
+        let freed = before.saturating_sub(inner.bytes_used());
+        drop(inner);
+        if empty {
+            self.emptied();
+        } else if freed != 0 {
+            self.freed(freed);
+        }
+        if report.values != 0 {
+            self.advance_epoch();
+            self.report(&report);
+        }
+        report.values
+    }
+
+    /// Check whether a destructor panic unwound out of a clear of the bin.
+    ///
+    /// The values that the clear didn't get to are left in the bin. Until
+    /// [`recover`](Self::recover) is called, adding to the bin or clearing it panics, and dropping
+    /// it discards them without running their destructors. This only happens with the default
+    /// [`PanicPolicy::Propagate`].
+    #[must_use]
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned.load(atomic::Ordering::Relaxed)
+    }
+
+    /// Stop the bin from accepting values, so that it can't grow again after a final clear.
+    ///
+    /// Values added from now on are dropped immediately, [`try_add`](Self::try_add) gives them
+    /// back instead, and futures passed to [`add_async`](Self::add_async) are dropped without
+    /// being run. This waits for [adds in progress](Self::batch) to finish, so once it returns a
+    /// clear empties the bin for good. Sealing a bin can't be undone.
+    pub fn seal(&self) {
+        self.sealed.store(true, atomic::Ordering::SeqCst);
+        self.with_inner(|_| {});
+    }
+
+    /// Check whether the bin has been [sealed](Self::seal).
+    #[must_use]
+    pub fn is_sealed(&self) -> bool {
+        self.sealed.load(atomic::Ordering::SeqCst)
+    }
+
+    /// Discard the values left in a [poisoned](Self::is_poisoned) bin without running their
+    /// destructors, so that it can be used again.
+    ///
+    /// This returns the number of values discarded. Like [`leak`](Self::leak), it waits for adds
+    /// in progress on other threads to finish.
+    pub fn recover(&self) -> usize {
+        let leaked = self.leak();
+        self.poisoned.store(false, atomic::Ordering::Relaxed);
+        leaked
+    }
+
+    /// Panic if the bin is poisoned.
+    fn check_poisoned(&self) {
+        assert!(
+            !self.is_poisoned(),
+            "bin was poisoned by a panicking destructor; call `Bin::recover` to use it again"
+        );
+    }
+
+    /// Make room for a value of `bytes` bytes if the bin has a limit, or return `None` if it
+    /// can't be added.
+    fn reserve(&self, bytes: usize) -> Option<Reservation<'_>> {
+        match &self.limit {
+            Some(limit) => limit.reserve(bytes),
+            None => Some(Reservation::unlimited()),
+        }
+    }
+
+    /// Make room for a value of `bytes` bytes without applying the bin's [`Overflow`] policy, or
+    /// call the overflow callback and return `None` if it doesn't fit.
+    fn try_reserve(&self, bytes: usize) -> Option<Reservation<'_>> {
+        let Some(limit) = &self.limit else {
+            return Some(Reservation::unlimited());
+        };
+        let reservation = limit.try_reserve(bytes);
+        if reservation.is_none() {
+            limit.overflowed();
+        }
+        reservation
+    }
+
+    /// Record that the bin has been emptied, so that it is back under its limit.
+    fn emptied(&self) {
+        if let Some(limit) = &self.limit {
+            limit.reset();
+            // Wake sinks waiting for room.
+            self.waiters.wake_all();
+        }
+    }
+
+    /// Record that `bytes` bytes of values have been dropped from the bin while others were kept,
+    /// making room under its limit.
+    fn freed(&self, bytes: usize) {
+        if let Some(limit) = &self.limit {
+            limit.release(bytes);
+            // Wake sinks waiting for room.
+            self.waiters.wake_all();
+        }
+    }
+
+    /// Get shared access to the bin to add a value to it.
+    ///
+    /// A pending clear is attempted first. If other adds are still keeping it out, this gives up
+    /// instead of taking the lock as well, so that the clear can get through as soon as they
+    /// finish.
+    fn read_for_add(&self) -> Option<ReadGuard<'_, Inner<'a>>> {
+        self.check_poisoned();
+        self.run_pending_clear();
+        self.read_unsealed()
+    }
+
+    /// Lock the active arena for adding, unless the bin has been sealed.
+    fn read_unsealed(&self) -> Option<ReadGuard<'_, Inner<'a>>> {
+        // The flag is checked under the lock, so that `seal` can wait for adds that missed it.
+        self.arenas.read().filter(|_| !self.is_sealed())
+    }
+
+    /// Check whether the bin should be cleared after values have been added to it, and attempt to
+    /// clear it.
+    fn added(&self) {
+        #[cfg(feature = "memory-stats")]
+        if (self.rss_trigger.as_ref()).is_some_and(rss::RssTrigger::should_clear) {
+            self.clear.store(true, atomic::Ordering::Relaxed);
+        }
+        if (self.pacer.as_ref()).is_some_and(Pacer::should_clear) {
+            self.clear.store(true, atomic::Ordering::Relaxed);
+        }
+        if (self.debounce.as_ref()).is_some_and(Debounce::due) {
+            self.clear.store(true, atomic::Ordering::Relaxed);
+        }
+        if let Some(growth) = &self.growth {
+            growth.added(|| (self.epoch(), self.bytes_used()));
+        }
+        if let Some(incremental) = &self.incremental {
+            if incremental.should_step() {
+                self.retire_some(incremental);
+            }
+        }
+
+        self.run_pending_clear();
+    }
+
+    /// Drop a budget of values for incremental clearing, first taking the bin's values out if
+    /// there are none left to drop.
+    ///
+    /// This gives up instead of waiting for other threads, since it runs as part of an add.
+    fn retire_some(&self, incremental: &Incremental<'a>) {
+        if self.is_poisoned() {
+            return;
+        }
+        let Some(mut retiring) = incremental.try_lock() else {
+            return;
+        };
+        if retiring.is_none() {
+            let Some(mut inner) = self.arenas.try_write() else {
+                return;
+            };
+            if inner.is_empty() {
+                return;
+            }
+            *retiring = Some(inner.detach_ready(false));
+            drop(inner);
+            self.emptied();
+        }
+        let Some(detached) = &mut *retiring else {
+            return;
+        };
+
+        let mut report = ClearReport::default();
+        let poison = PoisonOnUnwind::new(&self.poisoned);
+        detached.clear_some(incremental.budget(), &mut report);
+        drop(poison);
+
+        // Hand the storage back to be reused. If the bin is in use, this is tried again next time.
+        if detached.is_empty() {
+            if let Some(mut inner) = self.arenas.try_write() {
+                inner.reattach(retiring.take().unwrap());
+            }
+        }
+        drop(retiring);
+
+        if report.values != 0 {
+            self.advance_epoch();
+            self.report(&report);
+        }
+    }
+
+    /// Take the values that incremental clearing is part way through dropping, unless another
+    /// thread is dropping them.
+    fn take_retiring(&self) -> Option<Detached<'a>> {
+        self.incremental.as_ref()?.try_lock()?.take()
+    }
+
+    /// Put the values that incremental clears, `end_frame` and sliced clears have taken out but
+    /// not dropped yet back in the bin, except those another thread is dropping.
+    fn reattach_remnants(&self, inner: &mut Inner<'a>) {
+        if let Some(detached) = self.take_retiring() {
+            inner.reattach(detached);
+        }
+        if let Some(detached) = self.take_carried() {
+            inner.reattach(detached);
+        }
+        if let Some((detached, _)) = self.slicer.as_ref().and_then(Slicer::take) {
+            inner.reattach(detached);
+        }
+    }
+
+    /// Take the values that `end_frame` has carried forward, unless another thread is dropping
+    /// them.
+    fn take_carried(&self) -> Option<Detached<'a>> {
+        self.frames.try_lock()?.carried.take()
+    }
+
+    /// Attempt to the clear the bin.
+    ///
+    /// This retires the active arena so that adds move on to the other one, then clears the
+    /// retired arena once adds that were already using it have finished.
+    fn run_pending_clear(&self) {
+        // A clear that is waiting for adds to leave the retired arena is finished first, and then
+        // one that has been asked for since.
+        for _ in 0..2 {
+            if !self.clear.load(atomic::Ordering::Relaxed) && !self.arenas.is_retired() {
+                return;
+            }
+            if self.is_poisoned() {
+                return;
+            }
+            let Some(inner) = self.arenas.lock_retired(&self.clear) else {
+                return;
+            };
+            self.clear_retired(inner);
+        }
+    }
+
+    /// Take the values out of the retired arena and drop them, returning how many there were.
+    ///
+    /// The arena is only locked while the values are taken out, so that the next clear can
+    /// retire the other arena while their destructors run.
+    fn clear_retired(&self, mut inner: WriteGuard<'_, Inner<'a>>) -> usize {
+        if let Some(detached) = self.take_retiring() {
+            inner.reattach(detached);
+        }
+        if let Some(detached) = self.take_carried() {
+            inner.reattach(detached);
+        }
+        // A clear split into slices finishes the values it took out before taking out more, which
+        // are left in the arena until then.
+        let (detached, compact) = if let Some(rest) = self.slicer.as_ref().and_then(Slicer::take) {
+            drop(inner);
+            rest
+        } else {
+            let compact = self.compact.swap(false, atomic::Ordering::Relaxed);
+            let detached = inner.detach_ready(compact);
+            // Adds carry on in the active arena, so that is where the storage is needed.
+            if let Some(active) = self.arenas.read() {
+                active.take_free(&mut inner, compact);
+            }
+            drop(inner);
+            self.emptied();
+
+            if let Some((threshold, offload)) = self.offload {
+                let values = detached.len();
+                if values > threshold {
+                    // The clear counts as finished once the values have been handed off.
+                    let report = ClearReport {
+                        values,
+                        bytes: detached.bytes_used(),
+                        ..ClearReport::default()
+                    };
+                    self.finished(&report);
+                    offload(detached);
+                    return values;
+                }
+            }
+            (detached, compact)
+        };
+
+        let bytes = detached.bytes_used();
+        let slice = self.slicer.as_ref().map_or(usize::MAX, Slicer::slice_len);
+        let mut remainder = Remainder::new(self, detached);
+        remainder.clear_some(slice);
+        let (mut detached, mut report) = remainder.finish();
+        report.bytes = bytes;
+
+        if let Some(slicer) = &self.slicer {
+            slicer.sliced(report.values, report.duration);
+            if let Some(rest) = detached.take_if(|detached| !detached.is_empty()) {
+                // The storage is only counted once the last slice has emptied it.
+                report.bytes = 0;
+                if let Some(rest) = slicer.put(rest, compact) {
+                    self.with_inner(|inner| inner.reattach(rest));
+                }
+            }
+        }
+
+        // Hand the storage to the active arena to be reused. Compacting has already given it the
+        // only storage it keeps.
+        if detached.is_some() {
+            if let Some(active) = self.arenas.read() {
+                if let Some(detached) = detached.take() {
+                    active.recycle(detached, !compact);
+                }
+                if let Some(shrinker) = &self.shrinker {
+                    if let Some(target) =
+                        shrinker.cleared(report.bytes, active.size() + active.free_size())
+                    {
+                        active.shrink_to(target);
+                    }
+                }
+            }
+        }
+        if let Some(mut detached) = detached {
+            let panics = detached.take_panics();
+            drop(detached);
+            if !panics.is_empty() {
+                self.with_inner(|inner| inner.add_panics(panics));
+            }
+        }
+
+        self.finished(&report);
+
+        if let Some((threshold, trim)) = self.trim_after_clear {
+            if report.bytes > threshold {
+                trim();
+            }
+        }
+
+        report.values
+    }
+
+    /// Record that a clear has finished, waking its waiters and reporting it.
+    fn finished(&self, report: &ClearReport) {
+        if let Some(pacer) = &self.pacer {
+            pacer.cleared(report.duration);
+        }
+        self.advance_epoch();
+        self.report(report);
+    }
+
+    /// Pass the report of a completed clear to the hook, if there is one.
+    fn report(&self, report: &ClearReport) {
+        #[cfg(feature = "drop-times")]
+        report.samples.learn();
+
+        let mut history = self.history.lock().unwrap_or_else(PoisonError::into_inner);
+        if history.len() == CLEAR_HISTORY {
+            history.pop_front();
+        }
+        let pause = ClearPause {
+            duration: report.duration,
+            items: report.values,
+        };
+        history.push_back((Instant::now(), pause));
+        drop(history);
+
+        if let Some(on_clear) = self.on_clear {
+            on_clear(report);
+        }
+
+        let mut subscribers = self
+            .subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        // Forget subscribers whose receivers have been dropped.
+        subscribers.retain(|subscriber| subscriber.send(report.clone()).is_ok());
+    }
+
+    /// Subscribe to the reports of the bin's clears.
+    ///
+    /// The report of every clear that completes after this call is sent to the returned channel,
+    /// so other threads can block until values have actually been dropped, or poll for clears
+    /// with [`try_recv`](mpsc::Receiver::try_recv). Reports are queued until they are received,
+    /// and the bin stops sending to the channel once the receiver is dropped.
+    pub fn subscribe_cleared(&self) -> mpsc::Receiver<ClearReport> {
+        let (sender, receiver) = mpsc::channel();
+        (self.subscribers.lock())
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(sender);
+        receiver
+    }
+
+    /// Get how long the last clear took, or `None` if the bin hasn't been cleared.
+    ///
+    /// Like [`ClearReport::duration`], this includes dropping the values.
+    #[must_use]
+    pub fn last_clear_duration(&self) -> Option<Duration> {
+        let history = self.history.lock().unwrap_or_else(PoisonError::into_inner);
+        Some(history.back()?.1.duration)
+    }
+
+    /// Get when the last clear finished, or `None` if the bin hasn't been cleared.
+    fn last_cleared(&self) -> Option<Instant> {
+        let history = self.history.lock().unwrap_or_else(PoisonError::into_inner);
+        Some(history.back()?.0)
+    }
+
+    /// Get how long the most recent clears took and how many values they dropped, oldest first.
+    ///
+    /// The bin remembers the last 64 clears, using the same notion of a clear as
+    /// [`epoch`](Self::epoch), so pauses can be monitored over time without timing every call
+    /// site.
+    #[must_use]
+    pub fn recent_clears(&self) -> Vec<ClearPause> {
+        let history = self.history.lock().unwrap_or_else(PoisonError::into_inner);
+        history.iter().map(|&(_, pause)| pause).collect()
+    }
+
+    /// Record that a clear has completed. This should be called after the bin is unlocked, since
+    /// woken tasks may want to use it.
+    fn advance_epoch(&self) {
+        self.epoch.fetch_add(1, atomic::Ordering::Release);
+        self.waiters.wake_all();
+    }
+
+    /// Wait until the bin is next cleared.
+    ///
+    /// The returned future resolves once a clear that completes after this call has finished,
+    /// using the same notion of a clear as [`epoch`](Self::epoch). This lets tasks that are
+    /// waiting for memory to be reclaimed sleep instead of polling [`size`](Self::size).
+    pub fn cleared(&self) -> Cleared<'_, 'a> {
+        Cleared::new(self)
+    }
+
+    /// Get the bin's current epoch, which counts the clears that have completed.
+    ///
+    /// Every clear that drops values advances the epoch once it has finished, including partial
+    /// clears like [`clear_tag`](Self::clear_tag). Together with
+    /// [`cleared_since`](Self::cleared_since), this lets readers check that nothing they saw has
+    /// been reclaimed since they took a snapshot.
+    #[must_use]
+    pub fn epoch(&self) -> u64 {
+        self.epoch.load(atomic::Ordering::Acquire)
+    }
+
+    /// Check whether a clear has completed since the bin was at the given epoch.
+    #[must_use]
+    pub fn cleared_since(&self, epoch: u64) -> bool {
+        self.epoch() != epoch
+    }
+
+    /// Take the payloads of the destructor panics collected by clears with
+    /// [`PanicPolicy::Collect`], oldest first.
+    ///
+    /// This waits for [adds in progress](Self::batch) to finish.
+    pub fn take_panics(&self) -> Vec<Box<dyn Any + Send>> {
+        self.with_inner(Inner::take_panics)
+    }
+
+    /// Get the number of values that have been dropped immediately because the bin was over its
+    /// limit, with [`Overflow::Spill`].
+    #[must_use]
+    pub fn spilled(&self) -> usize {
+        self.limit.as_ref().map_or(0, Limit::spilled)
+    }
+
+    /// Iterate over the values added with [`add_any`](Self::add_any) that are still in the bin,
+    /// oldest first.
+    ///
+    /// This is meant for tests and debugging tools, which can downcast the values to inspect them
+    /// before they are dropped.
+    pub fn iter_any(&mut self) -> impl Iterator<Item = &(dyn Any + Send)> {
+        self.arenas.get_mut().any_values().into_iter()
+    }
+
+    /// Take every value of type `T` back out of the bin without dropping it, oldest first.
+    ///
+    /// This lets values that were binned speculatively be reused after all. Only values added with
+    /// [`add_any`](Self::add_any) can be taken, since the types of other values aren't known. This
+    /// waits for [adds in progress](Self::batch) to finish.
+    pub fn take_all<T: Any + Send>(&self) -> Vec<T> {
+        self.with_inner(Inner::take_all)
+    }
+
+    /// Remove the value added with the given key from the bin without running its destructor,
+    /// returning whether it was still in the bin.
+    ///
+    /// This is for values whose ownership has been passed on some other way, such as through
+    /// FFI. The value's memory is reclaimed at the next clear. This takes constant time however
+    /// many values are in the bin, but waits for [adds in progress](Self::batch) to finish.
+    pub fn cancel(&self, key: Key) -> bool {
+        self.with_inner(|inner| inner.cancel(key))
+    }
+
+    /// Drop the value added with the given key straight away, leaving the rest of the bin alone.
+    ///
+    /// This returns whether the value was still in the bin. It is for values that occasionally
+    /// need to be released immediately, such as a file lock, and like [`cancel`](Self::cancel)
+    /// doesn't search the bin but waits for [adds in progress](Self::batch) to finish. Dropping the
+    /// value advances the [`epoch`](Self::epoch).
+    ///
+    /// This can drop more than the one value. If the value was added with
+    /// [`add_after`](Self::add_after) and the value it must be dropped after is still in the bin,
+    /// that value is taken out of the bin and dropped first, and so on along the chain, so their
+    /// keys no longer refer to anything either.
+    pub fn drop_now(&self, key: Key) -> bool {
+        self.check_poisoned();
+        let report = self.with_inner(|inner| {
+            let _poison = PoisonOnUnwind::new(&self.poisoned);
+            inner.drop_now(key)
+        });
+        if report.values == 0 {
+            return false;
+        }
+        self.advance_epoch();
+        self.report(&report);
+        true
+    }
+
+    /// Forget everything in the bin without running any destructors, returning the number of
+    /// values that were forgotten.
+    ///
+    /// This is for when running destructors would be wasted time, such as just before the process
+    /// exits or calls `exec`. The bin's storage is kept to be reused; to skip freeing it as well,
+    /// pass the bin to [`mem::forget`](std::mem::forget) afterward. This waits for
+    /// [adds in progress](Self::batch) to finish.
+    pub fn leak(&self) -> usize {
+        let leaked = self.with_inner(|inner| {
+            self.reattach_remnants(inner);
+            inner.leak()
+        });
+        self.emptied();
+        leaked
+    }
+
+    /// Set what happens to the values left in the bin when it is dropped.
+    ///
+    /// By default they are dropped like a [`clear`](Self::clear), which for a large bin can add
+    /// noticeably to the time a program takes to shut down. With [`DropBehavior::Leak`] they are
+    /// forgotten instead.
+    pub fn set_drop_behavior(&self, behavior: DropBehavior) {
+        let leak = behavior == DropBehavior::Leak;
+        self.leak_on_drop.store(leak, atomic::Ordering::Relaxed);
+    }
+
+    /// Forget what the bin's last user did with it, so that it can be handed to another as if it
+    /// were new. The bin must have been cleared, which has already dropped its values along with
+    /// their tags and keys.
+    pub(crate) fn reset(&mut self) {
+        self.epoch.store_mut(0);
+        self.leak_on_drop.store_mut(false);
+        (self.subscribers.lock())
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+        (self.history.lock())
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+        if let Some(debounce) = &mut self.debounce {
+            debounce.reset();
+        }
+    }
+
+    /// Consume the bin, moving its values into a [`Dropper`] that drops them when it is run or
+    /// dropped.
+    ///
+    /// Unlike [`clear_in_background`](Bin::clear_in_background), this leaves it up to the caller
+    /// where the values are dropped.
+    #[must_use]
+    pub fn into_dropper(mut self) -> Dropper<'a> {
+        Dropper::new(self.arenas.get_mut().detach())
+    }
+
+    /// Get the size of the bin in bytes.
+    ///
+    /// This counts the storage that holds values, but not the storage that was emptied by a clear
+    /// and is kept for reuse, which is counted by [`free_bytes`](Self::free_bytes) instead.
+    #[must_use]
+    pub fn size(&self) -> usize {
+        (self.arenas.iter())
+            .filter_map(|arena| Some(arena.try_read()?.size()))
+            .sum()
+    }
+
+    /// Get the number of bytes of storage that was emptied by a clear and is kept for reuse.
+    #[must_use]
+    pub fn free_bytes(&self) -> usize {
+        (self.arenas.iter())
+            .filter_map(|arena| Some(arena.try_read()?.free_size()))
+            .sum()
+    }
+
+    /// Get the number of bytes of storage the bin has allocated, including storage that was
+    /// emptied by a clear and is kept for reuse.
+    ///
+    /// Right after a clear this is as large as it was before, so to decide whether a bin has
+    /// built up enough garbage, use [`bytes_used`](Self::bytes_used) instead.
+    #[must_use]
+    pub fn capacity(&self) -> usize {
+        self.size() + self.free_bytes()
+    }
+
+    /// Get the number of bytes of storage that the values in the bin take up, including the
+    /// padding between them.
+    ///
+    /// This is tracked as values are added, so it is cheap to call often. Values dropped by partial
+    /// clears like [`clear_oldest`](Self::clear_oldest) stop being counted, although their storage
+    /// isn't reused until the bin is emptied. With the `boxed-storage` feature values have their
+    /// own allocations, so they are not counted.
+    #[must_use]
+    pub fn bytes_used(&self) -> usize {
+        (self.arenas.iter())
+            .filter_map(|arena| Some(arena.try_read()?.bytes_used()))
+            .sum()
+    }
+
+    /// Get a breakdown of the memory the bin is using.
+    ///
+    /// With the `boxed-storage` feature values have their own allocations, so they are not
+    /// counted.
+    #[must_use]
+    pub fn memory_usage(&self) -> MemoryUsage {
+        self.arenas
+            .stats()
+            .map_or_else(MemoryUsage::default, |stats| MemoryUsage {
+                values: stats.used - stats.padding,
+                padding: stats.padding,
+                unused: stats.capacity + stats.free - stats.used,
+                overhead: stats.entries,
+            })
+    }
+
+    /// Estimate what clearing the bin now would cost, so that clears can be scheduled into
+    /// moments when there is time for them.
+    ///
+    /// Like [`memory_usage`](Self::memory_usage), this may or may not count values that are being
+    /// added concurrently. With the `drop-times` feature, it looks through the values to estimate
+    /// how long they would take to drop, locking the bin while it does like a clear.
+    #[must_use]
+    pub fn estimate_clear_cost(&self) -> ClearCost {
+        self.arenas
+            .stats()
+            .map_or_else(ClearCost::default, |stats| ClearCost {
+                items: stats.values,
+                bytes: stats.used,
+                #[cfg(feature = "drop-times")]
+                duration: self.expected_drop_time(),
+            })
+    }
+
+    /// Estimate how long the values in the bin would take to drop, from the learned drop times of
+    /// their types.
+    #[cfg(feature = "drop-times")]
+    fn expected_drop_time(&self) -> Option<Duration> {
+        let destructors: Vec<usize> = (self.arenas.iter())
+            .filter_map(|arena| Some(arena.try_write()?.destructors()))
+            .flatten()
+            .collect();
+        DropTimes::learned().total(destructors)
+    }
+
+    /// Iterate over the type names of the values in the bin, oldest first. Enabled by the
+    /// `debug-types` feature.
+    ///
+    /// Values added with [`add_raw`](Self::add_raw) are listed as `*mut ()`. This waits for
+    /// [adds in progress](Self::batch) to finish.
+    #[cfg(feature = "debug-types")]
+    pub fn iter_types(&self) -> impl Iterator<Item = &'static str> {
+        self.with_inner(Inner::type_names).into_iter()
+    }
+
+    /// Count the values in the bin of each type, most common first. Enabled by the `debug-types`
+    /// feature.
+    ///
+    /// This is a summary of [`iter_types`](Self::iter_types), for finding out what a
+    /// surprisingly large bin is holding.
+    #[cfg(feature = "debug-types")]
+    #[must_use]
+    pub fn pending_types(&self) -> Vec<(&'static str, usize)> {
+        let mut counts = HashMap::<_, usize>::new();
+        for type_name in self.iter_types() {
+            *counts.entry(type_name).or_default() += 1;
+        }
+        let mut counts: Vec<_> = counts.into_iter().collect();
+        counts.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        counts
+    }
+
+    /// Iterate over where the values in the bin were added from, oldest first. Enabled by the
+    /// `debug-types` feature.
+    ///
+    /// Each location is the code that called the add method, such as [`add`](Self::add) or
+    /// [`Group::add`]. Like [`iter_types`](Self::iter_types), this waits for
+    /// [adds in progress](Self::batch) to finish.
+    #[cfg(feature = "debug-types")]
+    pub fn iter_callers(&self) -> impl Iterator<Item = &'static Location<'static>> {
+        self.with_inner(Inner::callers).into_iter()
+    }
+
+    /// Count the values in the bin added from each location, most common first. Enabled by the
+    /// `debug-types` feature.
+    ///
+    /// This is a summary of [`iter_callers`](Self::iter_callers), for finding out which code
+    /// paths are feeding a surprisingly large bin.
+    #[cfg(feature = "debug-types")]
+    #[must_use]
+    pub fn pending_callers(&self) -> Vec<(&'static Location<'static>, usize)> {
+        let mut counts = HashMap::<_, usize>::new();
+        for caller in self.iter_callers() {
+            *counts.entry(caller).or_default() += 1;
+        }
+        let mut counts: Vec<_> = counts.into_iter().collect();
+        counts.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        counts
+    }
+
+    /// Run a function with exclusive access to the bin, waiting for adds in progress to finish.
+    fn with_inner<R>(&self, f: impl FnOnce(&mut Inner<'a>) -> R) -> R {
+        f(&mut self.write())
+    }
+
+    /// Get exclusive access to the bin, waiting for adds in progress to finish.
+    fn write(&self) -> WriteGuard<'_, Inner<'a>> {
+        loop {
+            if let Some(inner) = self.arenas.try_write() {
+                return inner;
+            }
+            sync::yield_now();
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Bin<'static> {
+    /// Take everything out of the bin and drop it on a new thread.
+    ///
+    /// This gets expensive destructors off the current thread without setting up a persistent
+    /// cleaner, and the bin can be used again straight away once
+    /// [adds in progress](Self::batch) have finished. The clear counts as finished, advancing the
+    /// [`epoch`](Self::epoch), once the values have been handed to the thread.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the thread could not be spawned, in which case the values are dropped on the
+    /// current thread instead.
+    pub fn clear_in_background(&self) -> io::Result<JoinHandle<()>> {
+        self.check_poisoned();
+        let detached = self.with_inner(|inner| {
+            self.reattach_remnants(inner);
+            inner.detach_ready(false)
+        });
+        self.emptied();
+        let report = ClearReport {
+            values: detached.len(),
+            bytes: detached.bytes_used(),
+            ..ClearReport::default()
+        };
+        let handle = thread::Builder::new()
+            .name("drop-bin-clear".to_owned())
+            .spawn(move || drop(detached));
+        self.finished(&report);
+        handle
+    }
+}
+
+/// Drop values taken out of a bin on a new thread, or on this one if it can't be spawned.
+#[cfg(feature = "std")]
+fn drop_on_new_thread(detached: Detached<'static>) {
+    let spawned = thread::Builder::new()
+        .name("drop-bin-clear".to_owned())
+        .spawn(move || drop(detached));
+    drop(spawned);
+}
+
+/// Shared access to a bin for adding a value to it, which checks whether the bin should be cleared
+/// once it is dropped.
+///
+/// Adds call into the arena directly rather than through a closure, so that with `debug-types`
+/// the value's entry records where the add was called from.
+#[cfg(feature = "std")]
+pub(crate) struct Adding<'b, 'a> {
+    /// The bin, unless it was full.
+    bin: Option<&'b Bin<'a>>,
+    /// The active arena, unless the bin was full or the whole bin was locked.
+    inner: Option<ReadGuard<'b, Inner<'a>>>,
+}
+
+#[cfg(feature = "std")]
+impl<'a> Adding<'_, 'a> {
+    /// Get the arena to add the value to, if there is one.
+    pub(crate) fn inner(&self) -> Option<&Inner<'a>> {
+        self.inner.as_deref()
+    }
+}
+
+#[cfg(feature = "std")]
+impl Drop for Adding<'_, '_> {
+    fn drop(&mut self) {
+        // Unlock the arena first, so that the bin can be cleared.
+        self.inner = None;
+        if let Some(bin) = self.bin {
+            bin.added();
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl Debug for Bin<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut s = f.debug_struct("Bin");
+        if let Some(stats) = self.arenas.stats() {
+            #[allow(clippy::cast_precision_loss)]
+            let utilization = if stats.capacity == 0 {
+                0.0
+            } else {
+                stats.used as f64 / stats.capacity as f64 * 100.0
+            };
+            s.field("values", &stats.values)
+                .field("size", &stats.capacity)
+                .field("free", &stats.free)
+                .field("segments", &stats.segments)
+                .field("utilization", &format_args!("{utilization:.0}%"));
+        } else {
+            s.field("clearing", &true);
+        }
+        s.field("clear_pending", &self.clear.load(atomic::Ordering::Relaxed))
+            .field("epoch", &self.epoch())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a> Drop for Bin<'a> {
+    fn drop(&mut self) {
+        if let Some(detached) = (self.incremental.as_mut()).and_then(|i| i.get_mut().take()) {
+            self.arenas.get_mut().reattach(detached);
+        }
+        if let Some((detached, _)) = (self.slicer.as_mut()).and_then(|s| s.get_mut().take()) {
+            self.arenas.get_mut().reattach(detached);
+        }
+        if let Some(detached) = self.frames.get_mut().carried.take() {
+            self.arenas.get_mut().reattach(detached);
+        }
+        if self.poisoned.load_mut() || self.leak_on_drop.load_mut() {
+            self.arenas.get_mut().leak();
+        } else {
+            self.arenas.get_mut().clear();
+        }
+    }
+}
+
+#[cfg(all(test, not(loom), feature = "std"))]
+mod tests {
+    use crate::test_util::assert_thread_safe;
+    use crate::test_util::CallOnDrop;
+    use crate::Bin;
+    use crate::ClearAttempt;
+    use crate::DropBehavior;
+    use crate::OwnedBin;
+    #[cfg(feature = "debug-types")]
+    use crate::Tag;
+    #[cfg(feature = "debug-types")]
+    use crate::ValueMeta;
+    use std::marker::PhantomPinned;
+    #[cfg(not(feature = "boxed-storage"))]
+    use std::mem::size_of;
+    use std::ptr;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::sync::mpsc;
+    use std::sync::Arc;
+    use std::sync::Barrier;
+    use std::sync::Mutex;
+    use std::thread;
+    use std::thread::ThreadId;
+    use std::time::Duration;
+
+    #[test]
+    fn clear() {
+        let destructor_called = AtomicBool::new(false);
+
+        let bin = Bin::new();
+
+        bin.add(CallOnDrop(
+            || assert!(!destructor_called.swap(true, SeqCst)),
+        ));
+        assert!(!destructor_called.load(SeqCst));
+
+        bin.clear();
+        assert!(destructor_called.load(SeqCst));
+    }
+
+    #[test]
+    fn add_raw() {
+        static DESTRUCTOR_CALLED: AtomicBool = AtomicBool::new(false);
+
+        unsafe fn destructor(ptr: *mut ()) {
+            assert_eq!(*unsafe { Box::from_raw(ptr.cast::<u32>()) }, 5);
+            assert!(!DESTRUCTOR_CALLED.swap(true, SeqCst));
+        }
+
+        let bin = Bin::new();
+
+        unsafe { bin.add_raw(Box::into_raw(Box::new(5_u32)).cast::<()>(), destructor) };
+        assert!(!DESTRUCTOR_CALLED.load(SeqCst));
+
+        bin.clear();
+        assert!(DESTRUCTOR_CALLED.load(SeqCst));
+    }
+
+    #[test]
+    fn subscribe_cleared() {
+        let bin = Bin::new();
+        let receiver = bin.subscribe_cleared();
+
+        thread::scope(|s| {
+            s.spawn(|| {
+                bin.add(5);
+                bin.clear();
+            });
+            assert_eq!(receiver.recv().unwrap().values, 1);
+        });
+        assert!(receiver.try_recv().is_err());
+
+        drop(receiver);
+        bin.add(5);
+        bin.clear();
+        assert!(bin.subscribers.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn bytes_used() {
+        let bin = Bin::new();
+        bin.add(5_u64);
+        bin.add([1_u32; 4]);
+        #[cfg(not(feature = "boxed-storage"))]
+        assert_eq!(bin.bytes_used(), 24);
+        assert!(bin.bytes_used() <= bin.capacity());
+
+        // The storage is kept, but no longer counts as used.
+        let capacity = bin.capacity();
+        bin.clear();
+        assert_eq!(bin.bytes_used(), 0);
+        assert_eq!(bin.capacity(), capacity);
+    }
+
+    #[test]
+    fn add_box() {
+        let dropped = AtomicBool::new(false);
+
+        let bin = Bin::new();
+        let value: Box<dyn Send + '_> = Box::new(CallOnDrop(|| dropped.store(true, SeqCst)));
+        bin.add_box(value);
+        bin.add_box(Box::<[u8]>::from([1, 2, 3]));
+        // Both boxes are stored as just their fat pointers.
+        #[cfg(not(feature = "boxed-storage"))]
+        assert_eq!(bin.memory_usage().values, 2 * size_of::<Box<[u8]>>());
+        assert!(!dropped.load(SeqCst));
+
+        bin.clear();
+        assert!(dropped.load(SeqCst));
+    }
+
+    #[test]
+    fn add_vec() {
+        let dropped = AtomicUsize::new(0);
+
+        let bin = Bin::new();
+        let values = (0..10).map(|_| {
+            CallOnDrop(|| {
+                dropped.fetch_add(1, SeqCst);
+            })
+        });
+        bin.add_vec(values.collect());
+        bin.add_slice(Box::<[String]>::default());
+        // The elements aren't moved into the bin's storage.
+        assert_eq!(bin.size(), 0);
+        assert_eq!(dropped.load(SeqCst), 0);
+
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 10);
+    }
+
+    #[test]
+    fn clear_compact() {
+        let destructor_called = AtomicBool::new(false);
+
+        let bin = Bin::new();
+
+        bin.add(CallOnDrop(
+            || assert!(!destructor_called.swap(true, SeqCst)),
+        ));
+        for _ in 0..100 {
+            bin.add([0_u8; 100]);
+        }
+
+        bin.clear_compact();
+        assert!(destructor_called.load(SeqCst));
+        assert_eq!(bin.size(), 0);
+        assert!(bin.free_bytes() <= 100 * 100 + 64);
+    }
+
+    #[test]
+    fn add_iter() {
+        let destructors_called = AtomicUsize::new(0);
+
+        let bin = Bin::new();
+
+        bin.add_iter((0..100).map(|_| {
+            CallOnDrop(|| {
+                destructors_called.fetch_add(1, SeqCst);
+            })
+        }));
+        assert_eq!(destructors_called.load(SeqCst), 0);
+
+        bin.clear();
+        assert_eq!(destructors_called.load(SeqCst), 100);
+    }
+
+    #[test]
+    #[cfg(not(feature = "boxed-storage"))]
+    fn trim_after_clear() {
+        static TRIMS: AtomicUsize = AtomicUsize::new(0);
+
+        let bin = Bin::builder()
+            .trim_after_clear(500, || {
+                TRIMS.fetch_add(1, SeqCst);
+            })
+            .build();
+
+        bin.add([0_u8; 100]);
+        bin.clear();
+        assert_eq!(TRIMS.load(SeqCst), 0);
+
+        bin.add([0_u8; 1000]);
+        bin.clear();
+        assert_eq!(TRIMS.load(SeqCst), 1);
+    }
+
+    #[test]
+    fn add_while_clearing() {
+        static BIN: Bin<'static> = Bin::new();
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        BIN.add(CallOnDrop(|| {
+            // The bin isn't locked while destructors run, so this is kept for the next clear.
+            BIN.add(CallOnDrop(|| {
+                DROPPED.fetch_add(1, SeqCst);
+            }));
+        }));
+        BIN.clear();
+        assert_eq!(DROPPED.load(SeqCst), 0);
+        BIN.clear();
+        assert_eq!(DROPPED.load(SeqCst), 1);
+    }
+
+    #[test]
+    fn clear_in_background() {
+        static BIN: Bin<'static> = Bin::new();
+        static DROPPED_ON: Mutex<Option<ThreadId>> = Mutex::new(None);
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        BIN.add(CallOnDrop(|| {
+            *DROPPED_ON.lock().unwrap() = Some(thread::current().id());
+        }));
+        let epoch = BIN.epoch();
+        let handle = BIN.clear_in_background().unwrap();
+        assert!(BIN.cleared_since(epoch));
+        let background = handle.thread().id();
+        handle.join().unwrap();
+
+        assert_eq!(*DROPPED_ON.lock().unwrap(), Some(background));
+        assert_eq!(BIN.size(), 0);
+
+        // Values that `end_frame` carried forward are dropped by the thread as well.
+        let bin = Bin::new();
+        for _ in 0..1000 {
+            bin.add(CallOnDrop(|| _ = DROPPED.fetch_add(1, SeqCst)));
+        }
+        bin.end_frame(Duration::ZERO);
+        bin.clear_in_background().unwrap().join().unwrap();
+        assert_eq!(DROPPED.load(SeqCst), 1000);
+    }
+
+    #[test]
+    #[cfg(feature = "debug-types")]
+    fn pending_types() {
+        let bin = Bin::new();
+        bin.add(5_u32);
+        bin.add(String::new());
+        bin.add(6_u32);
+
+        assert_eq!(
+            bin.iter_types().collect::<Vec<_>>(),
+            ["u32", "alloc::string::String", "u32"]
+        );
+        assert_eq!(
+            bin.pending_types(),
+            [("u32", 2), ("alloc::string::String", 1)]
+        );
+
+        bin.clear();
+        assert!(bin.pending_types().is_empty());
+    }
+
+    #[test]
+    #[cfg(feature = "debug-types")]
+    fn pending_callers() {
+        let bin = Bin::new();
+        let line = line!();
+        for _ in 0..2 {
+            bin.add(5_u32);
+        }
+        bin.group().add(6_u32);
+        bin.add_iter([7_u32]);
+
+        assert!(bin.iter_callers().all(|caller| caller.file() == file!()));
+        let lines: Vec<_> = bin
+            .iter_callers()
+            .map(|caller| caller.line() - line)
+            .collect();
+        assert_eq!(lines, [2, 2, 4, 5]);
+        let pending: Vec<_> = (bin.pending_callers().into_iter())
+            .map(|(caller, n)| (caller.line() - line, n))
+            .collect();
+        assert_eq!(pending, [(2, 2), (4, 1), (5, 1)]);
+    }
+
+    #[test]
+    #[cfg(feature = "debug-types")]
+    fn clear_where() {
+        let bin = Bin::new();
+        bin.add(5_u32);
+        bin.add_tagged(6_u32, Tag::new("a"));
+        bin.add([0_u8; 64]);
+
+        assert_eq!(bin.clear_where(|meta| meta.type_name == "[u8; 64]"), 1);
+        assert_eq!(bin.clear_where(|meta| meta.tag.is_some()), 1);
+        let old = |meta: &ValueMeta| meta.age > Duration::from_secs(10);
+        assert_eq!(bin.clear_where(old), 0);
+        assert_eq!(bin.iter_types().collect::<Vec<_>>(), ["u32"]);
+    }
+
+    #[test]
+    #[cfg(feature = "zeroize")]
+    fn add_zeroizing() {
+        struct Secret<'a>(&'a Mutex<Vec<&'static str>>);
+        impl zeroize::Zeroize for Secret<'_> {
+            fn zeroize(&mut self) {
+                self.0.lock().unwrap().push("zeroize");
+            }
+        }
+        impl Drop for Secret<'_> {
+            fn drop(&mut self) {
+                self.0.lock().unwrap().push("drop");
+            }
+        }
+
+        let events = Mutex::new(Vec::new());
+        let bin = Bin::new();
+        bin.add_zeroizing(Secret(&events));
+        assert!(events.lock().unwrap().is_empty());
+
+        bin.clear();
+        assert_eq!(*events.lock().unwrap(), ["zeroize", "drop"]);
+    }
+
+    #[test]
+    #[cfg(not(feature = "boxed-storage"))]
+    fn debug() {
+        let bin = Bin::new();
+        assert_eq!(
+            format!("{bin:?}"),
+            "Bin { values: 0, size: 0, free: 0, segments: 0, utilization: 0%, \
+             clear_pending: false, epoch: 0, .. }"
+        );
+
+        bin.add([0_u8; 256]);
+        assert_eq!(
+            format!("{bin:?}"),
+            "Bin { values: 1, size: 1024, free: 0, segments: 1, utilization: 25%, \
+             clear_pending: false, epoch: 0, .. }"
+        );
+    }
+
+    #[test]
+    fn recent_clears() {
+        let bin = Bin::new();
+        assert_eq!(bin.last_clear_duration(), None);
+
+        for i in 0..100 {
+            for _ in 0..=i % 3 {
+                bin.add(i);
+            }
+            bin.clear();
+        }
+        let recent = bin.recent_clears();
+        assert_eq!(recent.len(), 64);
+        assert_eq!(bin.last_clear_duration(), Some(recent[63].duration));
+        let items: Vec<_> = recent.iter().map(|pause| pause.items).collect();
+        let expected: Vec<_> = (36..100).map(|i| i % 3 + 1).collect();
+        assert_eq!(items, expected);
+    }
+
+    #[test]
+    fn try_clear() {
+        let dropped = AtomicUsize::new(0);
+        let bin = Bin::new();
+        assert_eq!(bin.try_clear(), ClearAttempt::Empty);
+
+        bin.add(CallOnDrop(|| {
+            dropped.fetch_add(1, SeqCst);
+        }));
+        let guard = bin.batch();
+        assert_eq!(bin.try_clear(), ClearAttempt::Busy { readers: 1 });
+        // Nothing was left pending.
+        drop(guard);
+        assert_eq!(dropped.load(SeqCst), 0);
+
+        assert_eq!(bin.try_clear(), ClearAttempt::Cleared { items: 1 });
+        assert_eq!(dropped.load(SeqCst), 1);
+        assert_eq!(bin.epoch(), 1);
+        assert_eq!(bin.try_clear(), ClearAttempt::Empty);
+    }
+
+    #[test]
+    fn clear_blocking_with_timeout() {
+        let bin = Bin::new();
+        bin.add(5_u32);
+
+        let barrier = Barrier::new(2);
+        thread::scope(|s| {
+            s.spawn(|| {
+                let guard = bin.batch();
+                barrier.wait();
+                thread::sleep(Duration::from_millis(100));
+                drop(guard);
+            });
+            barrier.wait();
+            let attempt = bin.clear_blocking_with_timeout(Duration::from_millis(10));
+            assert_eq!(attempt, ClearAttempt::Busy { readers: 1 });
+            let attempt = bin.clear_blocking_with_timeout(Duration::from_secs(10));
+            assert_eq!(attempt, ClearAttempt::Cleared { items: 1 });
+        });
+    }
+
+    #[test]
+    fn adds_continue_during_pending_clear() {
+        let dropped = AtomicUsize::new(0);
+        let bin = Bin::new();
+
+        let guard = bin.batch();
+        // The guard keeps the clear out, so it is left pending, but adds go to the other arena.
+        bin.clear();
+        bin.add(CallOnDrop(|| {
+            dropped.fetch_add(1, SeqCst);
+        }));
+        assert_eq!(dropped.load(SeqCst), 0);
+
+        // The value was added after the clear, so it outlives it.
+        drop(guard);
+        assert_eq!(bin.epoch(), 1);
+        assert_eq!(dropped.load(SeqCst), 0);
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 1);
+    }
+
+    #[test]
+    fn iter_any() {
+        let mut bin = Bin::new();
+        bin.add_any(5_u32);
+        bin.add(6_u32);
+        bin.add_any("Hello".to_owned());
+
+        let values: Vec<_> = bin.iter_any().collect();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0].downcast_ref::<u32>(), Some(&5));
+        assert_eq!(values[1].downcast_ref::<String>().unwrap(), "Hello");
+
+        bin.clear();
+        assert_eq!(bin.iter_any().count(), 0);
+    }
+
+    #[test]
+    fn take_all() {
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+        let bin = Bin::new();
+        bin.add_any(vec![1, 2, 3]);
+        bin.add_any(5_u32);
+        bin.add(vec![4]);
+        bin.add_any(vec![6]);
+        bin.add_any(CallOnDrop(|| {
+            DROPPED.fetch_add(1, SeqCst);
+        }));
+
+        assert_eq!(bin.take_all::<Vec<i32>>(), [vec![1, 2, 3], vec![6]]);
+        assert_eq!(bin.take_all::<Vec<i32>>(), Vec::<Vec<i32>>::new());
+        assert_eq!(bin.take_all::<u32>(), [5]);
+
+        bin.clear();
+        assert_eq!(DROPPED.load(SeqCst), 1);
+    }
+
+    #[test]
+    fn cancel() {
+        let dropped = AtomicUsize::new(0);
+        let bin = Bin::new();
+        bin.add_keyed(CallOnDrop(|| {
+            dropped.fetch_add(1, SeqCst);
+        }));
+        let key = bin.add_keyed(CallOnDrop(|| {
+            dropped.fetch_add(10, SeqCst);
+        }));
+
+        assert!(bin.cancel(key));
+        assert!(!bin.cancel(key));
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 1);
+
+        let key = bin.add_keyed(vec![1, 2, 3]);
+        assert!(bin.cancel(key));
+    }
+
+    #[test]
+    fn drop_now() {
+        let dropped = AtomicUsize::new(0);
+        let bin = Bin::new();
+        let key = bin.add_keyed(CallOnDrop(|| {
+            dropped.fetch_add(1, SeqCst);
+        }));
+        bin.add(CallOnDrop(|| {
+            dropped.fetch_add(10, SeqCst);
+        }));
+
+        let epoch = bin.epoch();
+        assert!(bin.drop_now(key));
+        assert_eq!(dropped.load(SeqCst), 1);
+        assert!(bin.cleared_since(epoch));
+
+        assert!(!bin.drop_now(key));
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 11);
+    }
+
+    #[test]
+    fn add_after() {
+        let order = Mutex::new(Vec::new());
+        let push = |n| {
+            let order = &order;
+            CallOnDrop(move || order.lock().unwrap().push(n))
+        };
+        let bin = Bin::new();
+
+        // Clears drop newest first, except where that would break the order.
+        let pool = bin.add_keyed(push(1));
+        bin.add_after(pool, push(2));
+        bin.add(push(3));
+        bin.clear();
+        assert_eq!(*order.lock().unwrap(), [3, 1, 2]);
+        order.lock().unwrap().clear();
+
+        // Partial clears keep values that must wait.
+        let pool = bin.add_keyed(push(1));
+        let buffer = bin.add_after(pool, push(2));
+        bin.add_after(buffer, push(3));
+        assert_eq!(bin.clear_newest(2), 0);
+        assert_eq!(bin.clear_oldest(1), 1);
+        assert_eq!(*order.lock().unwrap(), [1]);
+        bin.clear();
+        assert_eq!(*order.lock().unwrap(), [1, 2, 3]);
+        order.lock().unwrap().clear();
+
+        // Dropping a value straight away drops what it must be dropped after first.
+        let pool = bin.add_keyed(push(1));
+        let buffer = bin.add_after(pool, push(2));
+        assert!(bin.drop_now(buffer));
+        assert_eq!(*order.lock().unwrap(), [1, 2]);
+        assert!(!bin.drop_now(pool));
+    }
+
+    #[test]
+    fn add_shared() {
+        let dropped = AtomicUsize::new(0);
+        let bin = Bin::new();
+
+        let value = Arc::new(CallOnDrop(|| _ = dropped.fetch_add(1, SeqCst)));
+        bin.add_shared(value.clone());
+        assert_eq!(bin.estimate_clear_cost().items, 0);
+        bin.add_shared(value);
+        assert_eq!(bin.estimate_clear_cost().items, 1);
+        assert_eq!(dropped.load(SeqCst), 0);
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 1);
+    }
+
+    #[test]
+    fn seal() {
+        let dropped = AtomicUsize::new(0);
+        let bin = Bin::new();
+
+        bin.add(CallOnDrop(|| _ = dropped.fetch_add(1, SeqCst)));
+        bin.seal();
+        assert!(bin.is_sealed());
+        bin.add(CallOnDrop(|| _ = dropped.fetch_add(1, SeqCst)));
+        assert_eq!(dropped.load(SeqCst), 1);
+        assert_eq!(bin.try_add(5_u8), Err(5));
+        assert_eq!(bin.estimate_clear_cost().items, 1);
+
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 2);
+    }
+
+    // Values aren't counted towards the bytes used with `boxed-storage`.
+    #[test]
+    #[cfg(not(feature = "boxed-storage"))]
+    fn clear_if_above() {
+        let bin = Bin::new();
+        for _ in 0..4 {
+            bin.add([0_u8; 16]);
+        }
+        assert_eq!(bin.clear_if_above(64, Some(32)), 0);
+
+        // The oldest values are dropped until the bin is down to the low watermark.
+        bin.add([0_u8; 16]);
+        assert_eq!(bin.clear_if_above(64, Some(32)), 3);
+        assert_eq!(bin.bytes_used(), 32);
+        bin.add([0_u8; 16]);
+        assert_eq!(bin.clear_if_above(64, Some(32)), 0);
+
+        bin.add_iter([[0_u8; 16]; 2]);
+        assert_eq!(bin.clear_if_above(64, None), 5);
+        assert_eq!(bin.bytes_used(), 0);
+
+        // Values that aren't ready are kept.
+        bin.add_when(0_u8, || false).unwrap();
+        bin.add(0_u8);
+        assert_eq!(bin.clear_if_above(0, None), 1);
+        assert_eq!(bin.estimate_clear_cost().items, 1);
+    }
+
+    #[test]
+    fn drop_behavior() {
+        let dropped = AtomicUsize::new(0);
+        let bin = Bin::new();
+        bin.add(CallOnDrop(|| _ = dropped.fetch_add(1, SeqCst)));
+        bin.set_drop_behavior(DropBehavior::Leak);
+        drop(bin);
+        assert_eq!(dropped.load(SeqCst), 0);
+    }
+
+    #[test]
+    fn emplace_pinned() {
+        struct SelfRef {
+            value: u32,
+            this: *const SelfRef,
+            _pinned: PhantomPinned,
+        }
+        // SAFETY: The pointer is only used to check where the value is.
+        unsafe impl Send for SelfRef {}
+        impl Drop for SelfRef {
+            fn drop(&mut self) {
+                assert_eq!(self.this, ptr::from_ref(self));
+            }
+        }
+
+        let bin = Bin::new();
+        let Ok(mut pinned) = bin.emplace_pinned(SelfRef {
+            value: 5,
+            this: ptr::null(),
+            _pinned: PhantomPinned,
+        }) else {
+            panic!("bin is not full or sealed");
+        };
+        // SAFETY: Nothing is moved out of the value.
+        let value = unsafe { pinned.as_mut().get_unchecked_mut() };
+        value.this = ptr::from_ref(value);
+        assert_eq!(pinned.value, 5);
+
+        bin.add(0_u8);
+        assert_eq!(bin.estimate_clear_cost().items, 2);
+        // The clear waits for the guard.
+        bin.clear();
+        assert_eq!(bin.estimate_clear_cost().items, 2);
+        assert_eq!(pinned.value, 5);
+        drop(pinned);
+        assert_eq!(bin.estimate_clear_cost().items, 0);
+
+        bin.seal();
+        assert_eq!(bin.emplace_pinned(5_u8).err(), Some(5));
+    }
+
+    #[test]
+    fn add_when() {
+        let ready = AtomicBool::new(false);
+        let dropped = AtomicUsize::new(0);
+        let bin = Bin::new();
+
+        let value = CallOnDrop(|| _ = dropped.fetch_add(1, SeqCst));
+        assert!(bin.add_when(value, || ready.load(SeqCst)).is_ok());
+        bin.add(CallOnDrop(|| _ = dropped.fetch_add(1, SeqCst)));
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 1);
+        assert_eq!(bin.clear_oldest(1), 0);
+
+        // The value is retried by later clears.
+        ready.store(true, SeqCst);
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 2);
+
+        bin.seal();
+        assert_eq!(bin.add_when(5_u8, || true), Err(5));
+    }
+
+    #[test]
+    fn build_offloading_above() {
+        let bin = Bin::builder().build_offloading_above(1);
+        let (sender, receiver) = mpsc::channel();
+        let add = || {
+            let sender = sender.clone();
+            bin.add(CallOnDrop(move || {
+                sender.send(thread::current().id()).unwrap();
+            }));
+        };
+
+        add();
+        bin.clear();
+        assert_eq!(receiver.try_recv(), Ok(thread::current().id()));
+
+        add();
+        add();
+        bin.clear();
+        for _ in 0..2 {
+            assert_ne!(receiver.recv(), Ok(thread::current().id()));
+        }
+        // The offloaded clear finished once the values were handed off.
+        assert_eq!(bin.epoch(), 2);
+        assert_eq!(bin.recent_clears()[1].items, 2);
+    }
+
+    #[test]
+    fn leak() {
+        let dropped = AtomicUsize::new(0);
+        let bin = Bin::new();
+        for _ in 0..3 {
+            bin.add(CallOnDrop(|| {
+                dropped.fetch_add(1, SeqCst);
+            }));
+        }
+
+        assert_eq!(bin.leak(), 3);
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 0);
+        assert_eq!(bin.leak(), 0);
+    }
+
+    #[test]
+    fn owned_bin() {
+        static BIN: OwnedBin = OwnedBin::new();
+        BIN.add("Hello World!".to_owned());
+        BIN.clear();
+    }
+
+    #[test]
+    fn epoch() {
+        let bin = Bin::new();
+        let epoch = bin.epoch();
+        assert!(!bin.cleared_since(epoch));
+
+        bin.add(1);
+        assert_eq!(bin.clear_oldest(0), 0);
+        assert!(!bin.cleared_since(epoch));
+
+        bin.clear();
+        assert!(bin.cleared_since(epoch));
+        assert_eq!(bin.epoch(), epoch + 1);
+    }
+
+    #[test]
+    #[allow(clippy::extra_unused_lifetimes)]
+    fn thread_safe<'a>() {
+        assert_thread_safe::<Bin<'a>>();
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod test_util {
+    #[cfg(feature = "std")]
+    pub(crate) fn assert_thread_safe<T: Send + Sync>() {}
+
+    pub(crate) struct CallOnDrop<T: FnMut()>(pub(crate) T);
+    impl<T: FnMut()> Drop for CallOnDrop<T> {
+        fn drop(&mut self) {
+            self.0();
+        }
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use crate::Bin;
+    use loom::sync::Arc;
+    use loom::thread;
+
+    #[test]
+    fn add_clear() {
+        loom::model(|| {
+            let bin = Arc::new(Bin::new());
+
+            let thread = thread::spawn({
+                let bin = bin.clone();
+                move || bin.add(Box::new(1))
+            });
+            bin.add(Box::new(2));
+            bin.clear();
+
+            thread.join().unwrap();
+        });
     }
 }
@@ -0,0 +1,292 @@
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+
+/// What happens to values added to a bin that is over its [limit](crate::Builder::limit).
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub enum Overflow {
+    /// Block the adding thread until a clear brings the bin back under its limit.
+    ///
+    /// This keeps fast producers from outrunning the thread that clears the bin. Something else
+    /// must be clearing the bin, such as a [`CleanerPool`](crate::CleanerPool), or the thread
+    /// blocks forever.
+    Block,
+    /// Drop the value immediately and call the function, so that the application can react by
+    /// shedding load or clearing the bin.
+    ///
+    /// [`Bin::try_add`](crate::Bin::try_add) gives the value back instead of dropping it.
+    Reject(fn()),
+    /// Drop the value immediately, count it in [`Bin::spilled`](crate::Bin::spilled) and call
+    /// the function, so that running destructors inline is measurable rather than silent.
+    Spill(fn()),
+}
+
+/// A cap on the number of bytes of values in a bin.
+#[derive(Debug)]
+pub(crate) struct Limit {
+    max: usize,
+    overflow: Overflow,
+    /// The number of bytes of values added since the bin was last emptied.
+    used: AtomicUsize,
+    /// The number of values that have been dropped immediately by `Overflow::Spill`.
+    spilled: AtomicUsize,
+    /// Held while checking `used` before waiting, so that a wakeup can't be missed.
+    lock: Mutex<()>,
+    /// Notified when values are dropped from the bin, making room for more.
+    emptied: Condvar,
+}
+
+impl Limit {
+    pub(crate) const fn new(max: usize, overflow: Overflow) -> Self {
+        Self {
+            max,
+            overflow,
+            used: AtomicUsize::new(0),
+            spilled: AtomicUsize::new(0),
+            lock: Mutex::new(()),
+            emptied: Condvar::new(),
+        }
+    }
+
+    /// Make room for a value of `bytes` bytes, or return `None` if it can't be added.
+    pub(crate) fn reserve(&self, bytes: usize) -> Option<Reservation<'_>> {
+        loop {
+            if let Some(reservation) = self.try_reserve(bytes) {
+                return Some(reservation);
+            }
+
+            match self.overflow {
+                Overflow::Block => self.wait(bytes),
+                Overflow::Reject(on_overflow) => {
+                    on_overflow();
+                    return None;
+                }
+                Overflow::Spill(on_spill) => {
+                    self.spilled.fetch_add(1, Ordering::Relaxed);
+                    on_spill();
+                    return None;
+                }
+            }
+        }
+    }
+
+    /// Make room for a value of `bytes` bytes if it fits, without applying the overflow policy.
+    pub(crate) fn try_reserve(&self, bytes: usize) -> Option<Reservation<'_>> {
+        let reserved = self
+            .used
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+                self.fits(used, bytes).then(|| used.saturating_add(bytes))
+            });
+        reserved.ok().map(|_| Reservation {
+            limit: Some(self),
+            bytes,
+        })
+    }
+
+    /// Give back room made for a value of `bytes` bytes that wasn't added after all.
+    fn unreserve(&self, bytes: usize) {
+        let _ = (self.used).fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+            Some(used.saturating_sub(bytes))
+        });
+    }
+
+    /// Call the overflow callback for a value that was given back, if there is one.
+    pub(crate) fn overflowed(&self) {
+        if let Overflow::Reject(on_overflow) = self.overflow {
+            on_overflow();
+        }
+    }
+
+    /// Count bytes of values that were added without checking the limit.
+    pub(crate) fn add(&self, bytes: usize) {
+        self.used.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Get the number of values that have been dropped immediately by `Overflow::Spill`.
+    pub(crate) fn spilled(&self) -> usize {
+        self.spilled.load(Ordering::Relaxed)
+    }
+
+    /// Record that the bin has been emptied, waking any blocked adds.
+    pub(crate) fn reset(&self) {
+        let guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+        self.used.store(0, Ordering::Relaxed);
+        drop(guard);
+        self.emptied.notify_all();
+    }
+
+    /// Record that `bytes` bytes of values have been dropped from a bin that still holds others,
+    /// waking any blocked adds.
+    pub(crate) fn release(&self, bytes: usize) {
+        let guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+        self.unreserve(bytes);
+        drop(guard);
+        self.emptied.notify_all();
+    }
+
+    /// Wait until a value of `bytes` bytes fits in the bin.
+    fn wait(&self, bytes: usize) {
+        let mut guard = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+        while !self.fits(self.used.load(Ordering::Relaxed), bytes) {
+            guard = (self.emptied.wait(guard)).unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// Whether a value of `bytes` bytes fits when `used` bytes are in use. Values larger than the
+    /// limit fit into an empty bin, so that they don't wait forever.
+    fn fits(&self, used: usize, bytes: usize) -> bool {
+        used == 0 || used.saturating_add(bytes) <= self.max
+    }
+}
+
+/// Room made for a value under a bin's limit, which is given back when this is dropped unless
+/// the value was [kept](Self::keep).
+#[derive(Debug)]
+#[must_use]
+pub(crate) struct Reservation<'l> {
+    /// The limit the room was made under, or `None` once it is kept or if there is no limit.
+    limit: Option<&'l Limit>,
+    bytes: usize,
+}
+
+impl Reservation<'_> {
+    /// Room made in a bin that has no limit.
+    pub(crate) const fn unlimited() -> Self {
+        Self {
+            limit: None,
+            bytes: 0,
+        }
+    }
+
+    /// Count the value towards the limit for good, now that it has been added.
+    pub(crate) fn keep(mut self) {
+        self.limit = None;
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        if let Some(limit) = self.limit {
+            limit.unreserve(self.bytes);
+        }
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::test_util::CallOnDrop;
+    use crate::Bin;
+    use crate::Overflow;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn block() {
+        let bin = Bin::builder().limit(16, Overflow::Block).build();
+        let added = AtomicBool::new(false);
+
+        bin.add([0_u8; 8]);
+        bin.add([0_u8; 8]);
+        thread::scope(|s| {
+            s.spawn(|| {
+                bin.add([0_u8; 8]);
+                added.store(true, SeqCst);
+            });
+
+            thread::sleep(Duration::from_millis(10));
+            assert!(!added.load(SeqCst));
+            bin.clear();
+        });
+        assert!(added.load(SeqCst));
+
+        // Values larger than the limit still get into an empty bin.
+        bin.clear();
+        bin.add([0_u8; 32]);
+    }
+
+    #[test]
+    fn partial_clears_give_back_room() {
+        let bin = Bin::builder().limit(16, Overflow::Block).build();
+
+        bin.add([0_u8; 8]);
+        bin.add([0_u8; 8]);
+        assert_eq!(bin.clear_if_above(8, Some(8)), 1);
+        assert_eq!(bin.bytes_used(), 8);
+        assert_eq!(bin.try_add([0_u8; 8]), Ok(()));
+
+        // A blocked add is woken by a partial clear.
+        let added = AtomicBool::new(false);
+        thread::scope(|s| {
+            s.spawn(|| {
+                bin.add([0_u8; 8]);
+                added.store(true, SeqCst);
+            });
+
+            thread::sleep(Duration::from_millis(10));
+            assert!(!added.load(SeqCst));
+            assert_eq!(bin.clear_oldest(1), 1);
+        });
+        assert!(added.load(SeqCst));
+    }
+
+    #[test]
+    fn reject() {
+        static OVERFLOWS: AtomicUsize = AtomicUsize::new(0);
+
+        fn on_overflow() {
+            OVERFLOWS.fetch_add(1, SeqCst);
+        }
+
+        let bin = Bin::builder()
+            .limit(16, Overflow::Reject(on_overflow))
+            .build();
+
+        assert_eq!(bin.try_add([1_u8; 16]), Ok(()));
+        assert_eq!(bin.try_add([2_u8; 8]), Err([2; 8]));
+        bin.add([3_u8; 8]);
+        assert_eq!(OVERFLOWS.load(SeqCst), 2);
+
+        bin.clear();
+        assert_eq!(bin.try_add([4_u8; 8]), Ok(()));
+        assert_eq!(OVERFLOWS.load(SeqCst), 2);
+    }
+
+    #[test]
+    fn dropped_values_give_back_room() {
+        let bin = Bin::builder().limit(16, Overflow::Block).build();
+        bin.seal();
+
+        // Values dropped because the bin is sealed don't keep the room made for them, so these
+        // adds never block.
+        for _ in 0..4 {
+            bin.add([0_u8; 8]);
+        }
+        assert_eq!(bin.limit.as_ref().unwrap().used.load(SeqCst), 0);
+    }
+
+    #[test]
+    fn spill() {
+        static SPILLS: AtomicUsize = AtomicUsize::new(0);
+
+        fn on_spill() {
+            SPILLS.fetch_add(1, SeqCst);
+        }
+
+        let dropped = AtomicUsize::new(0);
+        let bin = Bin::builder().limit(16, Overflow::Spill(on_spill)).build();
+
+        bin.add([0_u8; 16]);
+        bin.add(CallOnDrop(|| {
+            dropped.fetch_add(1, SeqCst);
+        }));
+        assert_eq!(dropped.load(SeqCst), 1);
+        assert_eq!(bin.spilled(), 1);
+        assert_eq!(SPILLS.load(SeqCst), 1);
+    }
+}
@@ -0,0 +1,113 @@
+//! Clearing bins when Windows reports that memory is low, enabled by the `low-memory` feature.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use drop_bin::low_memory::ClearOnLowMemory;
+//! use drop_bin::Bin;
+//! use std::sync::Arc;
+//!
+//! let bin = Arc::new(Bin::new());
+//! let _clear_on_low_memory = ClearOnLowMemory::new(bin.clone())?;
+//! # Ok::<_, std::io::Error>(())
+//! ```
+
+use crate::Bin;
+use std::io;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use windows_sys::Win32::Foundation::CloseHandle;
+use windows_sys::Win32::Foundation::HANDLE;
+use windows_sys::Win32::Foundation::WAIT_OBJECT_0;
+use windows_sys::Win32::System::Memory::CreateMemoryResourceNotification;
+use windows_sys::Win32::System::Memory::LowMemoryResourceNotification;
+use windows_sys::Win32::System::Threading::WaitForSingleObject;
+
+/// How often the background thread checks whether it should stop, and the minimum time between
+/// clears while memory stays low.
+const INTERVAL_MS: u32 = 1000;
+
+/// A handle to a memory resource notification object.
+struct Notification(HANDLE);
+
+// SAFETY: Kernel handles can be used from any thread.
+unsafe impl Send for Notification {}
+
+impl Drop for Notification {
+    fn drop(&mut self) {
+        // SAFETY: We own the handle.
+        unsafe { CloseHandle(self.0) };
+    }
+}
+
+/// Clears a bin whenever the operating system reports that physical memory is low.
+///
+/// The notification is waited on by a background thread, which stops when this is dropped. While
+/// memory stays low the bin is cleared at most once a second.
+#[derive(Debug)]
+pub struct ClearOnLowMemory {
+    stop: Arc<AtomicBool>,
+}
+
+impl ClearOnLowMemory {
+    /// Start clearing the bin when memory is low.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the notification object could not be created, or the background thread could not
+    /// be spawned.
+    pub fn new(bin: Arc<Bin<'static>>) -> io::Result<Self> {
+        // SAFETY: This has no preconditions.
+        let handle = unsafe { CreateMemoryResourceNotification(LowMemoryResourceNotification) };
+        if handle.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+        let notification = Notification(handle);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        thread::Builder::new()
+            .name("drop-bin-low-memory".to_owned())
+            .spawn({
+                let stop = stop.clone();
+                move || {
+                    // Move the whole handle in, rather than capturing just its pointer.
+                    let notification = notification;
+                    while !stop.load(Ordering::Relaxed) {
+                        // SAFETY: The handle is valid until `notification` is dropped.
+                        let signalled = unsafe { WaitForSingleObject(notification.0, INTERVAL_MS) };
+                        if signalled == WAIT_OBJECT_0 {
+                            bin.clear();
+                            // The notification stays signalled for as long as memory is low.
+                            thread::sleep(Duration::from_millis(INTERVAL_MS.into()));
+                        }
+                    }
+                }
+            })?;
+
+        Ok(Self { stop })
+    }
+}
+
+impl Drop for ClearOnLowMemory {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::low_memory::ClearOnLowMemory;
+    use crate::Bin;
+    use std::sync::Arc;
+
+    #[test]
+    fn start_stop() {
+        let bin = Arc::new(Bin::new());
+        let clear_on_low_memory = ClearOnLowMemory::new(bin.clone()).unwrap();
+        bin.add(Box::new(5));
+        drop(clear_on_low_memory);
+    }
+}
@@ -0,0 +1,193 @@
+use std::collections::TryReserveError;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::mem::ManuallyDrop;
+use std::mem::MaybeUninit;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::ptr;
+use std::ptr::NonNull;
+use std::slice;
+
+/// The capacity in bytes from which storages are memory mappings instead of allocations.
+pub(crate) const MMAP_THRESHOLD: usize = 1024 * 1024;
+
+/// The bytes of a storage, which behave like a `Vec` that never reallocates.
+///
+/// Large storages are anonymous memory mappings rather than allocations, so that the pages of
+/// emptied storages can be handed back to the operating system with [`release`](Self::release)
+/// instead of relying on the allocator to do it. The mapping itself is only unmapped when the
+/// bytes are dropped. Enabled by the `mmap` feature.
+pub(crate) struct Bytes {
+    ptr: NonNull<MaybeUninit<u8>>,
+    len: usize,
+    capacity: usize,
+    /// Whether the bytes are mapped, rather than allocated by a `Vec`.
+    mapped: bool,
+}
+
+// SAFETY: The bytes are owned like a `Vec`'s.
+unsafe impl Send for Bytes {}
+unsafe impl Sync for Bytes {}
+
+impl Bytes {
+    /// Create empty bytes with room for `capacity` bytes, mapping them if there are enough.
+    pub(crate) fn with_capacity(capacity: usize) -> Self {
+        Self::try_map(capacity).unwrap_or_else(|| Self::from_vec(Vec::with_capacity(capacity)))
+    }
+
+    /// Create empty bytes like `with_capacity`, failing instead of aborting if they can't be
+    /// allocated.
+    #[cfg_attr(feature = "boxed-storage", allow(dead_code))]
+    pub(crate) fn try_with_capacity(capacity: usize) -> Result<Self, TryReserveError> {
+        if let Some(bytes) = Self::try_map(capacity) {
+            return Ok(bytes);
+        }
+        let mut vec = Vec::new();
+        vec.try_reserve_exact(capacity)?;
+        Ok(Self::from_vec(vec))
+    }
+
+    /// Map `capacity` bytes if there are enough for it to be worth it.
+    fn try_map(capacity: usize) -> Option<Self> {
+        if capacity < MMAP_THRESHOLD {
+            return None;
+        }
+        Some(Self {
+            ptr: map(capacity)?,
+            len: 0,
+            capacity,
+            mapped: true,
+        })
+    }
+
+    /// Take over the allocation of an empty `Vec`.
+    fn from_vec(vec: Vec<MaybeUninit<u8>>) -> Self {
+        let mut vec = ManuallyDrop::new(vec);
+        Self {
+            // SAFETY: A `Vec`'s pointer is never null.
+            ptr: unsafe { NonNull::new_unchecked(vec.as_mut_ptr()) },
+            len: 0,
+            capacity: vec.capacity(),
+            mapped: false,
+        }
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Set the number of bytes in use.
+    ///
+    /// # Safety
+    ///
+    /// `len` must be at most the capacity.
+    #[cfg_attr(feature = "boxed-storage", allow(dead_code))]
+    pub(crate) unsafe fn set_len(&mut self, len: usize) {
+        self.len = len;
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    /// Give the pages of the bytes in use back to the operating system if they are mapped. They
+    /// read as zeros afterward.
+    pub(crate) fn release(&mut self) {
+        if self.mapped && self.len != 0 {
+            // SAFETY: The bytes are part of the mapping, and hold no values.
+            unsafe { libc::madvise(self.ptr.as_ptr().cast(), self.len, libc::MADV_DONTNEED) };
+        }
+    }
+}
+
+/// Map `len` bytes of anonymous memory, returning `None` if that fails.
+fn map(len: usize) -> Option<NonNull<MaybeUninit<u8>>> {
+    // SAFETY: A new private mapping doesn't affect any existing memory.
+    let ptr = unsafe {
+        libc::mmap(
+            ptr::null_mut(),
+            len,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        )
+    };
+    if ptr == libc::MAP_FAILED {
+        return None;
+    }
+    NonNull::new(ptr.cast())
+}
+
+impl Default for Bytes {
+    fn default() -> Self {
+        Self::with_capacity(0)
+    }
+}
+
+impl Deref for Bytes {
+    type Target = [MaybeUninit<u8>];
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: The first `len` bytes are within the allocation, and any byte is a valid
+        // `MaybeUninit<u8>`.
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl DerefMut for Bytes {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: As above, and the bytes are owned by `self`.
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl Debug for Bytes {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Bytes")
+            .field("len", &self.len)
+            .field("capacity", &self.capacity)
+            .field("mapped", &self.mapped)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for Bytes {
+    fn drop(&mut self) {
+        if self.mapped {
+            // SAFETY: The mapping was created by `map` with this length, and nothing else uses it.
+            unsafe { libc::munmap(self.ptr.as_ptr().cast(), self.capacity) };
+        } else {
+            // SAFETY: The pointer and capacity came from a `Vec` of this type.
+            drop(unsafe { Vec::from_raw_parts(self.ptr.as_ptr(), 0, self.capacity) });
+        }
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::mmap::Bytes;
+    use crate::mmap::MMAP_THRESHOLD;
+    use std::mem::MaybeUninit;
+
+    #[test]
+    fn mapped() {
+        let mut bytes = Bytes::with_capacity(MMAP_THRESHOLD);
+        assert!(bytes.mapped);
+        assert_eq!(bytes.capacity(), MMAP_THRESHOLD);
+
+        // SAFETY: The length is within the capacity.
+        unsafe { bytes.set_len(4096) };
+        bytes.fill(MaybeUninit::new(1));
+        bytes.release();
+        // SAFETY: Every byte was written, and then zeroed by the kernel.
+        #[cfg(target_os = "linux")]
+        assert!(bytes.iter().all(|byte| unsafe { byte.assume_init() } == 0));
+
+        let small = Bytes::with_capacity(16);
+        assert!(!small.mapped);
+        assert!(small.capacity() >= 16);
+    }
+}
@@ -0,0 +1,125 @@
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Clears are spaced out so that they take up at most this fraction of the time.
+const MAX_DUTY_CYCLE: u32 = 4;
+
+/// Schedules clears to keep the bytes of values in a bin near a target, set with
+/// [`Builder::pace`](crate::Builder::pace).
+///
+/// Like a garbage collector's pacer, it starts a clear early enough that the values added while
+/// the clear runs, at the rate they were added since the last one, don't take the bin far past
+/// the target.
+#[derive(Debug)]
+pub(crate) struct Pacer {
+    target: usize,
+    min_interval: Duration,
+    /// The number of bytes of values added since the last clear.
+    pending: AtomicUsize,
+    /// The number of pending bytes at which the next clear is started.
+    trigger: AtomicUsize,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    /// When the last clear finished, if there has been one.
+    last_clear: Option<Instant>,
+    /// How long the last clear took.
+    duration: Duration,
+}
+
+impl Pacer {
+    pub(crate) const fn new(target: usize, min_interval: Duration) -> Self {
+        Self {
+            target,
+            min_interval,
+            pending: AtomicUsize::new(0),
+            trigger: AtomicUsize::new(target),
+            state: Mutex::new(State {
+                last_clear: None,
+                duration: Duration::ZERO,
+            }),
+        }
+    }
+
+    /// Count bytes of values that have been added.
+    pub(crate) fn add(&self, bytes: usize) {
+        self.pending.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Check whether it is time to clear the bin.
+    pub(crate) fn should_clear(&self) -> bool {
+        if self.pending.load(Ordering::Relaxed) < self.trigger.load(Ordering::Relaxed) {
+            return false;
+        }
+        // Another thread is already deciding.
+        let Ok(state) = self.state.try_lock() else {
+            return false;
+        };
+        (state.last_clear).is_none_or(|last| last.elapsed() >= self.gap(&state))
+    }
+
+    /// Record that the bin has been cleared, in `duration`, and plan the next clear.
+    pub(crate) fn cleared(&self, duration: Duration) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        let pending = self.pending.swap(0, Ordering::Relaxed);
+
+        if let Some(last) = state.last_clear {
+            let elapsed = now.duration_since(last).as_secs_f64();
+            if elapsed > 0.0 {
+                // Truncation is fine for an estimate.
+                #[allow(
+                    clippy::cast_possible_truncation,
+                    clippy::cast_sign_loss,
+                    clippy::cast_precision_loss
+                )]
+                let growth = (pending as f64 / elapsed * duration.as_secs_f64()) as usize;
+                // Never start clearing before the bin is half way to the target.
+                let trigger = self.target.saturating_sub(growth).max(self.target / 2);
+                self.trigger.store(trigger, Ordering::Relaxed);
+            }
+        }
+
+        state.last_clear = Some(now);
+        state.duration = duration;
+    }
+
+    /// The shortest time to leave between clears.
+    fn gap(&self, state: &State) -> Duration {
+        self.min_interval.max(state.duration * MAX_DUTY_CYCLE)
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::Bin;
+    use std::time::Duration;
+
+    #[test]
+    fn clears_near_target() {
+        let bin = Bin::builder().pace(64, Duration::ZERO).build();
+        for _ in 0..7 {
+            bin.add(0_u64);
+        }
+        assert_eq!(bin.epoch(), 0);
+        bin.add(0_u64);
+        assert_eq!(bin.epoch(), 1);
+    }
+
+    #[test]
+    fn min_interval() {
+        let bin = Bin::builder().pace(8, Duration::MAX).build();
+        bin.add(0_u64);
+        assert_eq!(bin.epoch(), 1);
+        for _ in 0..10 {
+            bin.add(0_u64);
+        }
+        assert_eq!(bin.epoch(), 1);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::sync::atomic::AtomicBool;
+use crate::sync::atomic::Ordering;
+use std::any::Any;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::process;
+use std::thread;
+
+/// The payload of a panic.
+pub(crate) type Payload = Box<dyn Any + Send>;
+
+/// What a clear does when one of the destructors it runs panics, set with
+/// [`Builder::on_panic`](crate::Builder::on_panic).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PanicPolicy {
+    /// Let the panic unwind out of the call that was clearing the bin, which poisons the bin.
+    ///
+    /// Values that had not been dropped yet are kept in the bin until
+    /// [`Bin::recover`](crate::Bin::recover) discards them.
+    #[default]
+    Propagate,
+    /// Abort the process.
+    Abort,
+    /// Discard the panic and carry on dropping the other values.
+    Continue,
+    /// Carry on dropping the other values, keeping the panic's payload to be returned by
+    /// [`Bin::take_panics`](crate::Bin::take_panics).
+    Collect,
+}
+
+impl PanicPolicy {
+    /// Run a destructor, handling a panic in it according to the policy.
+    pub(crate) fn run(self, destructor: impl FnOnce(), panics: &mut Vec<Payload>) {
+        if self == Self::Propagate {
+            destructor();
+            return;
+        }
+
+        let Err(payload) = panic::catch_unwind(AssertUnwindSafe(destructor)) else {
+            return;
+        };
+        match self {
+            Self::Abort => process::abort(),
+            Self::Collect => panics.push(payload),
+            Self::Continue | Self::Propagate => {}
+        }
+    }
+}
+
+/// Poisons a bin if a destructor panic unwinds while this is alive.
+pub(crate) struct PoisonOnUnwind<'b> {
+    poisoned: &'b AtomicBool,
+    /// Whether the thread was already panicking, in which case the bin isn't to blame.
+    panicking: bool,
+}
+
+impl<'b> PoisonOnUnwind<'b> {
+    pub(crate) fn new(poisoned: &'b AtomicBool) -> Self {
+        Self {
+            poisoned,
+            panicking: thread::panicking(),
+        }
+    }
+}
+
+impl Drop for PoisonOnUnwind<'_> {
+    fn drop(&mut self) {
+        if !self.panicking && thread::panicking() {
+            self.poisoned.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::test_util::CallOnDrop;
+    use crate::Bin;
+    use crate::PanicPolicy;
+    use std::panic;
+    use std::panic::AssertUnwindSafe;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+
+    fn add_values(bin: &Bin<'_>, dropped: &'static AtomicUsize) {
+        bin.add(CallOnDrop(|| {
+            dropped.fetch_add(1, SeqCst);
+        }));
+        bin.add(CallOnDrop(|| panic!("destructor panicked")));
+        bin.add(CallOnDrop(|| {
+            dropped.fetch_add(1, SeqCst);
+        }));
+    }
+
+    #[test]
+    fn propagate() {
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        let bin = Bin::new();
+        add_values(&bin, &DROPPED);
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| bin.clear())).is_err());
+        assert_eq!(DROPPED.load(SeqCst), 1);
+
+        assert!(bin.is_poisoned());
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| bin.add(5))).is_err());
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| bin.clear())).is_err());
+
+        // The value that was never reached is discarded without being dropped.
+        assert_eq!(bin.recover(), 1);
+        assert!(!bin.is_poisoned());
+        bin.add(CallOnDrop(|| {
+            DROPPED.fetch_add(1, SeqCst);
+        }));
+        bin.clear();
+        assert_eq!(DROPPED.load(SeqCst), 2);
+    }
+
+    #[test]
+    fn drop_poisoned() {
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        let bin = Bin::new();
+        add_values(&bin, &DROPPED);
+        assert!(panic::catch_unwind(AssertUnwindSafe(|| bin.clear())).is_err());
+        drop(bin);
+        assert_eq!(DROPPED.load(SeqCst), 1);
+    }
+
+    #[test]
+    fn continue_() {
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        let bin = Bin::builder().on_panic(PanicPolicy::Continue).build();
+        add_values(&bin, &DROPPED);
+        bin.clear();
+        assert_eq!(DROPPED.load(SeqCst), 2);
+        assert!(bin.take_panics().is_empty());
+    }
+
+    #[test]
+    fn collect() {
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        let bin = Bin::builder().on_panic(PanicPolicy::Collect).build();
+        add_values(&bin, &DROPPED);
+        bin.clear();
+        assert_eq!(DROPPED.load(SeqCst), 2);
+
+        let panics = bin.take_panics();
+        assert_eq!(panics.len(), 1);
+        assert_eq!(
+            panics[0].downcast_ref::<&str>(),
+            Some(&"destructor panicked")
+        );
+        assert!(bin.take_panics().is_empty());
+    }
+}
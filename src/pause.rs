@@ -0,0 +1,133 @@
+use crate::inner::Detached;
+use crate::sync::atomic::AtomicU64;
+use crate::sync::atomic::Ordering;
+use crate::sync::const_fn;
+use crate::sync::Mutex;
+use std::sync::PoisonError;
+use std::time::Duration;
+
+/// The number of values dropped in the first slice, before anything is known about how long they
+/// take.
+const FIRST_SLICE: usize = 32;
+
+/// Splits clears into slices that each take about a target pause, set with
+/// [`Builder::max_pause`](crate::Builder::max_pause).
+///
+/// Like a garbage collector's pacer, it learns how long values take to drop from the slices that
+/// have run, and sizes the next slice to fit the target. The values a slice doesn't get to are
+/// kept here and dropped by the clears that follow.
+#[derive(Debug)]
+pub(crate) struct Slicer<'a> {
+    target: Duration,
+    /// The moving average of how long each value takes to drop in nanoseconds, or 0 before any
+    /// have been dropped.
+    per_value: AtomicU64,
+    /// The values left over from the last slice, and whether they were taken out by a compacting
+    /// clear.
+    rest: Mutex<Option<(Detached<'a>, bool)>>,
+}
+
+impl<'a> Slicer<'a> {
+    const_fn! {
+        pub(crate) fn new(target: Duration) -> Self {
+            Self {
+                target,
+                per_value: AtomicU64::new(0),
+                rest: Mutex::new(None),
+            }
+        }
+    }
+
+    /// The number of values to drop in the next slice.
+    pub(crate) fn slice_len(&self) -> usize {
+        let per_value = self.per_value.load(Ordering::Relaxed);
+        if per_value == 0 {
+            return FIRST_SLICE;
+        }
+        let len = self.target.as_nanos() / u128::from(per_value);
+        usize::try_from(len).unwrap_or(usize::MAX).max(1)
+    }
+
+    /// Record that a slice dropped `values` values in `duration`.
+    pub(crate) fn sliced(&self, values: usize, duration: Duration) {
+        let Some(sample) = duration.as_nanos().checked_div(values as u128) else {
+            return;
+        };
+        let sample = u64::try_from(sample).unwrap_or(u64::MAX).max(1);
+        let old = self.per_value.load(Ordering::Relaxed);
+        let new = if old == 0 {
+            sample
+        } else {
+            old / 4 * 3 + sample / 4
+        };
+        self.per_value.store(new.max(1), Ordering::Relaxed);
+    }
+
+    /// Take the values left over from the last slice, unless another thread is dropping them.
+    pub(crate) fn take(&self) -> Option<(Detached<'a>, bool)> {
+        self.rest.try_lock().ok()?.take()
+    }
+
+    /// Check whether there are values left over from the last slice.
+    pub(crate) fn has_rest(&self) -> bool {
+        (self.rest.lock().unwrap_or_else(PoisonError::into_inner)).is_some()
+    }
+
+    /// Keep values for the next slice, giving them back if values are already being kept.
+    pub(crate) fn put(&self, detached: Detached<'a>, compact: bool) -> Option<Detached<'a>> {
+        let mut rest = self.rest.lock().unwrap_or_else(PoisonError::into_inner);
+        if rest.is_some() {
+            return Some(detached);
+        }
+        *rest = Some((detached, compact));
+        None
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut Option<(Detached<'a>, bool)> {
+        (self.rest.get_mut()).unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::pause::FIRST_SLICE;
+    use crate::test_util::CallOnDrop;
+    use crate::Bin;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn slices() {
+        let dropped = AtomicUsize::new(0);
+        let bin = Bin::builder().max_pause(Duration::from_millis(1)).build();
+        for _ in 0..100 {
+            bin.add(CallOnDrop(|| {
+                thread::sleep(Duration::from_micros(100));
+                dropped.fetch_add(1, SeqCst);
+            }));
+        }
+
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), FIRST_SLICE);
+        assert_eq!(bin.epoch(), 1);
+
+        // Each value has been seen to take at least 100µs, so at most ten fit in a slice.
+        bin.clear();
+        let second = dropped.load(SeqCst) - FIRST_SLICE;
+        assert!((1..=10).contains(&second), "{second}");
+
+        // Values added in the meantime are taken out once the earlier ones have been dropped.
+        bin.add(CallOnDrop(|| {
+            dropped.fetch_add(1, SeqCst);
+        }));
+        for _ in 0..200 {
+            if dropped.load(SeqCst) == 101 {
+                break;
+            }
+            bin.clear();
+        }
+        assert_eq!(dropped.load(SeqCst), 101);
+    }
+}
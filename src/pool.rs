@@ -0,0 +1,316 @@
+use crate::weak_bins::WeakBins;
+use crate::Bin;
+use crate::Payload;
+#[cfg(feature = "thread-priority")]
+use crate::Scheduling;
+use std::any::Any;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::io;
+use std::mem;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use std::time::Instant;
+
+/// A small set of worker threads that periodically clear many bins.
+///
+/// Bins are cleared in turn, each at most once per interval, so that a few threads can serve
+/// any number of bins.
+///
+/// # Example
+///
+/// ```
+/// use drop_bin::Bin;
+/// use drop_bin::CleanerPool;
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let pool = CleanerPool::new(2, Duration::from_millis(100));
+///
+/// let audio = Arc::new(Bin::new());
+/// let network = Arc::new(Bin::new());
+/// pool.register(&audio);
+/// pool.register(&network);
+///
+/// audio.add(vec![0_f32; 4096]);
+/// // `audio` will be cleared by one of the pool's threads.
+/// ```
+pub struct CleanerPool {
+    shared: Arc<Shared>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+struct Shared {
+    /// Whether the pool has been stopped. This is locked before `bins`.
+    stopped: Mutex<bool>,
+    /// Notified when a bin is registered or the pool is stopped.
+    changed: Condvar,
+    /// The registered bins and when they are next due to be cleared, in order.
+    bins: WeakBins<Instant>,
+    /// The payloads of panics that unwound out of clears.
+    panics: Mutex<Vec<Payload>>,
+    interval: Duration,
+}
+
+impl CleanerPool {
+    /// Start a pool with the given number of worker threads, which clears each bin every
+    /// `interval`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a thread could not be spawned.
+    #[must_use]
+    pub fn new(threads: usize, interval: Duration) -> Self {
+        Self::start(threads, interval, || Ok(())).expect("failed to spawn cleaner thread")
+    }
+
+    /// Start a pool like [`new`](Self::new), whose threads are scheduled as given, such as at a
+    /// low priority on cores that latency-critical threads don't use.
+    ///
+    /// # Errors
+    ///
+    /// Fails if a thread could not be spawned, or its priority or affinity could not be set,
+    /// such as on platforms that don't support it.
+    #[cfg(feature = "thread-priority")]
+    pub fn with_scheduling(
+        threads: usize,
+        interval: Duration,
+        scheduling: &Scheduling,
+    ) -> io::Result<Self> {
+        let scheduling = scheduling.clone();
+        Self::start(threads, interval, move || scheduling.apply())
+    }
+
+    /// Start a pool whose threads each call `setup` before doing any work.
+    fn start(
+        threads: usize,
+        interval: Duration,
+        setup: impl Fn() -> io::Result<()> + Clone + Send + 'static,
+    ) -> io::Result<Self> {
+        let shared = Arc::new(Shared {
+            stopped: Mutex::new(false),
+            changed: Condvar::new(),
+            bins: WeakBins::new(),
+            panics: Mutex::new(Vec::new()),
+            interval,
+        });
+        // If a thread fails to start, dropping the pool stops the others.
+        let mut pool = Self {
+            shared,
+            workers: Vec::with_capacity(threads),
+        };
+
+        let (started, results) = mpsc::channel();
+        for i in 0..threads {
+            let shared = pool.shared.clone();
+            let setup = setup.clone();
+            let started = started.clone();
+            let worker = thread::Builder::new()
+                .name(format!("drop-bin-cleaner-{i}"))
+                .spawn(move || {
+                    let result = setup();
+                    let ok = result.is_ok();
+                    let _ = started.send(result);
+                    drop(started);
+                    if ok {
+                        shared.work();
+                    }
+                })?;
+            pool.workers.push(worker);
+        }
+        drop(started);
+        for result in results {
+            result?;
+        }
+
+        Ok(pool)
+    }
+
+    /// Have the pool clear the bin until it is dropped.
+    pub fn register(&self, bin: &Arc<Bin<'static>>) {
+        let stopped = self.shared.lock();
+        (self.shared.bins).register(bin, Instant::now() + self.shared.interval);
+        drop(stopped);
+        self.shared.changed.notify_one();
+    }
+
+    /// Take the payloads of the panics that unwound out of the pool's clears, oldest first.
+    ///
+    /// A destructor panic doesn't stop the pool. With the default [`PanicPolicy`], it
+    /// [poisons](Bin::is_poisoned) the bin, which the pool then skips until it is
+    /// [recovered](Bin::recover).
+    ///
+    /// [`PanicPolicy`]: crate::PanicPolicy
+    pub fn take_panics(&self) -> Vec<Box<dyn Any + Send>> {
+        let mut panics = (self.shared.panics.lock()).unwrap_or_else(PoisonError::into_inner);
+        mem::take(&mut *panics)
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, bool> {
+        self.stopped.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn work(&self) {
+        let mut stopped = self.lock();
+        loop {
+            if *stopped {
+                return;
+            }
+            let next = self.bins.lock().first().map(|&(_, due)| due);
+            let Some(due) = next else {
+                stopped = (self.changed.wait(stopped)).unwrap_or_else(PoisonError::into_inner);
+                continue;
+            };
+            let now = Instant::now();
+            if now < due {
+                stopped = (self.changed.wait_timeout(stopped, due - now))
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .0;
+                continue;
+            }
+
+            let (bin, _) = self.bins.lock().remove(0);
+            drop(stopped);
+
+            // Dropping the last reference to the bin clears it too, so that is caught as well.
+            let cleared = bin.upgrade().map(|bin| {
+                panic::catch_unwind(AssertUnwindSafe(move || {
+                    if !bin.is_poisoned() {
+                        bin.force_clear();
+                    }
+                }))
+            });
+
+            stopped = self.lock();
+            let Some(cleared) = cleared else {
+                continue;
+            };
+            if let Err(payload) = cleared {
+                (self.panics.lock().unwrap_or_else(PoisonError::into_inner)).push(payload);
+            }
+            // Go to the back of the queue, so that other bins get a turn first.
+            self.bins.lock().push((bin, Instant::now() + self.interval));
+        }
+    }
+}
+
+impl Debug for CleanerPool {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CleanerPool")
+            .field("threads", &self.workers.len())
+            .field("bins", &self.shared.bins.len())
+            .field("interval", &self.shared.interval)
+            .finish()
+    }
+}
+
+impl Drop for CleanerPool {
+    fn drop(&mut self) {
+        *self.shared.lock() = true;
+        self.shared.changed.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::test_util::CallOnDrop;
+    use crate::Bin;
+    use crate::CleanerPool;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn clears_bins() {
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        let pool = CleanerPool::new(2, Duration::from_millis(1));
+        let bins: Vec<_> = (0..5).map(|_| Arc::new(Bin::new())).collect();
+        for bin in &bins {
+            pool.register(bin);
+            bin.add(CallOnDrop(|| {
+                DROPPED.fetch_add(1, SeqCst);
+            }));
+        }
+
+        for _ in 0..1000 {
+            if DROPPED.load(SeqCst) == 5 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(DROPPED.load(SeqCst), 5);
+
+        drop(bins);
+        drop(pool);
+    }
+
+    #[test]
+    fn survives_panics() {
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        let pool = CleanerPool::new(1, Duration::from_millis(1));
+        let panicking = Arc::new(Bin::new());
+        pool.register(&panicking);
+        panicking.add(CallOnDrop(|| panic!("destructor panicked")));
+
+        let mut panics = Vec::new();
+        for _ in 0..1000 {
+            panics.extend(pool.take_panics());
+            if !panics.is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(panics.len(), 1);
+        assert!(panicking.is_poisoned());
+
+        // The worker carries on with the other bins.
+        let bin = Arc::new(Bin::new());
+        pool.register(&bin);
+        bin.add(CallOnDrop(|| _ = DROPPED.fetch_add(1, SeqCst)));
+        for _ in 0..1000 {
+            if DROPPED.load(SeqCst) == 1 {
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert_eq!(DROPPED.load(SeqCst), 1);
+        assert!(pool.take_panics().is_empty());
+    }
+
+    #[test]
+    #[cfg(all(feature = "thread-priority", target_os = "linux"))]
+    fn scheduling() {
+        use crate::Scheduling;
+        use crate::ThreadPriority;
+        use std::io;
+
+        let scheduling = Scheduling::new()
+            .priority(ThreadPriority::Lowest)
+            .cores([0]);
+        let pool = CleanerPool::with_scheduling(2, Duration::from_millis(1), &scheduling).unwrap();
+        drop(pool);
+
+        let scheduling = Scheduling::new().cores([usize::MAX]);
+        let error = CleanerPool::with_scheduling(2, Duration::from_millis(1), &scheduling);
+        assert_eq!(error.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,283 @@
+//! A bin for quiescent-state based reclamation, which only drops values once no thread can still
+//! be using them.
+//!
+//! This makes a bin usable for reclaiming memory in lock-free data structures: a value that has
+//! been unlinked from the structure is added to the bin, but readers that found it before it was
+//! unlinked may still hold references to it. Each reader registers with the bin and periodically
+//! announces a *quiescent state*, a point at which it holds no references into the structure, and
+//! values are only dropped once every registered reader has done so since they were added.
+//!
+//! # Example
+//!
+//! ```
+//! let bin = drop_bin::qsbr::Bin::new();
+//! let reader = bin.register();
+//!
+//! bin.add(Box::new(5));
+//! bin.clear();
+//! // The reader might still be using the box, so it is kept.
+//! assert_eq!(bin.pending(), 1);
+//!
+//! reader.quiescent();
+//! bin.clear();
+//! assert_eq!(bin.pending(), 0);
+//! ```
+
+use crate::inner::Detached;
+use crate::sync::atomic::AtomicU64;
+use crate::sync::atomic::Ordering;
+use crate::sync::const_fn;
+use crate::sync::Arc;
+use crate::sync::Mutex;
+use crate::sync::MutexGuard;
+use crate::sync::RwLock;
+use crate::Inner;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::sync::PoisonError;
+
+/// A bin whose values are only dropped once every registered thread has announced a quiescent
+/// state since they were added.
+///
+/// Clearing the bin takes its values out and drops those that every registered thread has since
+/// passed a quiescent state for; the rest are kept for later clears. With no registered threads,
+/// clearing drops everything like [`Bin::clear`](crate::Bin::clear).
+pub struct Bin<'a> {
+    retired: Retired<'a>,
+    /// The era that each registered thread last saw at a quiescent state.
+    threads: Mutex<Vec<Arc<AtomicU64>>>,
+}
+
+impl<'a> Bin<'a> {
+    const_fn! {
+        /// Create a new bin.
+        #[must_use]
+        pub fn new() -> Self {
+            Self {
+                retired: Retired::new(),
+                threads: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    /// Add a value to the bin, to be dropped once every registered thread has passed a quiescent
+    /// state.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub fn add<T: Send + 'a>(&self, value: T) {
+        self.retired.add(value);
+    }
+
+    /// Register the current thread as a reader, which holds off dropping values until it
+    /// announces a quiescent state with [`Participant::quiescent`].
+    ///
+    /// Values added before the thread registers don't wait for it, so it must register before it
+    /// starts reading. It is unregistered when the participant is dropped.
+    pub fn register(&self) -> Participant<'_, 'a> {
+        let seen = Arc::new(AtomicU64::new(self.retired.era()));
+        self.lock_threads().push(seen.clone());
+        Participant { bin: self, seen }
+    }
+
+    /// Take the values out of the bin, then drop every value that all registered threads have
+    /// passed a quiescent state for since it was added.
+    ///
+    /// Like [`Bin::leak`](crate::Bin::leak), this waits for
+    /// [adds in progress](crate::Bin::batch) to finish.
+    pub fn clear(&self) {
+        self.retired.clear(|| {
+            (self.lock_threads().iter())
+                .map(|seen| seen.load(Ordering::SeqCst))
+                .min()
+        });
+    }
+
+    /// Get the number of values that have been taken out of the bin, but are waiting for
+    /// registered threads to pass a quiescent state before they are dropped.
+    #[must_use]
+    pub fn pending(&self) -> usize {
+        self.retired.pending()
+    }
+
+    fn lock_threads(&self) -> MutexGuard<'_, Vec<Arc<AtomicU64>>> {
+        self.threads.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for Bin<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for Bin<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Bin")
+            .field("values", self.retired.values())
+            .field("pending", &self.pending())
+            .field("threads", &self.lock_threads().len())
+            .finish()
+    }
+}
+
+/// Values waiting for readers to move on before they are dropped, shared by this module and
+/// [`epoch`](crate::epoch).
+///
+/// Time is measured in *eras*, which advance whenever values are taken out of the bin. Values
+/// taken out are only dropped once every reader has been seen in the era they were taken out in
+/// or a later one.
+pub(crate) struct Retired<'a> {
+    /// The values that haven't been taken out yet.
+    values: crate::Bin<'a>,
+    /// Held for reading by adds and for writing while the values are taken out, so that the bin
+    /// is never locked during an add, which would drop the value immediately.
+    adding: RwLock<()>,
+    /// The number of times values have been taken out.
+    era: AtomicU64,
+    /// The values that have been taken out but not dropped, oldest first, with the era that
+    /// every reader must have been seen in before they can be.
+    taken: Mutex<VecDeque<(u64, Detached<'a>)>>,
+}
+
+impl<'a> Retired<'a> {
+    const_fn! {
+        pub(crate) fn new() -> Self {
+            Self {
+                values: crate::Bin::new(),
+                adding: RwLock::new(()),
+                era: AtomicU64::new(0),
+                taken: Mutex::new(VecDeque::new()),
+            }
+        }
+    }
+
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub(crate) fn add<T: Send + 'a>(&self, value: T) {
+        let _adding = self.adding.read().unwrap_or_else(PoisonError::into_inner);
+        self.values.add(value);
+    }
+
+    /// Add a raw pointer, to be cleaned up by calling `destructor` on it.
+    ///
+    /// # Safety
+    ///
+    /// It must be safe to call `destructor` with `ptr` exactly once, on any thread, once the
+    /// pointer is dropped by a clear.
+    pub(crate) unsafe fn add_raw(&self, ptr: *mut (), destructor: unsafe fn(*mut ())) {
+        let _adding = self.adding.read().unwrap_or_else(PoisonError::into_inner);
+        // SAFETY: Upheld by the caller. The bin can't be locked during the add, so the destructor
+        // isn't called immediately.
+        unsafe { self.values.add_raw(ptr, destructor) };
+    }
+
+    pub(crate) fn values(&self) -> &crate::Bin<'a> {
+        &self.values
+    }
+
+    /// Get the current era.
+    pub(crate) fn era(&self) -> u64 {
+        self.era.load(Ordering::SeqCst)
+    }
+
+    /// Take the values out, then drop those taken out in eras up to `oldest`, the earliest era
+    /// that a reader has been seen in, or all of them if it returns `None` for no readers.
+    pub(crate) fn clear(&self, oldest: impl FnOnce() -> Option<u64>) {
+        let mut taken = self.lock();
+        let adding = self.adding.write().unwrap_or_else(PoisonError::into_inner);
+        let detached = self.values.with_inner(Inner::detach);
+        drop(adding);
+        if !detached.is_empty() {
+            // The era is advanced after the values are taken out, so that a reader seen in it
+            // was seen after they were all added.
+            let era = self.era.fetch_add(1, Ordering::SeqCst) + 1;
+            taken.push_back((era, detached));
+        }
+
+        let oldest = oldest().unwrap_or(u64::MAX);
+        let ready = taken.partition_point(|&(era, _)| era <= oldest);
+        let ready: Vec<_> = taken.drain(..ready).collect();
+        // The destructors may add to the bin again.
+        drop(taken);
+        drop(ready);
+    }
+
+    /// Get the number of values that have been taken out, but not dropped.
+    pub(crate) fn pending(&self) -> usize {
+        self.lock().iter().map(|(_, d)| d.len()).sum()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, VecDeque<(u64, Detached<'a>)>> {
+        self.taken.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// A thread registered with a [`Bin`], returned by [`Bin::register`].
+///
+/// Values added to the bin aren't dropped until the thread has announced a quiescent state with
+/// [`quiescent`](Self::quiescent). Dropping this unregisters the thread.
+#[derive(Debug)]
+#[must_use]
+pub struct Participant<'b, 'a> {
+    bin: &'b Bin<'a>,
+    /// The era that the thread last saw at a quiescent state.
+    seen: Arc<AtomicU64>,
+}
+
+impl Participant<'_, '_> {
+    /// Announce that the thread holds no references to values that have been added to the bin,
+    /// so that they can be dropped by the next clear.
+    pub fn quiescent(&self) {
+        let era = self.bin.retired.era();
+        self.seen.store(era, Ordering::SeqCst);
+    }
+}
+
+impl Drop for Participant<'_, '_> {
+    fn drop(&mut self) {
+        (self.bin.lock_threads()).retain(|seen| !Arc::ptr_eq(seen, &self.seen));
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::qsbr::Bin;
+    use crate::test_util::CallOnDrop;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+
+    #[test]
+    fn waits_for_readers() {
+        let dropped = AtomicUsize::new(0);
+        let drop = || CallOnDrop(|| _ = dropped.fetch_add(1, SeqCst));
+        let bin = Bin::new();
+
+        // With no readers, clears drop everything.
+        bin.add(drop());
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 1);
+
+        let first = bin.register();
+        let second = bin.register();
+        bin.add(drop());
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 1);
+
+        // A quiescent state only counts for values taken out before it.
+        first.quiescent();
+        bin.add(drop());
+        bin.clear();
+        assert_eq!(bin.pending(), 2);
+
+        second.quiescent();
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 2);
+        assert_eq!(bin.pending(), 1);
+
+        // Unregistering stops a reader holding values back.
+        std::mem::drop(second);
+        first.quiescent();
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 3);
+    }
+}
@@ -0,0 +1,323 @@
+//! A bin with a fixed capacity that real-time threads can add to.
+//!
+//! # Example
+//!
+//! ```
+//! let bin = drop_bin::realtime::Bin::with_capacity(4096, 64);
+//!
+//! std::thread::scope(|s| {
+//!     s.spawn(|| {
+//!         // On the audio thread:
+//!         let buffer = vec![0.0_f32; 512];
+//!         if let Err(buffer) = bin.add(buffer) {
+//!             // The bin is full; keep the buffer until next time.
+//!             # drop(buffer);
+//!         }
+//!     });
+//! });
+//!
+//! bin.clear();
+//! ```
+
+use crate::inner::Destructor;
+use crate::sync::atomic::AtomicUsize;
+use crate::sync::atomic::Ordering;
+use crate::sync::yield_now;
+use crate::sync::Mutex;
+use crate::sync::UnsafeCell;
+use crate::zeroize;
+// Values are written to the storage through raw pointers, which loom can't check.
+use std::cell::UnsafeCell as StdUnsafeCell;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::marker::PhantomData;
+use std::mem;
+use std::mem::align_of;
+use std::mem::size_of;
+use std::mem::MaybeUninit;
+use std::ptr;
+use std::sync::PoisonError;
+
+/// The largest alignment of the values a bin can hold.
+const MAX_ALIGN: usize = 64;
+
+/// The alignment that every value's place in storage starts at.
+const GRAIN: usize = 8;
+
+/// A bin that never blocks, allocates or retries when values are added to it, so that threads
+/// with real-time deadlines can use it.
+///
+/// All of the bin's memory is allocated when it is created. Adding a value takes a bounded number
+/// of steps however many other threads are adding to or clearing the bin at the same time: it
+/// never waits for a lock, never loops on a failed compare-and-swap and never calls the
+/// allocator. When there is no room for a value, or in the rare case that two clears happen
+/// while it is being added, the value is given back instead.
+///
+/// The bin is split into two halves. Values are added to one half while the other is cleared, and
+/// each clear swaps them around, so clearing never holds off adds. Clearing may wait for adds to
+/// the half it is about to clear to finish, so it shouldn't be done on a real-time thread.
+pub struct Bin<'a> {
+    halves: [Half; 2],
+    /// The index of the half that values are added to.
+    active: AtomicUsize,
+    /// Held while clearing, so that only one clear swaps the halves at a time.
+    clearing: Mutex<()>,
+    invariant_over_lifetime_a: PhantomData<fn(&'a ()) -> &'a ()>,
+}
+
+/// One half of a bin's memory.
+struct Half {
+    /// The storage that values are moved into.
+    storage: Box<[StdUnsafeCell<MaybeUninit<Block>>]>,
+    /// The values in the storage and their destructors. Slots that are `None` were reserved by
+    /// adds that didn't go ahead, or have already been dropped.
+    entries: Box<[UnsafeCell<Entry>]>,
+    /// The number of bytes of the storage that have been reserved, which may be more than its
+    /// size.
+    bytes: AtomicUsize,
+    /// The number of entries that have been reserved, which may be more than there are.
+    values: AtomicUsize,
+    /// The number of adds that are using this half.
+    adding: AtomicUsize,
+}
+
+/// A value in a half's storage and its destructor.
+type Entry = Option<(Destructor, *mut ())>;
+
+/// A maximally-aligned chunk of storage.
+#[repr(align(64))]
+#[allow(dead_code)] // Only the size and alignment are used.
+struct Block([u8; MAX_ALIGN]);
+
+unsafe impl Send for Bin<'_> {}
+unsafe impl Sync for Bin<'_> {}
+
+impl<'a> Bin<'a> {
+    /// Create a new bin that can hold up to `values` values taking up to `bytes` bytes, both
+    /// between clears.
+    ///
+    /// Values take up their size rounded up to a multiple of 8 bytes, plus up to their alignment
+    /// in padding if that is more than 8. Twice this is allocated, since the bin can be added to
+    /// while it is being cleared.
+    #[must_use]
+    pub fn with_capacity(bytes: usize, values: usize) -> Self {
+        Self {
+            halves: [Half::new(bytes, values), Half::new(bytes, values)],
+            active: AtomicUsize::new(0),
+            clearing: Mutex::new(()),
+            invariant_over_lifetime_a: PhantomData,
+        }
+    }
+
+    /// Add a value to the bin, or give it back if there is no room for it.
+    ///
+    /// This is wait-free and doesn't allocate; see the [type-level docs](Self) for details.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the bin is full, if the value is aligned to more than 64 bytes, or if the bin was
+    /// cleared twice while the value was being added.
+    pub fn add<T: Send + 'a>(&self, value: T) -> Result<(), T> {
+        // The first attempt only fails if a clear swaps the halves around during it, so the second
+        // can only fail if another clear swaps them back.
+        for _ in 0..2 {
+            let index = self.active.load(Ordering::SeqCst);
+            let half = &self.halves[index];
+
+            half.adding.fetch_add(1, Ordering::SeqCst);
+            // Either a clear that swaps the halves from here on waits for this add to finish, or
+            // this sees the swap and leaves the half alone.
+            if self.active.load(Ordering::SeqCst) != index {
+                half.adding.fetch_sub(1, Ordering::Release);
+                continue;
+            }
+
+            let result = half.add(value);
+            half.adding.fetch_sub(1, Ordering::Release);
+            return result;
+        }
+        Err(value)
+    }
+
+    /// Drop all the values that have been added so far.
+    ///
+    /// This waits for any adds to the half being cleared to finish, which takes a bounded number of
+    /// steps. Returns the number of values dropped.
+    pub fn clear(&self) -> usize {
+        let _clearing = self.clearing.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let index = self.active.load(Ordering::Relaxed);
+        self.active.store(1 - index, Ordering::SeqCst);
+
+        let half = &self.halves[index];
+        while half.adding.load(Ordering::SeqCst) != 0 {
+            yield_now();
+        }
+
+        // SAFETY: Adds have moved to the other half, and no other clear can run.
+        unsafe { half.clear() }
+    }
+}
+
+impl Half {
+    fn new(bytes: usize, values: usize) -> Self {
+        Self {
+            storage: (0..bytes.div_ceil(MAX_ALIGN))
+                .map(|_| StdUnsafeCell::new(MaybeUninit::uninit()))
+                .collect(),
+            entries: (0..values).map(|_| UnsafeCell::new(None)).collect(),
+            bytes: AtomicUsize::new(0),
+            values: AtomicUsize::new(0),
+            adding: AtomicUsize::new(0),
+        }
+    }
+
+    /// Move a value into the half. The caller must be counted in `adding`.
+    fn add<T>(&self, value: T) -> Result<(), T> {
+        let (size, align) = (size_of::<T>(), align_of::<T>());
+        if align > MAX_ALIGN {
+            return Err(value);
+        }
+
+        // Reserving enough for the value to be aligned wherever it ends up avoids retrying.
+        // Values too large to round up can't fit anyway.
+        let Some(rounded) = size.checked_next_multiple_of(GRAIN) else {
+            return Err(value);
+        };
+        let reserved = rounded.saturating_add(align.saturating_sub(GRAIN));
+        let capacity = self.storage.len() * MAX_ALIGN;
+        if reserved > capacity {
+            return Err(value);
+        }
+        let start = self.bytes.fetch_add(reserved, Ordering::Relaxed);
+        let offset = start.next_multiple_of(align);
+        if offset + size > capacity {
+            return Err(value);
+        }
+        let index = self.values.fetch_add(1, Ordering::Relaxed);
+        let Some(entry) = self.entries.get(index) else {
+            return Err(value);
+        };
+
+        let storage = StdUnsafeCell::raw_get(self.storage.as_ptr()).cast::<u8>();
+        // SAFETY: The storage is aligned to `MAX_ALIGN`, and the bytes were reserved for this
+        // value alone.
+        let value_ptr = unsafe { storage.add(offset) }.cast::<T>();
+        unsafe { value_ptr.write(value) };
+
+        let destructor: Destructor = unsafe {
+            // SAFETY: `*mut T` can be soundly transmuted to `*mut ()`, and so `fn(*mut T)` can be
+            // soundly transmuted to `fn(*mut ())`
+            mem::transmute::<unsafe fn(*mut T), fn(*mut ())>(ptr::drop_in_place::<T>)
+        };
+        // SAFETY: The entry was reserved for this value alone.
+        entry.with_mut(|entry| unsafe { *entry = Some((destructor, value_ptr.cast::<()>())) });
+        Ok(())
+    }
+
+    /// Drop the values in the half.
+    ///
+    /// # Safety
+    ///
+    /// Nothing else may be using the half.
+    unsafe fn clear(&self) -> usize {
+        let values = self.values.load(Ordering::Relaxed).min(self.entries.len());
+        let mut dropped = 0;
+        for entry in &self.entries[..values] {
+            // The entry is emptied first, so that if the destructor panics the remaining values
+            // are dropped by the next clear of this half.
+            if let Some((destructor, value_ptr)) =
+                entry.with_mut(|entry| unsafe { (*entry).take() })
+            {
+                unsafe { destructor(value_ptr) };
+                dropped += 1;
+            }
+        }
+        let used = (self.bytes.load(Ordering::Relaxed)).min(self.storage.len() * MAX_ALIGN);
+        // SAFETY: The values in the storage are gone.
+        unsafe { zeroize::zero(StdUnsafeCell::raw_get(self.storage.as_ptr()), used) };
+        self.bytes.store(0, Ordering::Relaxed);
+        self.values.store(0, Ordering::Relaxed);
+        dropped
+    }
+}
+
+impl Drop for Bin<'_> {
+    fn drop(&mut self) {
+        for half in &self.halves {
+            // SAFETY: We have unique access to the bin.
+            unsafe { half.clear() };
+        }
+    }
+}
+
+impl Debug for Bin<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Bin")
+            .field("capacity", &self.halves[0].entries.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::realtime::Bin;
+    use crate::test_util::assert_thread_safe;
+    use crate::test_util::CallOnDrop;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::thread;
+
+    #[test]
+    fn realtime() {
+        let dropped = AtomicUsize::new(0);
+        let bin = Bin::with_capacity(64, 8);
+
+        thread::scope(|s| {
+            for _ in 0..2 {
+                s.spawn(|| {
+                    for _ in 0..4 {
+                        let value = CallOnDrop(|| {
+                            dropped.fetch_add(1, SeqCst);
+                        });
+                        assert!(bin.add(value).is_ok());
+                    }
+                });
+            }
+        });
+        // The bin is full.
+        assert!(bin.add(5_u8).is_err());
+        assert!(bin.add([0_u8; 128]).is_err());
+        assert_eq!(dropped.load(SeqCst), 0);
+
+        assert_eq!(bin.clear(), 8);
+        assert_eq!(dropped.load(SeqCst), 8);
+
+        // Values are added to the other half while this one is cleared, and both are dropped with
+        // the bin.
+        bin.add(vec![1, 2, 3]).unwrap();
+        let value = CallOnDrop(|| {
+            dropped.fetch_add(1, SeqCst);
+        });
+        assert!(bin.add(value).is_ok());
+        drop(bin);
+        assert_eq!(dropped.load(SeqCst), 9);
+    }
+
+    #[test]
+    fn over_aligned() {
+        #[repr(align(128))]
+        struct Aligned;
+
+        let bin = Bin::with_capacity(1024, 8);
+        assert!(bin.add(Aligned).is_err());
+        assert!(bin.add(()).is_ok());
+    }
+
+    #[test]
+    #[allow(clippy::extra_unused_lifetimes)]
+    fn thread_safe<'a>() {
+        assert_thread_safe::<Bin<'a>>();
+    }
+}
@@ -0,0 +1,97 @@
+use crate::Bin;
+use crate::Key;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+
+/// A part of a bin that can be cleared on its own, created by [`Bin::region`].
+///
+/// Values added through a region share the bin's storage and are dropped by its ordinary clears
+/// and background cleaner, but [`clear`](Self::clear) drops only them, leaving the rest of the
+/// bin alone. This is useful for cleaning up after a single request or task. Dropping the region
+/// doesn't drop its values; they stay in the bin until it is next cleared.
+///
+/// # Example
+///
+/// ```
+/// let bin = drop_bin::Bin::new();
+/// bin.add("Hello World!".to_owned());
+///
+/// let request = bin.region();
+/// request.add(vec![0_u8; 1024]);
+/// request.add(vec![1_u8; 1024]);
+///
+/// assert_eq!(request.clear(), 2);
+/// // The string is still in the bin.
+/// ```
+pub struct Region<'b, 'a> {
+    bin: &'b Bin<'a>,
+    id: Key,
+}
+
+impl<'b, 'a> Region<'b, 'a> {
+    pub(crate) fn new(bin: &'b Bin<'a>) -> Self {
+        Self {
+            bin,
+            id: Key::next(),
+        }
+    }
+
+    /// Add a value to the bin through the region.
+    ///
+    /// Like [`Bin::add`], the value may be dropped immediately.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub fn add<T: Send + 'a>(&self, value: T) {
+        if let Some(inner) = self.bin.adding::<T>().inner() {
+            inner.add_in_region(value, self.id);
+        }
+    }
+
+    /// Drop only the values that were added through the region.
+    ///
+    /// This returns the number of values dropped, and like [`Bin::clear_tag`] does nothing if
+    /// another thread is currently adding a value.
+    // The count is informational; the clear is what matters.
+    #[allow(clippy::must_use_candidate)]
+    pub fn clear(&self) -> usize {
+        let id = self.id;
+        self.bin.clear_some(|inner| inner.clear_region(id))
+    }
+}
+
+impl Debug for Region<'_, '_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Region").finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::test_util::CallOnDrop;
+    use crate::Bin;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+
+    #[test]
+    fn region() {
+        let dropped = AtomicUsize::new(0);
+        let value = || CallOnDrop(|| _ = dropped.fetch_add(1, SeqCst));
+        let bin = Bin::new();
+
+        bin.add(value());
+        let a = bin.region();
+        let b = bin.region();
+        a.add(value());
+        b.add(value());
+        a.add(value());
+
+        assert_eq!(a.clear(), 2);
+        assert_eq!(dropped.load(SeqCst), 2);
+        assert_eq!(a.clear(), 0);
+
+        // Values in a region are still dropped by ordinary clears.
+        bin.clear();
+        assert_eq!(dropped.load(SeqCst), 4);
+        assert_eq!(b.clear(), 0);
+    }
+}
@@ -0,0 +1,98 @@
+//! A process-wide list of named bins, for inspecting them at runtime.
+//!
+//! Bins registered under a name show up in [`snapshot`], which reports what each of them holds
+//! and when it was last cleared, such as for an admin or debug endpoint.
+//!
+//! # Example
+//!
+//! ```
+//! use drop_bin::registry;
+//! use drop_bin::Bin;
+//! use std::sync::Arc;
+//!
+//! let textures = Arc::new(Bin::new());
+//! registry::register("textures", &textures);
+//! textures.add(vec![0_u8; 4096]);
+//!
+//! for bin in registry::snapshot() {
+//!     println!("{}: {} items, {} bytes", bin.name, bin.items, bin.bytes);
+//! }
+//! ```
+
+use crate::weak_bins::WeakBins;
+use crate::Bin;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// The registered bins, in the order they were registered.
+static BINS: WeakBins<String> = WeakBins::new();
+
+/// What a registered bin held when [`snapshot`] was called.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BinSnapshot {
+    /// The name the bin was registered under.
+    pub name: String,
+    /// The number of values in the bin.
+    pub items: usize,
+    /// The number of bytes of storage that the values take up, as given by
+    /// [`Bin::bytes_used`].
+    pub bytes: usize,
+    /// When the bin was last cleared, or `None` if it hasn't been.
+    pub last_clear: Option<SystemTime>,
+}
+
+/// Register a bin under a name, so that it is included in [`snapshot`].
+///
+/// Names don't have to be unique; bins registered under the same name are listed separately.
+pub fn register(name: impl Into<String>, bin: &Arc<Bin<'static>>) {
+    BINS.register(bin, name.into());
+}
+
+/// Get what every registered bin holds, in the order they were registered.
+///
+/// Like [`Bin::memory_usage`], this may or may not count values that are being added
+/// concurrently.
+#[must_use]
+pub fn snapshot() -> Vec<BinSnapshot> {
+    let now = SystemTime::now();
+    (BINS.alive().into_iter())
+        .map(|(bin, name)| BinSnapshot {
+            name,
+            items: bin.estimate_clear_cost().items,
+            bytes: bin.bytes_used(),
+            last_clear: (bin.last_cleared()).and_then(|at| now.checked_sub(at.elapsed())),
+        })
+        .collect()
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::registry;
+    use crate::Bin;
+    use std::sync::Arc;
+
+    #[test]
+    fn snapshot() {
+        let find = |name| {
+            registry::snapshot()
+                .into_iter()
+                .find(|bin| bin.name == name)
+        };
+        let bin = Arc::new(Bin::new());
+        registry::register("registry::tests::snapshot", &bin);
+
+        bin.add(5_u64);
+        let snapshot = find("registry::tests::snapshot").unwrap();
+        assert_eq!(snapshot.items, 1);
+        assert_eq!(snapshot.last_clear, None);
+
+        bin.clear();
+        let snapshot = find("registry::tests::snapshot").unwrap();
+        assert_eq!(snapshot.items, 0);
+        assert!(snapshot.last_clear.is_some());
+
+        drop(bin);
+        assert!(find("registry::tests::snapshot").is_none());
+    }
+}
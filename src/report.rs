@@ -0,0 +1,339 @@
+#[cfg(feature = "drop-times")]
+use crate::drop_times;
+#[cfg(feature = "debug-types")]
+use crate::Tag;
+#[cfg(feature = "debug-types")]
+use std::panic::Location;
+use std::time::Duration;
+#[cfg(any(feature = "drop-histogram", feature = "drop-times"))]
+use std::time::Instant;
+
+/// A summary of a completed clear, given to the hook set with
+/// [`Builder::on_clear`](crate::Builder::on_clear).
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct ClearReport {
+    /// The number of values that were dropped.
+    pub values: usize,
+    /// The number of bytes of storage that the values took up.
+    pub bytes: usize,
+    /// How long the clear took, including dropping the values.
+    pub duration: Duration,
+    /// How long each of the values took to drop. Enabled by the `drop-histogram` feature.
+    #[cfg(feature = "drop-histogram")]
+    pub histogram: DropHistogram,
+    /// How long the values took to drop by type, to be learned from once the clear is done.
+    #[cfg(feature = "drop-times")]
+    pub(crate) samples: drop_times::Samples,
+}
+
+impl ClearReport {
+    /// Drop a value, recording it in the report. `destructor` identifies the value's type.
+    #[cfg_attr(not(feature = "drop-times"), allow(unused_variables))]
+    pub(crate) fn record(&mut self, destructor: usize, drop_value: impl FnOnce()) {
+        #[cfg(any(feature = "drop-histogram", feature = "drop-times"))]
+        let start = Instant::now();
+
+        drop_value();
+
+        #[cfg(any(feature = "drop-histogram", feature = "drop-times"))]
+        let elapsed = start.elapsed();
+        #[cfg(feature = "drop-histogram")]
+        self.histogram.record(elapsed);
+        #[cfg(feature = "drop-times")]
+        self.samples.record(destructor, elapsed);
+        self.values += 1;
+    }
+}
+
+/// A breakdown of the memory a bin is using, returned by
+/// [`Bin::memory_usage`](crate::Bin::memory_usage).
+///
+/// Storages that are being added to at the time are counted as unused.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct MemoryUsage {
+    /// The number of bytes occupied by the values themselves.
+    pub values: usize,
+    /// The number of bytes of padding inserted to align the values.
+    pub padding: usize,
+    /// The number of bytes of storage that don't hold anything, including emptied storages
+    /// kept for reuse.
+    pub unused: usize,
+    /// The number of bytes taken up by the table of the values' destructors.
+    pub overhead: usize,
+}
+
+impl MemoryUsage {
+    /// Get the total number of bytes.
+    #[must_use]
+    pub fn total(&self) -> usize {
+        self.values + self.padding + self.unused + self.overhead
+    }
+}
+
+/// How long a clear paused for, as remembered by
+/// [`Bin::recent_clears`](crate::Bin::recent_clears).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ClearPause {
+    /// How long the clear took, including dropping the values.
+    pub duration: Duration,
+    /// The number of values that were dropped.
+    pub items: usize,
+}
+
+/// An estimate of what clearing a bin would cost, returned by
+/// [`Bin::estimate_clear_cost`](crate::Bin::estimate_clear_cost).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ClearCost {
+    /// The number of values that would be dropped.
+    pub items: usize,
+    /// The number of bytes of storage that the values take up, including padding.
+    pub bytes: usize,
+    /// How long the values are expected to take to drop, or `None` if no value of any of their
+    /// types has been dropped yet. Enabled by the `drop-times` feature.
+    ///
+    /// This sums the moving averages of the drop times of the values' types, counting types that
+    /// haven't been dropped yet as taking no time. Values in an arena that adds are using at the
+    /// time aren't counted.
+    #[cfg(feature = "drop-times")]
+    pub duration: Option<Duration>,
+}
+
+/// What is known about a value in a bin, given to the predicate of
+/// [`Bin::clear_where`](crate::Bin::clear_where). Enabled by the `debug-types` feature.
+#[cfg(feature = "debug-types")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ValueMeta {
+    /// The name of the value's type.
+    pub type_name: &'static str,
+    /// The number of bytes of the bin's storage that the value takes up, as counted by
+    /// [`Bin::bytes_used`](crate::Bin::bytes_used).
+    pub bytes: usize,
+    /// How long ago the value was added.
+    pub age: Duration,
+    /// The tag the value was added with, if any.
+    pub tag: Option<Tag>,
+    /// Where the value was added from.
+    pub location: &'static Location<'static>,
+}
+
+/// A storage segment that a bin has allocated, given to the callback set with
+/// [`Builder::on_segment_alloc`](crate::Builder::on_segment_alloc).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SegmentAlloc {
+    /// The size of the segment in bytes.
+    pub size: usize,
+    /// The size in bytes of the value that the segment was allocated for, or 0 if a clear
+    /// allocated it ahead of time to compact the bin's storage.
+    pub value_size: usize,
+}
+
+/// The outcome of [`Bin::try_clear`](crate::Bin::try_clear).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClearAttempt {
+    /// The bin was cleared, dropping `items` values.
+    Cleared {
+        /// The number of values that were dropped.
+        items: usize,
+    },
+    /// The bin couldn't be cleared without waiting for `readers` adds in progress to finish. This
+    /// may be zero if another thread is clearing the bin.
+    Busy {
+        /// The number of adds in progress.
+        readers: usize,
+    },
+    /// There was nothing to clear.
+    Empty,
+}
+
+/// A histogram of how long individual destructors took to run, enabled by the `drop-histogram`
+/// feature.
+///
+/// Durations are grouped into buckets by powers of two nanoseconds, so that a handful of slow
+/// destructors stand out against a large number of fast ones.
+#[cfg(feature = "drop-histogram")]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DropHistogram {
+    /// The number of durations below 2<sup>i + 1</sup> nanoseconds but not below the previous
+    /// bucket. The last bucket holds everything longer.
+    buckets: [usize; 32],
+    max: Duration,
+}
+
+#[cfg(feature = "drop-histogram")]
+impl DropHistogram {
+    fn record(&mut self, duration: Duration) {
+        let nanos = u64::try_from(duration.as_nanos()).unwrap_or(u64::MAX);
+        let bucket = (nanos.checked_ilog2().unwrap_or(0) as usize).min(self.buckets.len() - 1);
+        self.buckets[bucket] += 1;
+        self.max = self.max.max(duration);
+    }
+
+    /// Get the number of durations recorded.
+    #[must_use]
+    pub fn count(&self) -> usize {
+        self.buckets.iter().sum()
+    }
+
+    /// Get the longest duration recorded.
+    #[must_use]
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// Iterate over the non-empty buckets, as the exclusive upper bound of each bucket together
+    /// with the number of durations in it.
+    ///
+    /// The bound of the last bucket is [`Duration::MAX`].
+    pub fn buckets(&self) -> impl Iterator<Item = (Duration, usize)> + '_ {
+        let last = self.buckets.len() - 1;
+        (self.buckets.iter().enumerate())
+            .filter(|&(_, &count)| count != 0)
+            .map(move |(i, &count)| {
+                let bound = if i == last {
+                    Duration::MAX
+                } else {
+                    Duration::from_nanos(2 << i)
+                };
+                (bound, count)
+            })
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    #[cfg(feature = "drop-times")]
+    use crate::test_util::CallOnDrop;
+    use crate::Bin;
+    use crate::ClearCost;
+    use crate::ClearReport;
+    use crate::MemoryUsage;
+    #[cfg(not(feature = "boxed-storage"))]
+    use crate::SegmentAlloc;
+    use std::sync::Mutex;
+    #[cfg(feature = "drop-times")]
+    use std::thread;
+    #[cfg(feature = "drop-times")]
+    use std::time::Duration;
+
+    static REPORT: Mutex<Option<ClearReport>> = Mutex::new(None);
+
+    fn on_clear(report: &ClearReport) {
+        *REPORT.lock().unwrap() = Some(report.clone());
+    }
+
+    #[test]
+    fn on_clear_hook() {
+        let bin = Bin::builder().on_clear(on_clear).build();
+        bin.add(vec![1, 2, 3]);
+        bin.add(5_u32);
+        bin.clear();
+
+        let report = REPORT.lock().unwrap().take().unwrap();
+        assert_eq!(report.values, 2);
+
+        #[cfg(feature = "drop-histogram")]
+        {
+            let histogram = report.histogram;
+            assert_eq!(histogram.count(), 2);
+            assert_eq!(
+                histogram.buckets().map(|(_, count)| count).sum::<usize>(),
+                2
+            );
+            assert!(histogram.buckets().last().unwrap().0 > histogram.max());
+        }
+    }
+
+    #[test]
+    #[cfg(not(feature = "boxed-storage"))]
+    fn on_segment_alloc() {
+        static ALLOCS: Mutex<Vec<SegmentAlloc>> = Mutex::new(Vec::new());
+        fn on_segment_alloc(alloc: &SegmentAlloc) {
+            ALLOCS.lock().unwrap().push(*alloc);
+        }
+        let sizes = || {
+            let allocs = ALLOCS.lock().unwrap();
+            allocs
+                .iter()
+                .map(|a| (a.size, a.value_size))
+                .collect::<Vec<_>>()
+        };
+
+        let bin = Bin::builder()
+            .max_segment_size(4096)
+            .on_segment_alloc(on_segment_alloc)
+            .build();
+        bin.add(5_u64);
+        bin.add(6_u64);
+        assert_eq!(sizes(), [(1024, 8)]);
+
+        // Values larger than the segment size get segments of their own.
+        bin.add([0_u8; 8192]);
+        assert_eq!(sizes(), [(1024, 8), (8193, 8192)]);
+
+        // Emptied segments are reused rather than allocated again. Each clear switches adds over
+        // to the other arena, so two bring them back to the first one.
+        bin.clear();
+        bin.clear();
+        bin.add(7_u64);
+        assert_eq!(sizes().len(), 2);
+    }
+
+    #[test]
+    fn memory_usage() {
+        let bin = Bin::new();
+        assert_eq!(bin.memory_usage(), MemoryUsage::default());
+
+        bin.add(1_u8);
+        bin.add(2_u64);
+        let usage = bin.memory_usage();
+        #[cfg(not(feature = "boxed-storage"))]
+        {
+            assert_eq!(usage.values, 9);
+            assert!(usage.padding < 8);
+        }
+        assert_ne!(usage.overhead, 0);
+        assert_eq!(usage.total(), bin.capacity() + usage.overhead);
+
+        bin.clear();
+        let usage = bin.memory_usage();
+        assert_eq!((usage.values, usage.padding, usage.overhead), (0, 0, 0));
+        assert_eq!(usage.unused, bin.free_bytes());
+    }
+
+    #[test]
+    fn estimate_clear_cost() {
+        let bin = Bin::new();
+        assert_eq!(bin.estimate_clear_cost(), ClearCost::default());
+
+        bin.add(1_u8);
+        bin.add(2_u64);
+        let cost = bin.estimate_clear_cost();
+        assert_eq!(cost.items, 2);
+        assert_eq!(cost.bytes, bin.bytes_used());
+
+        bin.clear();
+        assert_eq!(bin.estimate_clear_cost().items, 0);
+
+        #[cfg(feature = "drop-times")]
+        {
+            let bin = Bin::new();
+            let sleep = || thread::sleep(Duration::from_millis(1));
+            bin.add(CallOnDrop(sleep));
+            assert_eq!(bin.estimate_clear_cost().duration, None);
+            bin.clear();
+
+            // The estimate goes by the types of the values in the bin.
+            bin.add(CallOnDrop(sleep));
+            bin.add(CallOnDrop(sleep));
+            bin.add(CallOnDrop(|| {}));
+            let expected = bin.estimate_clear_cost().duration.unwrap();
+            assert!(expected >= Duration::from_millis(2));
+        }
+    }
+}
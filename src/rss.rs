@@ -0,0 +1,128 @@
+//! Clearing bins when the process's resident set size gets too large, enabled by the
+//! `memory-stats` feature.
+//!
+//! The RSS can either be checked every so many adds, with
+//! [`Builder::clear_above_rss`](crate::Builder::clear_above_rss), or periodically on a background
+//! thread, with [`RssWatcher`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use drop_bin::rss::RssWatcher;
+//! use drop_bin::Bin;
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//!
+//! let bin = Arc::new(Bin::new());
+//! // Clear the bin whenever the process is using more than 1 GiB.
+//! let _watcher = RssWatcher::new(bin.clone(), 1 << 30, Duration::from_secs(1))?;
+//! # Ok::<_, std::io::Error>(())
+//! ```
+
+use crate::sync::atomic::AtomicUsize;
+use crate::sync::const_fn;
+use crate::Bin;
+use std::io;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Get the resident set size of the process in bytes, if it is supported on this platform.
+#[must_use]
+pub fn rss() -> Option<usize> {
+    memory_stats::memory_stats().map(|stats| stats.physical_mem)
+}
+
+/// Clears a bin whenever the process's RSS is above a limit.
+///
+/// The RSS is polled by a background thread, which stops when this is dropped.
+#[derive(Debug)]
+pub struct RssWatcher {
+    stop: Arc<AtomicBool>,
+}
+
+impl RssWatcher {
+    /// Start clearing the bin whenever the RSS is above `limit` bytes, checking every `interval`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the RSS can't be measured on this platform, or the background thread could not be
+    /// spawned.
+    pub fn new(bin: Arc<Bin<'static>>, limit: usize, interval: Duration) -> io::Result<Self> {
+        if rss().is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "RSS can't be measured on this platform",
+            ));
+        }
+
+        let stop = Arc::new(AtomicBool::new(false));
+        thread::Builder::new()
+            .name("drop-bin-rss".to_owned())
+            .spawn({
+                let stop = stop.clone();
+                move || {
+                    while !stop.load(Ordering::Relaxed) {
+                        if rss().is_some_and(|rss| rss > limit) {
+                            bin.clear();
+                        }
+                        thread::sleep(interval);
+                    }
+                }
+            })?;
+
+        Ok(Self { stop })
+    }
+}
+
+impl Drop for RssWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Checks the RSS every so many adds to a bin.
+#[derive(Debug)]
+pub(crate) struct RssTrigger {
+    limit: usize,
+    every: usize,
+    adds: AtomicUsize,
+}
+
+impl RssTrigger {
+    const_fn! {
+        pub(crate) fn new(limit: usize, every: usize) -> Self {
+            Self {
+                limit,
+                every: if every == 0 { 1 } else { every },
+                adds: AtomicUsize::new(0),
+            }
+        }
+    }
+
+    /// Record an add, returning whether the bin should be cleared.
+    pub(crate) fn should_clear(&self) -> bool {
+        let adds = self.adds.fetch_add(1, Ordering::Relaxed);
+        (adds + 1).is_multiple_of(self.every) && rss().is_some_and(|rss| rss > self.limit)
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::Bin;
+
+    #[test]
+    fn clear_above_rss() {
+        let bin = Bin::builder().clear_above_rss(0, 3).build();
+
+        for _ in 0..2 {
+            bin.add(Box::new(5));
+        }
+        assert_eq!(bin.epoch(), 0);
+
+        bin.add(Box::new(5));
+        assert_eq!(bin.epoch(), 1);
+    }
+}
@@ -0,0 +1,178 @@
+use std::io;
+#[cfg(target_os = "linux")]
+use std::mem;
+#[cfg(windows)]
+use windows_sys::Win32::System::Threading::GetCurrentThread;
+#[cfg(windows)]
+use windows_sys::Win32::System::Threading::SetThreadAffinityMask;
+#[cfg(windows)]
+use windows_sys::Win32::System::Threading::SetThreadPriority;
+#[cfg(windows)]
+use windows_sys::Win32::System::Threading::THREAD_PRIORITY_BELOW_NORMAL;
+#[cfg(windows)]
+use windows_sys::Win32::System::Threading::THREAD_PRIORITY_IDLE;
+#[cfg(windows)]
+use windows_sys::Win32::System::Threading::THREAD_PRIORITY_LOWEST;
+
+/// How the operating system schedules the threads of a [`CleanerPool`](crate::CleanerPool),
+/// for [`CleanerPool::with_scheduling`](crate::CleanerPool::with_scheduling). Enabled by the
+/// `thread-priority` feature.
+///
+/// Running destructors at a low priority on spare cores keeps them from competing with
+/// latency-critical threads. By default, the threads are left as they were spawned.
+///
+/// # Example
+///
+/// ```no_run
+/// use drop_bin::CleanerPool;
+/// use drop_bin::Scheduling;
+/// use drop_bin::ThreadPriority;
+/// use std::time::Duration;
+///
+/// let scheduling = Scheduling::new()
+///     .priority(ThreadPriority::Idle)
+///     .cores([6, 7]);
+/// let pool = CleanerPool::with_scheduling(2, Duration::from_millis(100), &scheduling)?;
+/// # Ok::<_, std::io::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+#[must_use]
+pub struct Scheduling {
+    priority: Option<ThreadPriority>,
+    cores: Vec<usize>,
+}
+
+/// The priority of a thread, from the point of view of the operating system's scheduler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ThreadPriority {
+    /// A little below normal threads: a nice level of 5 on Unix, or
+    /// `THREAD_PRIORITY_BELOW_NORMAL` on Windows.
+    BelowNormal,
+    /// Well below normal threads: a nice level of 10 on Unix, or `THREAD_PRIORITY_LOWEST` on
+    /// Windows.
+    Lowest,
+    /// Only when nothing else wants to run, as far as the platform allows: a nice level of 19 on
+    /// Unix, or `THREAD_PRIORITY_IDLE` on Windows.
+    Idle,
+    /// A platform-specific priority: a nice level on Unix, or a priority to pass to
+    /// `SetThreadPriority` on Windows.
+    Os(i32),
+}
+
+impl Scheduling {
+    /// Create settings that leave the threads as they were spawned.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run the threads at the given priority.
+    pub fn priority(mut self, priority: ThreadPriority) -> Self {
+        self.priority = Some(priority);
+        self
+    }
+
+    /// Only run the threads on the CPU cores with the given indices.
+    pub fn cores(mut self, cores: impl IntoIterator<Item = usize>) -> Self {
+        self.cores = cores.into_iter().collect();
+        self
+    }
+
+    /// Apply the settings to the current thread.
+    pub(crate) fn apply(&self) -> io::Result<()> {
+        if let Some(priority) = self.priority {
+            set_priority(priority)?;
+        }
+        if !self.cores.is_empty() {
+            set_affinity(&self.cores)?;
+        }
+        Ok(())
+    }
+}
+
+/// Set the priority of the current thread.
+#[cfg(target_os = "linux")]
+fn set_priority(priority: ThreadPriority) -> io::Result<()> {
+    let nice = match priority {
+        ThreadPriority::BelowNormal => 5,
+        ThreadPriority::Lowest => 10,
+        ThreadPriority::Idle => 19,
+        ThreadPriority::Os(nice) => nice,
+    };
+    // SAFETY: This has no preconditions.
+    let tid = unsafe { libc::gettid() };
+    // Thread IDs are positive.
+    #[allow(clippy::cast_sign_loss)]
+    let tid = tid as libc::id_t;
+    // On Linux, nice levels belong to threads rather than the whole process.
+    // SAFETY: This has no preconditions.
+    if unsafe { libc::setpriority(libc::PRIO_PROCESS, tid, nice) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Set the priority of the current thread.
+#[cfg(windows)]
+fn set_priority(priority: ThreadPriority) -> io::Result<()> {
+    let priority = match priority {
+        ThreadPriority::BelowNormal => THREAD_PRIORITY_BELOW_NORMAL,
+        ThreadPriority::Lowest => THREAD_PRIORITY_LOWEST,
+        ThreadPriority::Idle => THREAD_PRIORITY_IDLE,
+        ThreadPriority::Os(priority) => priority,
+    };
+    // SAFETY: The pseudo handle always refers to the current thread.
+    if unsafe { SetThreadPriority(GetCurrentThread(), priority) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Fail, on platforms where the priority of a single thread can't be set.
+#[cfg(not(any(target_os = "linux", windows)))]
+fn set_priority(_priority: ThreadPriority) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Only run the current thread on the given cores.
+#[cfg(target_os = "linux")]
+fn set_affinity(cores: &[usize]) -> io::Result<()> {
+    let max = usize::try_from(libc::CPU_SETSIZE).unwrap_or(0);
+    // SAFETY: An empty CPU set is all zeroes.
+    let mut set: libc::cpu_set_t = unsafe { mem::zeroed() };
+    for &core in cores {
+        if core >= max {
+            return Err(io::ErrorKind::InvalidInput.into());
+        }
+        // SAFETY: The core is within the set.
+        unsafe { libc::CPU_SET(core, &mut set) };
+    }
+    // SAFETY: The set is the size that is passed.
+    if unsafe { libc::sched_setaffinity(0, size_of_val(&set), &raw const set) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Only run the current thread on the given cores.
+#[cfg(windows)]
+fn set_affinity(cores: &[usize]) -> io::Result<()> {
+    let mut mask = 0_usize;
+    for &core in cores {
+        let bit = u32::try_from(core)
+            .ok()
+            .and_then(|core| 1_usize.checked_shl(core));
+        mask |= bit.ok_or(io::ErrorKind::InvalidInput)?;
+    }
+    // SAFETY: The pseudo handle always refers to the current thread.
+    if unsafe { SetThreadAffinityMask(GetCurrentThread(), mask) } == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Fail, on platforms where the affinity of a thread can't be set.
+#[cfg(not(any(target_os = "linux", windows)))]
+fn set_affinity(_cores: &[usize]) -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
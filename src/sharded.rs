@@ -0,0 +1,212 @@
+//! A bin with a shard for each thread, for when many threads add to the same bin at once.
+//!
+//! # Example
+//!
+//! ```
+//! static BIN: drop_bin::sharded::Bin = drop_bin::sharded::Bin::new();
+//!
+//! std::thread::scope(|s| {
+//!     for _ in 0..4 {
+//!         s.spawn(|| BIN.add(vec![1, 2, 3]));
+//!     }
+//! });
+//!
+//! BIN.clear();
+//! // Every thread's values have been dropped.
+//! ```
+
+use std::cell::RefCell;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+use std::sync::Weak;
+
+/// The ID of the next bin to be used. IDs start at 1 so that 0 can mean unassigned.
+static NEXT_ID: AtomicUsize = AtomicUsize::new(1);
+
+thread_local! {
+    /// This thread's shards of the bins it has added to, by the bins' IDs.
+    static SHARDS: RefCell<Vec<(usize, Weak<crate::Bin<'static>>)>> =
+        const { RefCell::new(Vec::new()) };
+}
+
+/// A bin that each thread adds to its own shard of, so that adding never contends with other
+/// threads.
+///
+/// Clearing the bin clears every thread's shard. Shards of threads that have exited are
+/// forgotten once they have been cleared.
+#[derive(Default)]
+pub struct Bin {
+    /// The ID of the bin, or 0 if it hasn't been used yet.
+    id: AtomicUsize,
+    /// Every thread's shard of the bin.
+    shards: Mutex<Vec<Arc<crate::Bin<'static>>>>,
+}
+
+impl Bin {
+    /// Create a new bin.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            id: AtomicUsize::new(0),
+            shards: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Add a value to this thread's shard of the bin.
+    ///
+    /// Like [`Bin::add`](crate::Bin::add), this may drop the value immediately, for example if it
+    /// is called while the thread is exiting.
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    pub fn add<T: Send + 'static>(&self, value: T) {
+        if let Some(shard) = self.shard() {
+            shard.add(value);
+        }
+    }
+
+    /// Clear every thread's shard of the bin.
+    ///
+    /// Like [`Bin::clear`](crate::Bin::clear), a shard may not be cleared immediately if its
+    /// thread is currently adding a value to it.
+    pub fn clear(&self) {
+        let shards = self.lock().clone();
+        for shard in &shards {
+            shard.clear();
+        }
+        drop(shards);
+
+        // Shards only have weak references from the threads using them.
+        self.lock().retain(|shard| Arc::weak_count(shard) != 0);
+    }
+
+    /// Get the total size of the bin's shards in bytes.
+    #[must_use]
+    pub fn size(&self) -> usize {
+        self.lock().iter().map(|shard| shard.size()).sum()
+    }
+
+    /// Get this thread's shard of the bin, creating it if it doesn't exist.
+    ///
+    /// The thread-local list is not borrowed while the shard is used, since adding to it can run
+    /// destructors that add to the bin again.
+    fn shard(&self) -> Option<Arc<crate::Bin<'static>>> {
+        let id = self.id();
+        let shards = SHARDS.try_with(|shards| {
+            let mut shards = shards.borrow_mut();
+            let existing = (shards.iter())
+                .find(|&&(shard_id, _)| shard_id == id)
+                .and_then(|(_, shard)| shard.upgrade());
+            if let Some(shard) = existing {
+                return shard;
+            }
+
+            // Forget shards of bins that have been dropped, or that were pruned by a clear.
+            shards.retain(|(_, shard)| shard.strong_count() != 0);
+
+            let shard = Arc::new(crate::Bin::new());
+            self.lock().push(shard.clone());
+            shards.push((id, Arc::downgrade(&shard)));
+            shard
+        });
+        shards.ok()
+    }
+
+    /// Get the ID of the bin, assigning one if it hasn't got one yet.
+    fn id(&self) -> usize {
+        let id = self.id.load(Ordering::Relaxed);
+        if id != 0 {
+            return id;
+        }
+        let new = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        match (self.id).compare_exchange(0, new, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => new,
+            Err(id) => id,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<Arc<crate::Bin<'static>>>> {
+        self.shards.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Debug for Bin {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Bin")
+            .field("shards", &self.lock().len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::sharded::Bin;
+    use crate::test_util::assert_thread_safe;
+    use crate::test_util::CallOnDrop;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::thread;
+
+    #[test]
+    fn sharded() {
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+        static BIN: Bin = Bin::new();
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                thread::spawn(|| {
+                    for _ in 0..10 {
+                        BIN.add(CallOnDrop(|| {
+                            DROPPED.fetch_add(1, SeqCst);
+                        }));
+                    }
+                })
+            })
+            .collect();
+        // Joining waits for the threads' thread-locals to be dropped.
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        BIN.add(CallOnDrop(|| {
+            DROPPED.fetch_add(1, SeqCst);
+        }));
+        assert_eq!(BIN.lock().len(), 5);
+        assert_eq!(DROPPED.load(SeqCst), 0);
+
+        BIN.clear();
+        assert_eq!(DROPPED.load(SeqCst), 41);
+        // Only this thread's shard is left.
+        assert_eq!(BIN.lock().len(), 1);
+    }
+
+    #[test]
+    fn separate_bins() {
+        static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+        let a = Bin::new();
+        let b = Bin::new();
+
+        a.add(5);
+        b.add(6);
+        assert_eq!(a.lock().len(), 1);
+        assert_eq!(b.lock().len(), 1);
+        drop(a);
+
+        b.clear();
+        b.add(CallOnDrop(|| {
+            DROPPED.fetch_add(1, SeqCst);
+        }));
+        drop(b);
+        assert_eq!(DROPPED.load(SeqCst), 1);
+    }
+
+    #[test]
+    fn thread_safe() {
+        assert_thread_safe::<Bin>();
+    }
+}
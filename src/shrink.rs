@@ -0,0 +1,82 @@
+use std::cmp::max;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+
+/// Frees storage that a bin has kept using less of than it has for several clears in a row, set
+/// with [`Builder::shrink_below`](crate::Builder::shrink_below).
+#[derive(Debug)]
+pub(crate) struct Shrinker {
+    utilization: f64,
+    clears: u32,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    /// The number of clears in a row that found the bin underused.
+    low: u32,
+    /// The most bytes any of those clears found in use.
+    peak: usize,
+}
+
+impl Shrinker {
+    pub(crate) const fn new(utilization: f64, clears: u32) -> Self {
+        Self {
+            utilization,
+            clears,
+            state: Mutex::new(State { low: 0, peak: 0 }),
+        }
+    }
+
+    /// Record a clear that found `used` bytes in use out of `capacity`.
+    ///
+    /// Returns the number of bytes of storage to shrink the bin to, if it is time to.
+    pub(crate) fn cleared(&self, used: usize, capacity: usize) -> Option<usize> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        // Precision loss is fine for a ratio.
+        #[allow(clippy::cast_precision_loss)]
+        let underused = (used as f64) < capacity as f64 * self.utilization;
+        if !underused {
+            *state = State { low: 0, peak: 0 };
+            return None;
+        }
+
+        state.low += 1;
+        state.peak = max(state.peak, used);
+        if state.low < self.clears {
+            return None;
+        }
+        let peak = state.peak;
+        *state = State { low: 0, peak: 0 };
+        Some(peak)
+    }
+}
+
+// With the `boxed-storage` feature, bins have no storage to shrink.
+#[cfg(all(test, not(loom), not(feature = "boxed-storage")))]
+mod tests {
+    use crate::Bin;
+
+    #[test]
+    fn shrink_below() {
+        let bin = Bin::builder().shrink_below(0.25, 2).build();
+
+        // A burst leaves plenty of storage behind.
+        for _ in 0..1000 {
+            bin.add(0_u64);
+        }
+        bin.clear();
+        let capacity = bin.capacity();
+
+        bin.add(0_u64);
+        bin.clear();
+        assert_eq!(bin.capacity(), capacity);
+
+        // The second underused clear in a row frees what isn't needed.
+        bin.add(0_u64);
+        bin.clear();
+        assert!(bin.capacity() < capacity);
+        assert_ne!(bin.capacity(), 0);
+    }
+}
@@ -0,0 +1,92 @@
+//! Clearing bins in response to Unix signals, enabled by the `signal` feature.
+//!
+//! This lets operators force a clear on a running process from the shell, for example with
+//! `kill -USR1 <pid>`.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use drop_bin::signal::ClearOnSignal;
+//! use drop_bin::Bin;
+//! use std::sync::Arc;
+//!
+//! let bin = Arc::new(Bin::new());
+//! let _clear_on_signal = ClearOnSignal::new(bin.clone(), signal_hook::consts::SIGUSR1)?;
+//! # Ok::<_, std::io::Error>(())
+//! ```
+
+use crate::Bin;
+use signal_hook::iterator::Handle;
+use signal_hook::iterator::Signals;
+use std::ffi::c_int;
+use std::io;
+use std::sync::Arc;
+use std::thread;
+
+/// Clears a bin every time the process receives a signal.
+///
+/// The signal is handled on a background thread, since destructors can't safely be run inside a
+/// signal handler. The bin stops being cleared when this is dropped.
+#[derive(Debug)]
+pub struct ClearOnSignal {
+    handle: Handle,
+}
+
+impl ClearOnSignal {
+    /// Start clearing the bin whenever the process receives `signal`.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the signal handler could not be registered, or the background thread could not be
+    /// spawned. Signals that can't be handled, such as `SIGKILL`, are also rejected.
+    pub fn new(bin: Arc<Bin<'static>>, signal: c_int) -> io::Result<Self> {
+        let mut signals = Signals::new([signal])?;
+        let handle = signals.handle();
+
+        thread::Builder::new()
+            .name("drop-bin-signal".to_owned())
+            .spawn(move || {
+                for _ in signals.forever() {
+                    bin.clear();
+                }
+            })?;
+
+        Ok(Self { handle })
+    }
+}
+
+impl Drop for ClearOnSignal {
+    fn drop(&mut self) {
+        self.handle.close();
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::signal::ClearOnSignal;
+    use crate::Bin;
+    use signal_hook::consts::SIGUSR1;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn clear_on_signal() {
+        let bin = Arc::new(Bin::new());
+        let clear_on_signal = ClearOnSignal::new(bin.clone(), SIGUSR1).unwrap();
+
+        bin.add(Box::new(5));
+        let epoch = bin.epoch();
+        signal_hook::low_level::raise(SIGUSR1).unwrap();
+
+        for _ in 0..1000 {
+            if bin.cleared_since(epoch) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(bin.cleared_since(epoch));
+
+        drop(clear_on_signal);
+    }
+}
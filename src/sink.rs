@@ -0,0 +1,153 @@
+use crate::Bin;
+use crate::Reservation;
+use futures_sink::Sink;
+use std::convert::Infallible;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::mem::size_of;
+use std::pin::Pin;
+use std::task::Context;
+use std::task::Poll;
+
+/// A [`Sink`] that adds the values sent to it to a bin, so that the bin can be the last stage of a
+/// stream pipeline. Returned by [`Bin::sink`].
+///
+/// If the bin has a [limit](crate::Builder::limit), the sink isn't ready until there is room for
+/// another value, and that room is set aside for the next value sent. This holds back the stream
+/// until the bin is cleared, instead of blocking the thread or applying the bin's
+/// [`Overflow`](crate::Overflow) policy. Room that was set aside but not used is given back when
+/// the sink is dropped.
+pub struct BinSink<'b, 'a> {
+    bin: &'b Bin<'a>,
+    /// The room made for the next value by `poll_ready`.
+    reservation: Option<Reservation<'b>>,
+}
+
+impl<'a> Bin<'a> {
+    /// Get a [`Sink`] that adds values to the bin. Enabled by the `futures` feature.
+    ///
+    /// Any number of sinks can feed the same bin, such as one per stream on a shared
+    /// `Arc<Bin>`.
+    pub fn sink(&self) -> BinSink<'_, 'a> {
+        BinSink {
+            bin: self,
+            reservation: None,
+        }
+    }
+}
+
+impl<'a, T: Send + 'a> Sink<T> for BinSink<'_, 'a> {
+    type Error = Infallible;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        if self.reservation.is_some() {
+            return Poll::Ready(Ok(()));
+        }
+        let bin = self.bin;
+        let Some(limit) = &bin.limit else {
+            return Poll::Ready(Ok(()));
+        };
+
+        let mut reservation = limit.try_reserve(size_of::<T>());
+        if reservation.is_none() {
+            bin.waiters.register(cx.waker());
+            // Check again in case the bin was emptied before we were registered.
+            reservation = limit.try_reserve(size_of::<T>());
+        }
+        if reservation.is_none() {
+            return Poll::Pending;
+        }
+        self.reservation = reservation;
+        Poll::Ready(Ok(()))
+    }
+
+    #[cfg_attr(feature = "debug-types", track_caller)]
+    fn start_send(mut self: Pin<&mut Self>, item: T) -> Result<(), Infallible> {
+        // The room was made by `poll_ready`, so the limit isn't checked again.
+        let reservation = (self.reservation.take()).unwrap_or_else(Reservation::unlimited);
+        if let Some(inner) = self.bin.adding_reserved::<T>(reservation).inner() {
+            inner.add(item);
+        }
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Debug for BinSink<'_, '_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BinSink")
+            .field("reserved", &self.reservation.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::Bin;
+    use crate::Overflow;
+    use futures_sink::Sink;
+    use std::pin::Pin;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::sync::Arc;
+    use std::task::Context;
+    use std::task::Wake;
+    use std::task::Waker;
+
+    struct Flag(AtomicBool);
+    impl Wake for Flag {
+        fn wake(self: Arc<Self>) {
+            self.0.store(true, SeqCst);
+        }
+    }
+
+    #[test]
+    fn backpressure() {
+        let woken = Arc::new(Flag(AtomicBool::new(false)));
+        let waker = Waker::from(woken.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        let bin = Bin::builder().limit(8, Overflow::Block).build();
+        let mut sink = bin.sink();
+        let mut sink = Pin::new(&mut sink);
+        assert!(Sink::<u64>::poll_ready(sink.as_mut(), &mut cx).is_ready());
+        sink.as_mut().start_send(5_u64).unwrap();
+        assert!(Sink::<u64>::poll_ready(sink.as_mut(), &mut cx).is_pending());
+
+        bin.clear();
+        assert!(woken.0.load(SeqCst));
+        assert!(Sink::<u64>::poll_ready(sink.as_mut(), &mut cx).is_ready());
+    }
+
+    #[test]
+    fn reserves_room() {
+        let waker = Waker::from(Arc::new(Flag(AtomicBool::new(false))));
+        let mut cx = Context::from_waker(&waker);
+
+        let bin = Bin::builder().limit(8, Overflow::Block).build();
+        bin.add(1_u8);
+        let mut sink = bin.sink();
+        let mut other = bin.sink();
+
+        // The room is set aside for the sink that was ready, so the other one has to wait and
+        // the send doesn't block.
+        assert!(Sink::<[u8; 4]>::poll_ready(Pin::new(&mut sink), &mut cx).is_ready());
+        assert!(Sink::<[u8; 4]>::poll_ready(Pin::new(&mut other), &mut cx).is_pending());
+        Pin::new(&mut sink).start_send([2_u8; 4]).unwrap();
+        assert_eq!(bin.estimate_clear_cost().items, 2);
+
+        // Room that isn't used is given back when the sink is dropped.
+        bin.clear();
+        assert!(Sink::<[u8; 8]>::poll_ready(Pin::new(&mut other), &mut cx).is_ready());
+        drop(other);
+        assert!(Sink::<[u8; 8]>::poll_ready(Pin::new(&mut sink), &mut cx).is_ready());
+    }
+}
@@ -0,0 +1,258 @@
+use crate::sync::atomic;
+use crate::sync::atomic::AtomicBool;
+use crate::sync::atomic::AtomicUsize;
+use crate::sync::atomic::Ordering;
+use crate::sync::const_fn;
+use crate::sync::thread_local;
+use crate::sync::UnsafeCell;
+use std::cell::Cell;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::ops::Deref;
+use std::ops::DerefMut;
+// Slot indices only spread threads out, and loom's model has a single slot, so they are left
+// out of it.
+use std::sync::atomic::AtomicUsize as StdAtomicUsize;
+
+/// The number of slots that readers are spread over.
+#[cfg(not(loom))]
+const SLOTS: usize = 8;
+/// The number of slots that readers are spread over. Loom's model uses a single slot, since
+/// which slot a thread is given isn't deterministic.
+#[cfg(loom)]
+const SLOTS: usize = 1;
+
+/// The slot to give to the next thread that needs one.
+static NEXT_SLOT: StdAtomicUsize = StdAtomicUsize::new(0);
+
+// Loom's `thread_local!` doesn't support `const` initializers.
+thread_local! {
+    /// The slot this thread counts itself in as a reader, or `usize::MAX` if it hasn't got one.
+    #[allow(clippy::missing_const_for_thread_local)]
+    static SLOT: Cell<usize> = Cell::new(usize::MAX);
+}
+
+/// A lock that can be held by many readers or one writer, where neither waits for the other.
+///
+/// Readers on different threads count themselves in different slots, so that taking a read lock
+/// doesn't contend with readers on other threads. In exchange, taking the write lock has to check
+/// every slot.
+pub(crate) struct SlotLock<T> {
+    /// Whether the lock is write-locked, or a writer is checking whether it can be.
+    writer: AtomicBool,
+    /// The number of readers in each slot.
+    slots: [Slot; SLOTS],
+    value: UnsafeCell<T>,
+}
+
+/// A count of readers, on a cache line of its own.
+#[repr(align(64))]
+struct Slot(AtomicUsize);
+
+unsafe impl<T: Send> Send for SlotLock<T> {}
+unsafe impl<T: Send + Sync> Sync for SlotLock<T> {}
+
+impl<T> SlotLock<T> {
+    const_fn! {
+        pub(crate) fn new(value: T) -> Self {
+            Self {
+                writer: AtomicBool::new(false),
+                slots: empty_slots(),
+                value: UnsafeCell::new(value),
+            }
+        }
+    }
+
+    pub(crate) fn try_read(&self) -> Option<ReadGuard<'_, T>> {
+        let slot = self.slot();
+        slot.0.fetch_add(1, Ordering::Relaxed);
+
+        // Either this sees a writer that is checking the slots, or the writer sees this reader.
+        atomic::fence(Ordering::SeqCst);
+        if self.writer.load(Ordering::Acquire) {
+            slot.0.fetch_sub(1, Ordering::Relaxed);
+            return None;
+        }
+
+        Some(ReadGuard { lock: self, slot })
+    }
+
+    pub(crate) fn try_write(&self) -> Option<WriteGuard<'_, T>> {
+        // A failed attempt leaves the flag to the writer that holds it.
+        if (self.writer)
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            return None;
+        }
+
+        atomic::fence(Ordering::SeqCst);
+        if (self.slots.iter()).any(|slot| slot.0.load(Ordering::Relaxed) != 0) {
+            self.writer.store(false, Ordering::Release);
+            return None;
+        }
+        // Synchronize with the readers that have left.
+        atomic::fence(Ordering::Acquire);
+
+        Some(WriteGuard { lock: self })
+    }
+
+    /// Count the read locks that are currently held.
+    pub(crate) fn readers(&self) -> usize {
+        (self.slots.iter())
+            .map(|slot| slot.0.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    pub(crate) fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+
+    /// Get the slot the current thread counts itself in.
+    fn slot(&self) -> &Slot {
+        let index = SLOT.try_with(|slot| {
+            if slot.get() == usize::MAX {
+                slot.set(NEXT_SLOT.fetch_add(1, Ordering::Relaxed) % SLOTS);
+            }
+            slot.get()
+        });
+        // Threads that are exiting share the first slot.
+        &self.slots[index.unwrap_or(0)]
+    }
+}
+
+/// Create the slots of an unlocked lock.
+#[cfg(not(loom))]
+const fn empty_slots() -> [Slot; SLOTS] {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const EMPTY: Slot = Slot(AtomicUsize::new(0));
+    [EMPTY; SLOTS]
+}
+
+/// Create the slots of an unlocked lock.
+#[cfg(loom)]
+fn empty_slots() -> [Slot; SLOTS] {
+    std::array::from_fn(|_| Slot(AtomicUsize::new(0)))
+}
+
+impl<T: Default> Default for SlotLock<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> Debug for SlotLock<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SlotLock").finish_non_exhaustive()
+    }
+}
+
+pub(crate) struct ReadGuard<'a, T> {
+    lock: &'a SlotLock<T>,
+    slot: &'a Slot,
+}
+
+impl<T> Deref for ReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: We hold a read lock.
+        self.lock.value.with(|value| unsafe { &*value })
+    }
+}
+
+impl<T> Drop for ReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.slot.0.fetch_sub(1, Ordering::Release);
+    }
+}
+
+pub(crate) struct WriteGuard<'a, T> {
+    lock: &'a SlotLock<T>,
+}
+
+impl<T> Deref for WriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: We hold the write lock.
+        self.lock.value.with(|value| unsafe { &*value })
+    }
+}
+
+impl<T> DerefMut for WriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: We hold the write lock.
+        self.lock.value.with_mut(|value| unsafe { &mut *value })
+    }
+}
+
+impl<T> Drop for WriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.lock.writer.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::slot_lock::SlotLock;
+    use std::thread;
+
+    #[test]
+    fn exclusion() {
+        let lock = SlotLock::new(5);
+
+        let read = lock.try_read().unwrap();
+        assert!(lock.try_write().is_none());
+        // A failed write doesn't keep readers out.
+        let read2 = lock.try_read().unwrap();
+        thread::scope(|s| {
+            s.spawn(|| {
+                assert_eq!(*lock.try_read().unwrap(), 5);
+                assert!(lock.try_write().is_none());
+            });
+        });
+        drop((read, read2));
+
+        let mut write = lock.try_write().unwrap();
+        *write = 6;
+        assert!(lock.try_read().is_none());
+        assert!(lock.try_write().is_none());
+        drop(write);
+
+        assert_eq!(*lock.try_read().unwrap(), 6);
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use crate::slot_lock::SlotLock;
+    use loom::sync::Arc;
+    use loom::thread;
+
+    #[test]
+    fn exclusion() {
+        loom::model(|| {
+            let lock = Arc::new(SlotLock::new(0));
+
+            let thread = thread::spawn({
+                let lock = lock.clone();
+                move || {
+                    if let Some(mut write) = lock.try_write() {
+                        *write += 1;
+                    }
+                }
+            });
+            // Loom's cell reports a data race if the write isn't excluded.
+            let read = lock.try_read().map(|read| *read);
+            if let Some(mut write) = lock.try_write() {
+                *write += 1;
+            }
+            thread.join().unwrap();
+
+            let value = *lock.try_read().unwrap();
+            assert!(read.is_none_or(|read| read <= value));
+        });
+    }
+}
@@ -0,0 +1,241 @@
+//! Synchronization primitives.
+//!
+//! When model checking with `--cfg loom`, these are swapped out for versions built on loom's
+//! primitives.
+
+#[cfg(not(loom))]
+pub(crate) use core::hint::spin_loop;
+#[cfg(not(loom))]
+pub(crate) use core::sync::atomic;
+#[cfg(all(not(loom), feature = "std"))]
+pub(crate) use std::sync::Arc;
+#[cfg(all(not(loom), feature = "std"))]
+pub(crate) use std::sync::Mutex;
+#[cfg(all(not(loom), feature = "std"))]
+pub(crate) use std::sync::MutexGuard;
+#[cfg(all(not(loom), feature = "std"))]
+pub(crate) use std::sync::RwLock;
+#[cfg(all(not(loom), feature = "std"))]
+pub(crate) use std::thread::yield_now;
+#[cfg(all(not(loom), feature = "std"))]
+pub(crate) use std::thread_local;
+#[cfg(all(not(loom), feature = "std"))]
+pub(crate) use try_mutex::TryMutex;
+#[cfg(all(not(loom), feature = "std"))]
+pub(crate) use try_mutex::TryMutexGuard;
+
+#[cfg(loom)]
+pub(crate) use loom::hint::spin_loop;
+#[cfg(loom)]
+pub(crate) use loom::sync::atomic;
+#[cfg(loom)]
+pub(crate) use loom::sync::Arc;
+#[cfg(loom)]
+pub(crate) use loom::sync::Mutex;
+#[cfg(loom)]
+pub(crate) use loom::sync::MutexGuard;
+#[cfg(loom)]
+pub(crate) use loom::sync::RwLock;
+#[cfg(loom)]
+pub(crate) use loom::thread::yield_now;
+#[cfg(loom)]
+pub(crate) use loom::thread_local;
+#[cfg(loom)]
+pub(crate) use try_locks::TryMutex;
+#[cfg(loom)]
+pub(crate) use try_locks::TryMutexGuard;
+
+/// Define a function that is `const`, except under loom whose primitives can't be created in
+/// const contexts.
+macro_rules! const_fn {
+    ($(#[$attr:meta])* $vis:vis fn $($rest:tt)*) => {
+        #[cfg(not(loom))]
+        $(#[$attr])*
+        $vis const fn $($rest)*
+
+        #[cfg(loom)]
+        $(#[$attr])*
+        $vis fn $($rest)*
+    };
+}
+pub(crate) use const_fn;
+
+/// Non-atomic access to an atomic through a unique reference.
+///
+/// This papers over loom's atomics not supporting `get_mut`.
+#[cfg_attr(not(feature = "std"), allow(dead_code))]
+pub(crate) trait AtomicMut {
+    type Value: Copy;
+
+    fn load_mut(&mut self) -> Self::Value;
+    fn store_mut(&mut self, value: Self::Value);
+}
+
+macro_rules! impl_atomic_mut {
+    ($(impl$(<$param:ident>)? for $atomic:ty = $value:ty;)*) => {$(
+        impl$(<$param>)? AtomicMut for $atomic {
+            type Value = $value;
+
+            #[cfg(not(loom))]
+            fn load_mut(&mut self) -> Self::Value {
+                *self.get_mut()
+            }
+            #[cfg(not(loom))]
+            fn store_mut(&mut self, value: Self::Value) {
+                *self.get_mut() = value;
+            }
+
+            #[cfg(loom)]
+            fn load_mut(&mut self) -> Self::Value {
+                // SAFETY: We have unique access to the atomic.
+                unsafe { self.unsync_load() }
+            }
+            #[cfg(loom)]
+            fn store_mut(&mut self, value: Self::Value) {
+                self.store(value, atomic::Ordering::Relaxed);
+            }
+        }
+    )*};
+}
+impl_atomic_mut! {
+    impl for atomic::AtomicBool = bool;
+    impl for atomic::AtomicU64 = u64;
+    impl for atomic::AtomicUsize = usize;
+    impl<T> for atomic::AtomicPtr<T> = *mut T;
+}
+
+/// A cell that allows mutation through shared references, whose accesses are checked by loom.
+///
+/// This has the interface of loom's `UnsafeCell`, plus `get_mut` for unique access.
+#[derive(Default)]
+pub(crate) struct UnsafeCell<T> {
+    #[cfg(not(loom))]
+    inner: core::cell::UnsafeCell<T>,
+    #[cfg(loom)]
+    inner: loom::cell::UnsafeCell<T>,
+}
+
+impl<T> UnsafeCell<T> {
+    const_fn! {
+        pub(crate) fn new(value: T) -> Self {
+            Self {
+                #[cfg(not(loom))]
+                inner: core::cell::UnsafeCell::new(value),
+                #[cfg(loom)]
+                inner: loom::cell::UnsafeCell::new(value),
+            }
+        }
+    }
+
+    /// Read through a pointer to the value.
+    #[cfg(not(loom))]
+    pub(crate) fn with<R>(&self, f: impl FnOnce(*const T) -> R) -> R {
+        f(self.inner.get())
+    }
+    /// Read through a pointer to the value.
+    #[cfg(loom)]
+    pub(crate) fn with<R>(&self, f: impl FnOnce(*const T) -> R) -> R {
+        self.inner.with(f)
+    }
+
+    /// Write through a pointer to the value.
+    #[cfg(not(loom))]
+    pub(crate) fn with_mut<R>(&self, f: impl FnOnce(*mut T) -> R) -> R {
+        f(self.inner.get())
+    }
+    /// Write through a pointer to the value.
+    #[cfg(loom)]
+    pub(crate) fn with_mut<R>(&self, f: impl FnOnce(*mut T) -> R) -> R {
+        self.inner.with_mut(f)
+    }
+
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    pub(crate) fn get_mut(&mut self) -> &mut T {
+        // SAFETY: We have unique access to the value.
+        self.with_mut(|value| unsafe { &mut *value })
+    }
+
+    #[cfg_attr(any(not(feature = "std"), feature = "crossbeam"), allow(dead_code))]
+    pub(crate) fn into_inner(self) -> T {
+        self.inner.into_inner()
+    }
+}
+
+/// An implementation of `TryMutex` that uses loom's atomics.
+#[cfg(loom)]
+mod try_locks {
+    use super::atomic;
+    use super::atomic::AtomicBool;
+    use super::UnsafeCell;
+    use std::fmt;
+    use std::fmt::Debug;
+    use std::fmt::Formatter;
+    use std::ops::Deref;
+    use std::ops::DerefMut;
+
+    #[derive(Default)]
+    pub(crate) struct TryMutex<T> {
+        locked: AtomicBool,
+        value: UnsafeCell<T>,
+    }
+
+    unsafe impl<T: Send> Send for TryMutex<T> {}
+    unsafe impl<T: Send> Sync for TryMutex<T> {}
+
+    impl<T> TryMutex<T> {
+        pub(crate) fn new(value: T) -> Self {
+            Self {
+                locked: AtomicBool::new(false),
+                value: UnsafeCell::new(value),
+            }
+        }
+
+        pub(crate) fn try_lock(&self) -> Option<TryMutexGuard<'_, T>> {
+            self.locked
+                .compare_exchange(
+                    false,
+                    true,
+                    atomic::Ordering::Acquire,
+                    atomic::Ordering::Relaxed,
+                )
+                .ok()?;
+            Some(TryMutexGuard { lock: self })
+        }
+
+        pub(crate) fn get_mut(&mut self) -> &mut T {
+            self.value.get_mut()
+        }
+    }
+
+    impl<T> Debug for TryMutex<T> {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            f.debug_struct("TryMutex").finish_non_exhaustive()
+        }
+    }
+
+    pub(crate) struct TryMutexGuard<'a, T> {
+        lock: &'a TryMutex<T>,
+    }
+
+    impl<T> Deref for TryMutexGuard<'_, T> {
+        type Target = T;
+
+        fn deref(&self) -> &Self::Target {
+            // SAFETY: We hold the lock.
+            self.lock.value.with(|value| unsafe { &*value })
+        }
+    }
+
+    impl<T> DerefMut for TryMutexGuard<'_, T> {
+        fn deref_mut(&mut self) -> &mut Self::Target {
+            // SAFETY: We hold the lock.
+            self.lock.value.with_mut(|value| unsafe { &mut *value })
+        }
+    }
+
+    impl<T> Drop for TryMutexGuard<'_, T> {
+        fn drop(&mut self) {
+            self.lock.locked.store(false, atomic::Ordering::Release);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::ptr;
+use std::sync::Mutex;
+use std::sync::PoisonError;
+
+/// All the tag names that have been created.
+static NAMES: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+
+/// An interned name that values in a bin can be grouped under.
+///
+/// Tags are compared by address, so once created they are as cheap to copy and compare as a
+/// pointer. Creating a tag looks its name up in a global table, so it is best done once up front.
+///
+/// # Example
+///
+/// ```
+/// use drop_bin::Tag;
+///
+/// let bin = drop_bin::Bin::new();
+/// let textures = Tag::new("textures");
+///
+/// bin.add_tagged(vec![0_u8; 1024], textures);
+/// bin.add("Hello World!".to_owned());
+///
+/// bin.clear_tag(textures);
+/// // Only the texture data has been dropped.
+/// ```
+#[derive(Clone, Copy)]
+pub struct Tag(&'static str);
+
+impl Tag {
+    /// Get the tag with the given name, creating it if it does not exist yet.
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        let mut names = NAMES.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(&name) = names.get(name) {
+            return Self(name);
+        }
+        let name: &'static str = Box::leak(name.into());
+        names.insert(name);
+        Self(name)
+    }
+
+    /// Get the name of the tag.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        self.0
+    }
+}
+
+impl From<&str> for Tag {
+    fn from(name: &str) -> Self {
+        Self::new(name)
+    }
+}
+
+impl PartialEq for Tag {
+    fn eq(&self, other: &Self) -> bool {
+        ptr::eq(self.0, other.0)
+    }
+}
+
+impl Eq for Tag {}
+
+impl Hash for Tag {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.as_ptr().hash(state);
+    }
+}
+
+impl Debug for Tag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Tag").field(&self.0).finish()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::Tag;
+
+    #[test]
+    fn interned() {
+        let a = Tag::new("interned");
+        let b = Tag::from(&*String::from("interned"));
+        assert_eq!(a, b);
+        assert!(std::ptr::eq(a.name(), b.name()));
+        assert_ne!(a, Tag::new("other"));
+    }
+}
@@ -0,0 +1,53 @@
+//! Built-in hooks for [`Builder::trim_after_clear`](crate::Builder::trim_after_clear) that return
+//! freed memory from the allocator to the operating system.
+//!
+//! # Example
+//!
+//! ```
+//! # #[cfg(all(feature = "malloc-trim", target_os = "linux", target_env = "gnu"))]
+//! let bin = drop_bin::Bin::builder()
+//!     .trim_after_clear(16 * 1024 * 1024, drop_bin::trim::malloc_trim)
+//!     .build();
+//! ```
+
+/// Release free memory at the top of glibc's heap with `malloc_trim`. Enabled by the
+/// `malloc-trim` feature.
+#[cfg(all(feature = "malloc-trim", target_os = "linux", target_env = "gnu"))]
+pub fn malloc_trim() {
+    // SAFETY: This has no preconditions.
+    unsafe { libc::malloc_trim(0) };
+}
+
+/// Purge unused dirty pages from every jemalloc arena. Enabled by the `jemalloc` feature.
+///
+/// This only has an effect if jemalloc is the global allocator, for example through
+/// `tikv-jemallocator`.
+#[cfg(feature = "jemalloc")]
+pub fn jemalloc_purge() {
+    // `MALLCTL_ARENAS_ALL`
+    const NAME: &[u8] = b"arena.4096.purge\0";
+
+    // SAFETY: The name is nul-terminated, and purging takes no input or output.
+    unsafe {
+        tikv_jemalloc_sys::mallctl(
+            NAME.as_ptr().cast(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            0,
+        )
+    };
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    #[test]
+    #[cfg(all(feature = "malloc-trim", target_os = "linux", target_env = "gnu"))]
+    fn malloc_trim() {
+        let bin = crate::Bin::builder()
+            .trim_after_clear(0, crate::trim::malloc_trim)
+            .build();
+        bin.add(vec![0_u8; 1024 * 1024]);
+        bin.clear();
+    }
+}
@@ -0,0 +1,178 @@
+use crate::weak_bins::WeakBins;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::OnceLock;
+use std::sync::PoisonError;
+use std::thread;
+use std::thread::Thread;
+use std::time::Duration;
+use std::time::Instant;
+
+/// The shortest time the watchdog thread sleeps for between checks.
+const MIN_INTERVAL: Duration = Duration::from_millis(1);
+
+/// A destructor that has been running for longer than the limit set with
+/// [`Builder::watchdog`](crate::Builder::watchdog), which is passed to the watchdog's callback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct HungDestructor {
+    /// The name of the value's type, which is only known with the `debug-types` feature.
+    pub type_name: Option<&'static str>,
+    /// How long the destructor had been running for when the watchdog noticed it.
+    pub running: Duration,
+}
+
+/// The settings of a bin's watchdog.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Watchdog {
+    pub(crate) limit: Duration,
+    pub(crate) on_hung: fn(&HungDestructor),
+}
+
+/// The clears being watched.
+static WATCHED: WeakBins<(), Slot> = WeakBins::new();
+/// The thread that checks on the watched clears, or `None` if it couldn't be spawned.
+static THREAD: OnceLock<Option<Thread>> = OnceLock::new();
+
+/// The destructor that a clear is running, if any.
+#[derive(Debug)]
+struct Slot {
+    watchdog: Watchdog,
+    running: Mutex<Option<Running>>,
+}
+
+#[derive(Debug)]
+struct Running {
+    since: Instant,
+    type_name: Option<&'static str>,
+    /// Whether the callback has been called for this destructor.
+    reported: bool,
+}
+
+impl Slot {
+    /// Call the callback if the running destructor has just gone over the limit.
+    fn check(&self) {
+        let mut guard = self.running.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some(running) = guard.as_mut().filter(|running| !running.reported) else {
+            return;
+        };
+        let elapsed = running.since.elapsed();
+        if elapsed < self.watchdog.limit {
+            return;
+        }
+        running.reported = true;
+        let hung = HungDestructor {
+            type_name: running.type_name,
+            running: elapsed,
+        };
+        drop(guard);
+        (self.watchdog.on_hung)(&hung);
+    }
+}
+
+/// Watches the destructors run by a clear until it is dropped.
+#[derive(Debug)]
+pub(crate) struct Watch(Arc<Slot>);
+
+impl Watch {
+    pub(crate) fn new(watchdog: Watchdog) -> Self {
+        let slot = Arc::new(Slot {
+            watchdog,
+            running: Mutex::new(None),
+        });
+        WATCHED.register(&slot, ());
+        if let Some(thread) = THREAD.get_or_init(spawn) {
+            thread.unpark();
+        }
+        Self(slot)
+    }
+
+    /// Time a destructor of a value of the given type until the returned guard is dropped.
+    pub(crate) fn time(&self, type_name: Option<&'static str>) -> Timer<'_> {
+        *self
+            .0
+            .running
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(Running {
+            since: Instant::now(),
+            type_name,
+            reported: false,
+        });
+        Timer(&self.0)
+    }
+}
+
+/// Stops timing a destructor when dropped, even if it panics.
+#[derive(Debug)]
+pub(crate) struct Timer<'w>(&'w Slot);
+
+impl Drop for Timer<'_> {
+    fn drop(&mut self) {
+        *self
+            .0
+            .running
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = None;
+    }
+}
+
+fn spawn() -> Option<Thread> {
+    let handle = thread::Builder::new()
+        .name("drop-bin-watchdog".to_owned())
+        .spawn(run)
+        .ok()?;
+    Some(handle.thread().clone())
+}
+
+/// Check on the watched clears for as long as the process runs, sleeping while there are none.
+fn run() {
+    loop {
+        // Clears that have finished are forgotten.
+        let slots = WATCHED.alive();
+
+        let Some(limit) = slots.iter().map(|(slot, ())| slot.watchdog.limit).min() else {
+            thread::park();
+            continue;
+        };
+        for (slot, ()) in &slots {
+            slot.check();
+        }
+        drop(slots);
+        thread::sleep((limit / 2).max(MIN_INTERVAL));
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::test_util::CallOnDrop;
+    use crate::Bin;
+    use crate::HungDestructor;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::thread;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    static HUNG: AtomicUsize = AtomicUsize::new(0);
+
+    #[test]
+    fn notices_hung_destructor() {
+        fn on_hung(hung: &HungDestructor) {
+            assert!(hung.running >= Duration::from_millis(10));
+            HUNG.fetch_add(1, SeqCst);
+        }
+        let bin = Bin::builder()
+            .watchdog(Duration::from_millis(10), on_hung)
+            .build();
+
+        bin.add(5_u8);
+        bin.add(CallOnDrop(|| {
+            let start = Instant::now();
+            while HUNG.load(SeqCst) == 0 && start.elapsed() < Duration::from_secs(10) {
+                thread::sleep(Duration::from_millis(1));
+            }
+        }));
+        bin.clear();
+        assert_eq!(HUNG.load(SeqCst), 1);
+    }
+}
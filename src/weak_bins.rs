@@ -0,0 +1,50 @@
+use crate::Bin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+use std::sync::PoisonError;
+use std::sync::Weak;
+
+/// A list of bins that doesn't keep them alive, each with data of its own.
+///
+/// A bin stays registered until it is dropped. Bins that have been dropped are forgotten the next
+/// time the list is looked through.
+pub(crate) struct WeakBins<D = (), T = Bin<'static>> {
+    bins: Mutex<Vec<(Weak<T>, D)>>,
+}
+
+impl<D, T> WeakBins<D, T> {
+    pub(crate) const fn new() -> Self {
+        Self {
+            bins: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub(crate) fn register(&self, bin: &Arc<T>, data: D) {
+        self.lock().push((Arc::downgrade(bin), data));
+    }
+
+    /// Get the bins that are still alive, in the order they were registered, with their data.
+    ///
+    /// Destructors may register bins, so the list isn't locked while the bins are used: dropping
+    /// the last reference to a bin drops its values.
+    pub(crate) fn alive(&self) -> Vec<(Arc<T>, D)>
+    where
+        D: Clone,
+    {
+        let mut bins = self.lock();
+        bins.retain(|(bin, _)| bin.strong_count() != 0);
+        (bins.iter())
+            .filter_map(|(bin, data)| Some((bin.upgrade()?, data.clone())))
+            .collect()
+    }
+
+    /// Lock the list, to change the bins' data or take bins out of it.
+    pub(crate) fn lock(&self) -> MutexGuard<'_, Vec<(Weak<T>, D)>> {
+        self.bins.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.lock().len()
+    }
+}
@@ -0,0 +1,29 @@
+//! Overwriting the memory that values were stored in once they have been dropped, enabled by the
+//! `zeroize` feature.
+//!
+//! Without the feature this does nothing.
+
+#[cfg(feature = "zeroize")]
+use core::sync::atomic;
+#[cfg(feature = "zeroize")]
+use core::sync::atomic::Ordering;
+
+/// Overwrite `len` bytes starting at `ptr` with zeros, in a way that isn't optimized out even
+/// though the bytes are never read again.
+///
+/// # Safety
+///
+/// The bytes must be valid for writes.
+#[cfg_attr(not(feature = "zeroize"), allow(unused_variables))]
+pub(crate) unsafe fn zero<T>(ptr: *mut T, len: usize) {
+    #[cfg(feature = "zeroize")]
+    {
+        let ptr = ptr.cast::<u8>();
+        for i in 0..len {
+            // SAFETY: Upheld by the caller.
+            unsafe { ptr.add(i).write_volatile(0) };
+        }
+        // Keep later accesses from being reordered before the writes.
+        atomic::compiler_fence(Ordering::SeqCst);
+    }
+}